@@ -31,6 +31,138 @@ fn count_points(curve: &EllipticCurve, q: i32) -> i32 {
     count
 }
 
+// Frobenius trace t with #E(Fq) = q + 1 - t
+fn trace(curve: &EllipticCurve, q: i32) -> i32 {
+    q + 1 - count_points(curve, q)
+}
+
+fn isqrt(n: i32) -> i32 {
+    (n as f64).sqrt().round() as i32
+}
+
+// Orders of the twists of E, labelled by their degree. Every curve has the quadratic twist
+// of order q + 1 + t. Writing 4q = t^2 + D f^2, the twists of a j = 0 curve (D = 3) have
+// traces -t, (-t -+ 3f)/2 (cubic) and (t +- 3f)/2 (sextic) when q = 1 mod 3, and those of a
+// j = 1728 curve (D = 4) have traces -t and +-2f (quartic) when q = 1 mod 4.
+fn twist_orders(curve: &EllipticCurve, q: i32) -> Vec<(u32, i32)> {
+    let t = trace(curve, q);
+    let mut orders = vec![(2, q + 1 + t)];
+
+    if curve.a % q == 0 && q % 3 == 1 {
+        let f = isqrt((4 * q - t * t) / 3);
+        assert_eq!(t * t + 3 * f * f, 4 * q);
+        for trace in [(-t - 3 * f) / 2, (-t + 3 * f) / 2] {
+            orders.push((3, q + 1 - trace));
+        }
+        for trace in [(t + 3 * f) / 2, (t - 3 * f) / 2] {
+            orders.push((6, q + 1 - trace));
+        }
+    }
+
+    if curve.b % q == 0 && q % 4 == 1 {
+        let f = isqrt((4 * q - t * t) / 4);
+        assert_eq!(t * t + 4 * f * f, 4 * q);
+        for trace in [2 * f, -2 * f] {
+            orders.push((4, q + 1 - trace));
+        }
+    }
+
+    orders
+}
+
+// Prime factors of n with multiplicity, by trial division
+fn factor(mut n: i32) -> Vec<i32> {
+    let mut factors = Vec::new();
+    let mut p = 2;
+    while p * p <= n {
+        while n % p == 0 {
+            factors.push(p);
+            n /= p;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+// None for the trivial group, which has no prime subgroup at all
+fn largest_prime_subgroup(order: i32) -> Option<i32> {
+    factor(order).last().copied()
+}
+
+fn describe_subgroup(r: Option<i32>) -> String {
+    r.map_or("none".to_string(), |r| r.to_string())
+}
+
+// Reports the largest prime subgroup of E and of each of its twists. An x-only ladder never
+// checks which of E or its quadratic twist an x-coordinate belongs to, so a twist with a
+// smaller prime subgroup than E leaks the secret scalar more cheaply; we flag those as weak.
+// A trivial group counts as weak. Returns whether all twists are at least as strong as E.
+fn twist_security_report(curve: &EllipticCurve, q: i32) -> bool {
+    let order = count_points(curve, q);
+    let r = largest_prime_subgroup(order);
+    println!(
+        "y^2 = x^3 + {}x + {} over F{}: #E = {} = {:?}, largest prime subgroup {}",
+        curve.a,
+        curve.b,
+        q,
+        order,
+        factor(order),
+        describe_subgroup(r)
+    );
+
+    let mut secure = r.is_some();
+    for (degree, twist_order) in twist_orders(curve, q) {
+        let twist_r = largest_prime_subgroup(twist_order);
+        let weak = match (twist_r, r) {
+            (Some(twist_r), Some(r)) => twist_r < r,
+            (None, _) => true,
+            (Some(_), None) => false,
+        };
+        secure &= !weak;
+        println!(
+            "  degree {} twist: order {} = {:?}, largest prime subgroup {}{}",
+            degree,
+            twist_order,
+            factor(twist_order),
+            describe_subgroup(twist_r),
+            if weak { " (weak)" } else { "" }
+        );
+    }
+    secure
+}
+
+// Checks the twist orders against point counts of the twists themselves: the quadratic
+// twist y^2 = x^3 + ad^2x + bd^3 for a non-square d and, for j = 0 and j = 1728, all the
+// curves y^2 = x^3 + b' and y^2 = x^3 + a'x, which run over every twist
+fn check_twist_orders(curve: &EllipticCurve, q: i32) {
+    let orders = twist_orders(curve, q);
+
+    let d = (2..q).find(|d| (0..q).all(|y| (y * y) % q != *d)).unwrap();
+    let quadratic = EllipticCurve {
+        a: curve.a * d % q * d % q,
+        b: curve.b * d % q * d % q * d % q,
+    };
+    assert_eq!(count_points(&quadratic, q), orders[0].1);
+
+    let mut expected: HashSet<i32> = orders.iter().map(|(_, order)| *order).collect();
+    expected.insert(count_points(curve, q));
+    let family: HashSet<i32> = if curve.a % q == 0 && orders.len() > 1 {
+        (1..q)
+            .map(|b| count_points(&EllipticCurve { a: 0, b }, q))
+            .collect()
+    } else if curve.b % q == 0 && orders.len() > 1 {
+        (1..q)
+            .map(|a| count_points(&EllipticCurve { a, b: 0 }, q))
+            .collect()
+    } else {
+        return;
+    };
+    assert_eq!(family, expected);
+}
+
 pub fn run() {
     let q = 23;
     // [q + 1 − 2√q, q+1 + 2√q]=> Hasse bound
@@ -57,4 +189,33 @@ pub fn run() {
 
     println!("Curves: {:?}", curves);
     println!("Orders: {:?}", orders);
+
+    // Keep only the curves whose twists are no weaker than the curve itself
+    let twist_secure: Vec<&EllipticCurve> = curves
+        .iter()
+        .filter(|curve| {
+            check_twist_orders(curve, q);
+            twist_security_report(curve, q)
+        })
+        .collect();
+    println!("Twist-secure curves: {:?}", twist_secure);
+
+    // j = 0 over F103 (q = 1 mod 3) with its quadratic, cubic and sextic twists, and
+    // j = 1728 over F101 (q = 1 mod 4) with its quadratic and quartic twists
+    for (curve, q) in [
+        (EllipticCurve { a: 0, b: 72 }, 103),
+        (EllipticCurve { a: 3, b: 0 }, 101),
+    ] {
+        check_twist_orders(&curve, q);
+        let secure = twist_security_report(&curve, q);
+        println!("Twist-secure: {}", secure);
+    }
+    let orders = twist_orders(&EllipticCurve { a: 0, b: 72 }, 103);
+    assert_eq!(orders, vec![(2, 124), (3, 117), (3, 111), (6, 91), (6, 97)]);
+
+    // y^2 = x^3 + 2x + 2 over F3 has no affine points, so E = {O} has no prime subgroup
+    let trivial = EllipticCurve { a: 2, b: 2 };
+    assert_eq!(count_points(&trivial, 3), 1);
+    assert_eq!(largest_prime_subgroup(1), None);
+    assert!(!twist_security_report(&trivial, 3));
 }
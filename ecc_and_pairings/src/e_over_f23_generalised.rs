@@ -1,3 +1,5 @@
+use crate::error::Error;
+use crate::sec1;
 use num_bigint::BigInt;
 use num_traits::Euclid;
 use num_traits::{One, Zero};
@@ -25,16 +27,16 @@ impl FiniteField {
         (a * b).rem_euclid(&self.p)
     }
 
-    fn inv(&self, a: &BigInt) -> Option<BigInt> {
+    fn inv(&self, a: &BigInt) -> Result<BigInt, Error> {
         let (g, x, _) = extended_gcd(a, &self.p);
         if g.is_one() {
-            Some(x.rem_euclid(&self.p))
+            Ok(x.rem_euclid(&self.p))
         } else {
-            None
+            Err(Error::NotInvertible)
         }
     }
 
-    fn div(&self, a: &BigInt, b: &BigInt) -> Option<BigInt> {
+    fn div(&self, a: &BigInt, b: &BigInt) -> Result<BigInt, Error> {
         self.inv(b).map(|b_inv| self.mul(a, &b_inv))
     }
 
@@ -64,20 +66,73 @@ struct EllipticCurve {
     b: BigInt,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Point {
-    x: BigInt,
-    y: BigInt,
-}
+type Point = crate::point::Point<BigInt>;
 
 impl EllipticCurve {
     fn new(field: FiniteField, a: BigInt, b: BigInt) -> Self {
         EllipticCurve { field, a, b }
     }
 
-    fn add(&self, p: &Point, q: &Point) -> Point {
-        if p == q {
-            return self.double(p);
+    // new(), refusing 4a^3 + 27b^2 = 0
+    fn try_new(field: FiniteField, a: BigInt, b: BigInt) -> Result<Self, Error> {
+        let f = &field;
+        let a3 = f.mul(&f.mul(&a, &a), &a);
+        let b2 = f.mul(&b, &b);
+        let sum = f.add(
+            &f.mul(&BigInt::from(4), &a3),
+            &f.mul(&BigInt::from(27), &b2),
+        );
+        if sum.is_zero() {
+            return Err(Error::SingularCurve);
+        }
+        Ok(EllipticCurve::new(field, a, b))
+    }
+
+    fn is_on_curve(&self, p: &Point) -> bool {
+        let Some((x, y)) = p.coordinates() else {
+            return true;
+        };
+        let f = &self.field;
+        let x3 = f.mul(&f.mul(x, x), x);
+        f.mul(y, y) == f.add(&f.add(&x3, &f.mul(&self.a, x)), &self.b)
+    }
+
+    // The affine point (x, y), if it satisfies the curve equation
+    fn point(&self, x: BigInt, y: BigInt) -> Result<Point, Error> {
+        let p = Point::new(x.rem_euclid(&self.field.p), y.rem_euclid(&self.field.p));
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
+    // SEC 1 encodings; over F23 a coordinate takes one byte
+    fn encode_point(&self, p: &Point, compressed: bool) -> Vec<u8> {
+        sec1::encode_point(p, &self.field.p, compressed)
+    }
+
+    fn decode_point(&self, bytes: &[u8]) -> Result<Point, Error> {
+        let f = &self.field;
+        let rhs = |x: &BigInt| f.add(&f.add(&f.mul(&f.mul(x, x), x), &f.mul(&self.a, x)), &self.b);
+        match sec1::decode_point(bytes, &f.p, rhs)? {
+            Point::Infinity => Ok(Point::Infinity),
+            Point::Affine { x, y } => self.point(x, y),
+        }
+    }
+
+    fn add(&self, p: &Point, q: &Point) -> Result<Point, Error> {
+        let ((px, py), (qx, qy)) = match (p.coordinates(), q.coordinates()) {
+            (None, _) => return Ok(q.clone()),
+            (_, None) => return Ok(p.clone()),
+            (Some(p), Some(q)) => (p, q),
+        };
+        if px == qx {
+            // Either P = Q, or P = -Q and the line is vertical
+            return if *py == self.field.neg(qy) {
+                Ok(Point::Infinity)
+            } else {
+                self.double(p)
+            };
         }
 
         // y = lambda*x + v
@@ -85,45 +140,42 @@ impl EllipticCurve {
         // lambda = Yq - Yp / Xq-Xp
         let lambda = self
             .field
-            .div(&self.field.sub(&q.y, &p.y), &self.field.sub(&q.x, &p.x))
-            .unwrap();
+            .div(&self.field.sub(qy, py), &self.field.sub(qx, px))?;
 
         // v = Yp - lambda*Xp
-        let nu = self.field.sub(&p.y, &self.field.mul(&lambda, &p.x));
+        let nu = self.field.sub(py, &self.field.mul(&lambda, px));
 
         // (Xr, Yr ) = (lambda^2 - Xp - Xq, - (lambda * Xr +v))
-        let x = self.field.sub(
-            &self.field.sub(&self.field.mul(&lambda, &lambda), &p.x),
-            &q.x,
-        );
+        let x = self
+            .field
+            .sub(&self.field.sub(&self.field.mul(&lambda, &lambda), px), qx);
         let y = self
             .field
             .neg(&self.field.add(&self.field.mul(&lambda, &x), &nu));
 
         println!("lambda = {:?}, nu = {:?}", lambda, nu);
 
-        Point { x, y }
+        Ok(Point::new(x, y))
     }
 
-    fn double(&self, p: &Point) -> Point {
-        let lambda = self
-            .field
-            .div(
-                &self.field.add(
-                    &self
-                        .field
-                        .mul(&BigInt::from(3), &self.field.mul(&p.x, &p.x)),
-                    &self.a,
-                ),
-                &self.field.mul(&BigInt::from(2), &p.y),
-            )
-            .unwrap();
-
-        let nu = self.field.sub(&p.y, &self.field.mul(&lambda, &p.x));
+    fn double(&self, p: &Point) -> Result<Point, Error> {
+        // The tangent at O, or at a point of order 2, is vertical
+        let Some((px, py)) = p.coordinates().filter(|(_, y)| !y.is_zero()) else {
+            return Ok(Point::Infinity);
+        };
+        let lambda = self.field.div(
+            &self.field.add(
+                &self.field.mul(&BigInt::from(3), &self.field.mul(px, px)),
+                &self.a,
+            ),
+            &self.field.mul(&BigInt::from(2), py),
+        )?;
+
+        let nu = self.field.sub(py, &self.field.mul(&lambda, px));
 
         let x = self.field.sub(
             &self.field.mul(&lambda, &lambda),
-            &self.field.mul(&BigInt::from(2), &p.x),
+            &self.field.mul(&BigInt::from(2), px),
         );
 
         let y = self
@@ -132,7 +184,25 @@ impl EllipticCurve {
 
         println!("lambda = {:?}, nu = {:?}", lambda, nu);
 
-        Point { x, y }
+        Ok(Point::new(x, y))
+    }
+
+    fn negate(&self, p: &Point) -> Point {
+        match p {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x.clone(), self.field.neg(y)),
+        }
+    }
+
+    fn scalar_mul(&self, k: u64, p: &Point) -> Result<Point, Error> {
+        let mut result = Point::Infinity;
+        for i in (0..u64::BITS - k.leading_zeros()).rev() {
+            result = self.double(&result)?;
+            if (k >> i) & 1 == 1 {
+                result = self.add(&result, p)?;
+            }
+        }
+        Ok(result)
     }
 }
 
@@ -140,20 +210,62 @@ pub fn run() {
     let fq = FiniteField::new(BigInt::from(23));
     let a = BigInt::from(5);
     let b = BigInt::from(7);
-    let e = EllipticCurve::new(fq.clone(), a, b);
-
-    let p = Point {
-        x: BigInt::from(2),
-        y: BigInt::from(5),
-    };
-    let q = Point {
-        x: BigInt::from(12),
-        y: BigInt::from(1),
-    };
-
-    let r = e.add(&p, &q);
-    println!("P + Q = ({:?}, {:?})", r.x, r.y);
-
-    let s = e.double(&p);
-    println!("2P = ({:?}, {:?})", s.x, s.y);
+    let e = EllipticCurve::try_new(fq.clone(), a, b).unwrap();
+
+    let p = e.point(BigInt::from(2), BigInt::from(5)).unwrap();
+    let q = e.point(BigInt::from(12), BigInt::from(1)).unwrap();
+
+    let r = e.add(&p, &q).unwrap();
+    println!("P + Q = {}", r);
+
+    let s = e.double(&p).unwrap();
+    println!("2P = {}", s);
+    assert_eq!(e.scalar_mul(2, &p), Ok(s.clone()));
+
+    // O is the identity and P + (-P) = O rather than some affine point
+    assert_eq!(e.add(&p, &Point::Infinity), Ok(p.clone()));
+    assert_eq!(e.add(&p, &e.negate(&p)), Ok(Point::Infinity));
+    assert_eq!(e.scalar_mul(0, &p), Ok(Point::Infinity));
+    println!("P + (-P) = {}", e.add(&p, &e.negate(&p)).unwrap());
+
+    // Sums stay on the curve; (2, 6) and y^2 = x^3 - 3x + 2 are rejected
+    assert!(e.is_on_curve(&r) && e.is_on_curve(&s));
+    assert_eq!(
+        e.point(BigInt::from(2), BigInt::from(6)),
+        Err(Error::NotOnCurve)
+    );
+    assert!(EllipticCurve::try_new(fq, BigInt::from(-3), BigInt::from(2)).is_err());
+
+    // Every point of E(F23) survives both encodings, and P + Q travels as 3 bytes
+    let mut count = 1;
+    for x in 0..23 {
+        for y in 0..23 {
+            if let Ok(point) = e.point(BigInt::from(x), BigInt::from(y)) {
+                for compressed in [false, true] {
+                    let bytes = e.encode_point(&point, compressed);
+                    assert_eq!(e.decode_point(&bytes), Ok(point.clone()));
+                }
+                count += 1;
+            }
+        }
+    }
+    assert_eq!(e.decode_point(&[0x00]), Ok(Point::Infinity));
+    // -1 and 28 encode as their residues mod 23
+    let p23 = BigInt::from(23);
+    assert_eq!(sec1::encode_field_element(&BigInt::from(-1), &p23), [22]);
+    assert_eq!(sec1::encode_field_element(&BigInt::from(28), &p23), [5]);
+    println!(
+        "#E(F23) = {}, P + Q = {:02x?}",
+        count,
+        e.encode_point(&r, true)
+    );
+
+    // On y^2 = x^3 + 1 over Z/25 the chord through (0, 1) and (5, 1) has slope 0 / 5, and 5
+    // has no inverse
+    let z25 = FiniteField::new(BigInt::from(25));
+    let e25 = EllipticCurve::new(z25, BigInt::zero(), BigInt::one());
+    let p = e25.point(BigInt::zero(), BigInt::one()).unwrap();
+    let q = e25.point(BigInt::from(5), BigInt::one()).unwrap();
+    assert_eq!(e25.add(&p, &q), Err(Error::NotInvertible));
+    println!("Adding over Z/25: {}", e25.add(&p, &q).unwrap_err());
 }
@@ -1,4 +1,7 @@
+use crate::error::Error;
+use num_integer::Integer;
 use std::{
+    fmt,
     ops::{Add, Mul, Neg, Sub},
     vec,
 };
@@ -16,6 +19,29 @@ impl Fq {
             q,
         }
     }
+
+    pub fn inv(self) -> Result<Fq, Error> {
+        inverse_mod(self.value, self.q)
+    }
+
+    pub fn pow(self, mut exp: u64) -> Fq {
+        let mut base = self;
+        let mut result = Fq::new(1, self.q);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+impl fmt::Display for Fq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
 }
 
 impl Add for Fq {
@@ -82,12 +108,9 @@ impl Sub for Polynomial {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Point {
-    pub x: Fq,
-    pub y: Fq,
-}
+pub type Point = crate::point::Point<Fq>;
 
+#[derive(Clone, Debug)]
 pub struct EllipticCurve {
     pub a: Fq,
     pub b: Fq,
@@ -99,38 +122,245 @@ impl EllipticCurve {
         EllipticCurve { a, b, q }
     }
 
-    pub fn add(&self, p1: &Point, p2: &Point) -> Point {
-        if p1.x == p2.x && p1.y == p2.y {
+    // new(), refusing composite q and singular curves
+    pub fn try_new(a: Fq, b: Fq, q: u8) -> Result<Self, Error> {
+        if !is_prime(q) {
+            return Err(Error::CompositeModulus);
+        }
+        let curve = EllipticCurve::new(a, b, q);
+        if curve.discriminant().value == 0 {
+            return Err(Error::SingularCurve);
+        }
+        Ok(curve)
+    }
+
+    pub fn is_on_curve(&self, p: &Point) -> bool {
+        match *p {
+            Point::Infinity => true,
+            Point::Affine { x, y } => y * y == x * x * x + self.a * x + self.b,
+        }
+    }
+
+    // The affine point (x, y), if it satisfies the curve equation
+    pub fn point(&self, x: Fq, y: Fq) -> Result<Point, Error> {
+        let p = Point::new(x, y);
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
+    // point(), and also [n]P = O so that P lies in the subgroup of order n
+    pub fn subgroup_point(&self, x: Fq, y: Fq, n: u32) -> Result<Point, Error> {
+        let p = self.point(x, y)?;
+        if !self.scalar_mul(n, &p)?.is_infinity() {
+            return Err(Error::NotInSubgroup);
+        }
+        Ok(p)
+    }
+
+    // Fails only when q is not prime and x2 - x1 or 2y is a zero divisor
+    pub fn add(&self, p1: &Point, p2: &Point) -> Result<Point, Error> {
+        let ((x1, y1), (x2, y2)) = match (p1, p2) {
+            (Point::Infinity, _) => return Ok(*p2),
+            (_, Point::Infinity) => return Ok(*p1),
+            (Point::Affine { x: x1, y: y1 }, Point::Affine { x: x2, y: y2 }) => {
+                ((*x1, *y1), (*x2, *y2))
+            }
+        };
+
+        if x1 == x2 && y1 == -y2 {
+            // P + (-P), including doubling a point of order 2
+            Ok(Point::Infinity)
+        } else if x1 == x2 {
             self.double(p1)
-        } else if p1.x == p2.x {
-            Point {
-                x: Fq::new(0, self.q),
-                y: Fq::new(0, self.q),
-            } // Point at infinity
         } else {
-            let m = (p2.y - p1.y) * inverse_mod((p2.x - p1.x).value, self.q);
-            let x3 = m * m - p1.x - p2.x;
-            let y3 = m * (p1.x - x3) - p1.y;
-            Point { x: x3, y: y3 }
+            let m = (y2 - y1) * (x2 - x1).inv()?;
+            let x3 = m * m - x1 - x2;
+            let y3 = m * (x1 - x3) - y1;
+            Ok(Point::new(x3, y3))
         }
     }
 
-    fn double(&self, p: &Point) -> Point {
-        let m = (Fq::new(3, self.q) * p.x * p.x + self.a)
-            * inverse_mod((Fq::new(2, self.q) * p.y).value, self.q);
-        let x3 = m * m - Fq::new(2, self.q) * p.x;
-        let y3 = m * (p.x - x3) - p.y;
-        Point { x: x3, y: y3 }
+    fn double(&self, p: &Point) -> Result<Point, Error> {
+        let Point::Affine { x, y } = *p else {
+            return Ok(Point::Infinity);
+        };
+        if y.value == 0 {
+            return Ok(Point::Infinity);
+        }
+        let m = (Fq::new(3, self.q) * x * x + self.a) * (Fq::new(2, self.q) * y).inv()?;
+        let x3 = m * m - Fq::new(2, self.q) * x;
+        let y3 = m * (x - x3) - y;
+        Ok(Point::new(x3, y3))
     }
+
+    pub fn negate(&self, p: &Point) -> Point {
+        match *p {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x, -y),
+        }
+    }
+
+    pub fn scalar_mul(&self, k: u32, p: &Point) -> Result<Point, Error> {
+        let mut result = Point::Infinity;
+        for i in (0..32 - k.leading_zeros()).rev() {
+            result = self.double(&result)?;
+            if k >> i & 1 == 1 {
+                result = self.add(&result, p)?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+// How y^2 = x^3 + a'x + b' relates to y^2 = x^3 + ax + b
+#[derive(Debug, PartialEq)]
+pub enum Isomorphism {
+    // E' = E^(u, r, s, t): x = u^2 x' + r, y = u^3 y' + s u^2 x' + t. Between two short
+    // Weierstrass equations (q > 3) this forces r = s = t = 0, a' = a / u^4 and b' = b / u^6.
+    Rational { u: Fq, r: Fq, s: Fq, t: Fq },
+    // Same j-invariant, but u only exists in Fq^d, so E' is a twist of E of degree d
+    Extension(u32),
+    NotIsomorphic,
+}
+
+// One Fq-isomorphism class of curves together with its number of equations (a, b)
+#[derive(Debug)]
+pub struct IsomorphismClass {
+    pub representative: EllipticCurve,
+    pub j: Fq,
+    pub size: usize,
+    pub automorphisms: usize,
 }
 
-pub fn inverse_mod(a: u8, m: u8) -> Fq {
+impl EllipticCurve {
+    pub fn discriminant(&self) -> Fq {
+        let four_a3 = Fq::new(4, self.q) * self.a * self.a * self.a;
+        let twenty_seven_b2 = Fq::new(27, self.q) * self.b * self.b;
+        -Fq::new(16, self.q) * (four_a3 + twenty_seven_b2)
+    }
+
+    // j = 1728 * 4a^3 / (4a^3 + 27b^2), undefined for a singular curve
+    pub fn j_invariant(&self) -> Result<Fq, Error> {
+        let four_a3 = Fq::new(4, self.q) * self.a * self.a * self.a;
+        let twenty_seven_b2 = Fq::new(27, self.q) * self.b * self.b;
+        let denominator = four_a3 + twenty_seven_b2;
+        if denominator.value == 0 {
+            return Err(Error::SingularCurve);
+        }
+        Ok(Fq::new(1728, self.q) * four_a3 * denominator.inv()?)
+    }
+
+    // The u in Fq* with a' = a / u^4 and b' = b / u^6
+    fn scalings(&self, other: &EllipticCurve) -> Vec<Fq> {
+        (1..self.q)
+            .map(|u| Fq::new(u as i32, self.q))
+            .filter(|u| {
+                let u4 = u.pow(4);
+                let u6 = u.pow(6);
+                other.a * u4 == self.a && other.b * u6 == self.b
+            })
+            .collect()
+    }
+
+    pub fn isomorphism(&self, other: &EllipticCurve) -> Result<Isomorphism, Error> {
+        if self.j_invariant()? != other.j_invariant()? {
+            return Ok(Isomorphism::NotIsomorphic);
+        }
+
+        let zero = Fq::new(0, self.q);
+        if let Some(&u) = self.scalings(other).first() {
+            return Ok(Isomorphism::Rational {
+                u,
+                r: zero,
+                s: zero,
+                t: zero,
+            });
+        }
+
+        // Equal j-invariants leave a single condition u^n = c: n = 6 for j = 0, n = 4 for
+        // j = 1728 and u^2 = (b / b')(a' / a) otherwise. It has a root in Fq^d exactly when
+        // c^((q^d - 1) / gcd(n, q^d - 1)) = 1, and c lies in Fq so the exponent can be
+        // reduced mod q - 1.
+        let (c, n) = if self.a == zero {
+            (self.b * other.b.inv()?, 6)
+        } else if self.b == zero {
+            (self.a * other.a.inv()?, 4)
+        } else {
+            (self.b * other.b.inv()? * other.a * self.a.inv()?, 2)
+        };
+        // Any root of u^n - c lies in Fq^d for d the degree of its irreducible factor, so
+        // d <= n and the search always stops by d = n; the fallback is never reached.
+        let q = self.q as u64;
+        let degree = (2..=n)
+            .find(|d| {
+                let order = q.pow(*d) - 1;
+                let exp = order / order.gcd(&(n as u64));
+                c.pow(exp % (q - 1)) == Fq::new(1, self.q)
+            })
+            .unwrap_or(n);
+        Ok(Isomorphism::Extension(degree))
+    }
+
+    // Aut(E) over Fq: the u with u^4 a = a and u^6 b = b, so {+-1} unless j = 0 or 1728
+    pub fn automorphisms(&self) -> Vec<Fq> {
+        self.scalings(self)
+    }
+}
+
+// All Fq-isomorphism classes of y^2 = x^3 + ax + b over Fq, q > 3
+pub fn isomorphism_classes(q: u8) -> Result<Vec<IsomorphismClass>, Error> {
+    let mut classes: Vec<IsomorphismClass> = Vec::new();
+    for a in 0..q {
+        for b in 0..q {
+            let curve = match EllipticCurve::try_new(Fq::new(a as i32, q), Fq::new(b as i32, q), q)
+            {
+                Err(Error::SingularCurve) => continue,
+                curve => curve?,
+            };
+            let j = curve.j_invariant()?;
+            let mut class = None;
+            for candidate in classes.iter_mut() {
+                if candidate.j == j
+                    && matches!(
+                        candidate.representative.isomorphism(&curve)?,
+                        Isomorphism::Rational { .. }
+                    )
+                {
+                    class = Some(candidate);
+                    break;
+                }
+            }
+            match class {
+                Some(class) => class.size += 1,
+                None => classes.push(IsomorphismClass {
+                    automorphisms: curve.automorphisms().len(),
+                    representative: curve,
+                    j,
+                    size: 1,
+                }),
+            }
+        }
+    }
+    Ok(classes)
+}
+
+pub fn inverse_mod(a: u8, m: u8) -> Result<Fq, Error> {
     for i in 1..m {
         if (a as u16 * i as u16) % m as u16 == 1 {
-            return Fq::new(i as i32, m);
+            return Ok(Fq::new(i as i32, m));
         }
     }
-    panic!("Inverse doesn't exist");
+    Err(Error::NotInvertible)
+}
+
+// Trial division, plenty for a u8 modulus
+pub fn is_prime(q: u8) -> bool {
+    q >= 2
+        && (2..q)
+            .take_while(|d| *d <= q / d)
+            .all(|d| !q.is_multiple_of(d))
 }
 
 fn roots(poly: &Polynomial, q: u8) -> Vec<Fq> {
@@ -157,16 +387,15 @@ pub fn run() {
         ],
         11,
     ); // x^3 - 2x
-    let e = EllipticCurve::new(Fq::new(-2, field_size), Fq::new(0, field_size), field_size); // E: y^2 = x^3 + ax + b
+    let e = EllipticCurve::try_new(Fq::new(-2, field_size), Fq::new(0, field_size), field_size)
+        .unwrap(); // E: y^2 = x^3 + ax + b
 
-    let p = Point {
-        x: Fq::new(5, field_size),
-        y: Fq::new(7, field_size),
-    };
-    let q = Point {
-        x: Fq::new(8, field_size),
-        y: Fq::new(10, field_size),
-    };
+    let p = e
+        .point(Fq::new(5, field_size), Fq::new(7, field_size))
+        .unwrap();
+    let q = e
+        .point(Fq::new(8, field_size), Fq::new(10, field_size))
+        .unwrap();
 
     let y = Polynomial::new(
         vec![Fq::new(2, field_size), Fq::new(1, field_size)],
@@ -186,9 +415,67 @@ pub fn run() {
         roots(&roots_poly, field_size)
     );
 
-    let r = e.add(&p, &q);
+    let r = e.add(&p, &q).unwrap();
+    println!("P {} + Q {} = {}", p, q, r);
+
+    // (0, 0) is an honest point of order 2 on y^2 = x^3 - 2x, not the identity
+    let t = Point::new(Fq::new(0, field_size), Fq::new(0, field_size));
+    assert_eq!(e.add(&t, &Point::Infinity), Ok(t));
+    assert_eq!(e.add(&t, &t), Ok(Point::Infinity));
+    assert_eq!(e.add(&p, &e.negate(&p)), Ok(Point::Infinity));
+    println!(
+        "{} + {} = {}, {} + O = {}",
+        t,
+        t,
+        e.add(&t, &t).unwrap(),
+        t,
+        e.add(&t, &Point::Infinity).unwrap()
+    );
+
+    // [#E]P = O for every P in E(F11)
+    let mut points = vec![Point::Infinity];
+    for x in 0..field_size {
+        for y in 0..field_size {
+            let (x, y) = (Fq::new(x as i32, field_size), Fq::new(y as i32, field_size));
+            if let Ok(point) = e.point(x, y) {
+                points.push(point);
+            }
+        }
+    }
+    let order = points.len() as u32;
+    for point in &points {
+        assert_eq!(e.scalar_mul(order, point), Ok(Point::Infinity));
+        assert_eq!(e.add(point, &Point::Infinity), Ok(*point));
+    }
+    println!("#E(F11) = {}, [#E]P = O for all of them", order);
+
+    // y^2 = x^3 is a cusp, (5, 8) is not on E, and (0, 0) has order 2 so it is not in a
+    // subgroup of order 3
+    let zero = Fq::new(0, field_size);
+    assert!(EllipticCurve::try_new(zero, zero, field_size).is_err());
+    assert!(e
+        .point(Fq::new(5, field_size), Fq::new(8, field_size))
+        .is_err());
+    assert_eq!(e.subgroup_point(zero, zero, 2), Ok(t));
+    assert_eq!(e.subgroup_point(zero, zero, 3), Err(Error::NotInSubgroup));
+
+    // Z/15 is not a field: the checked constructor refuses it, and on the unchecked curve
+    // the chord through (0, 1) and (3, 1) needs 1 / 3, which comes back as an error
+    let one = Fq::new(1, 15);
+    assert_eq!(
+        EllipticCurve::try_new(one, one, 15).err(),
+        Some(Error::CompositeModulus)
+    );
+    let e15 = EllipticCurve::new(one, one, 15);
+    let (p, q) = (
+        Point::new(Fq::new(0, 15), one),
+        Point::new(Fq::new(3, 15), one),
+    );
+    assert!(e15.is_on_curve(&p) && e15.is_on_curve(&q));
+    assert_eq!(e15.add(&p, &q), Err(Error::NotInvertible));
+    assert_eq!(inverse_mod(5, 15), Err(Error::NotInvertible));
     println!(
-        "P ({:?}, {:?}) + Q ({:?}, {:?}) = ({:?}, {:?})",
-        p.x.value, p.y.value, q.x.value, q.y.value, r.x.value, r.y.value
+        "Over Z/15: P + Q fails with \"{}\"",
+        e15.add(&p, &q).unwrap_err()
     );
 }
@@ -0,0 +1,428 @@
+use crate::error::Error;
+use crate::montgomery::{curve25519, MontgomeryCurve};
+use crate::mul_by_m::{FiniteField, Point};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+// Twisted Edwards curves
+//
+//   E: ax^2 + y^2 = 1 + dx^2y^2,   ad(a - d) != 0
+//
+// with neutral element (0, 1) and -(x, y) = (-x, y). When a is a square and d is not, the
+// addition law below has no exceptional cases: it works for doubling, for P + (-P) and for
+// the neutral element (Bernstein-Lange, Faster addition and doubling on elliptic curves).
+
+#[derive(Clone, Debug)]
+struct EdwardsCurve {
+    field: FiniteField,
+    a: BigInt,
+    d: BigInt,
+}
+
+// Affine points of E. Every point, the neutral element included, has affine coordinates,
+// so unlike the Weierstrass models there is no separate point at infinity.
+#[derive(Clone, Debug, PartialEq)]
+struct AffinePoint {
+    x: BigInt,
+    y: BigInt,
+}
+
+// Extended coordinates (X : Y : Z : T) with x = X / Z, y = Y / Z and T = XY / Z
+#[derive(Clone, Debug)]
+struct ExtendedPoint {
+    x: BigInt,
+    y: BigInt,
+    z: BigInt,
+    t: BigInt,
+}
+
+impl EdwardsCurve {
+    fn new(field: FiniteField, a: BigInt, d: BigInt) -> Self {
+        let a = a.mod_floor(&field.p);
+        let d = d.mod_floor(&field.p);
+        EdwardsCurve { field, a, d }
+    }
+
+    // new(), refusing ad(a - d) = 0: a = d is singular, a = 0 or d = 0 is not elliptic
+    fn try_new(field: FiniteField, a: BigInt, d: BigInt) -> Result<Self, Error> {
+        let curve = EdwardsCurve::new(field, a, d);
+        let f = &curve.field;
+        let a_minus_d = f.sub(&curve.a, &curve.d);
+        if f.mul(&f.mul(&curve.a, &curve.d), &a_minus_d).is_zero() {
+            return Err(Error::SingularCurve);
+        }
+        Ok(curve)
+    }
+
+    // The affine point (x, y), if it satisfies the curve equation
+    fn point(&self, x: BigInt, y: BigInt) -> Result<AffinePoint, Error> {
+        let p = AffinePoint {
+            x: x.mod_floor(&self.field.p),
+            y: y.mod_floor(&self.field.p),
+        };
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
+    // point(), and also [n]P = O. Edwards curves always have a cofactor of at least 4, so
+    // this is what keeps the points of small order out.
+    fn subgroup_point(&self, x: BigInt, y: BigInt, n: &BigInt) -> Result<AffinePoint, Error> {
+        let p = self.point(x, y)?;
+        let multiple = self.scalar_mul(n, &self.extended(&p));
+        if !self.same_point(&multiple, &self.identity()) {
+            return Err(Error::NotInSubgroup);
+        }
+        Ok(p)
+    }
+
+    fn is_square(&self, v: &BigInt) -> bool {
+        let p = &self.field.p;
+        v.is_zero() || v.modpow(&((p - 1) / 2), p).is_one()
+    }
+
+    // The addition law is complete exactly when a is a square and d is not
+    fn is_complete(&self) -> bool {
+        self.is_square(&self.a) && !self.is_square(&self.d)
+    }
+
+    fn is_on_curve(&self, p: &AffinePoint) -> bool {
+        let f = &self.field;
+        let x2 = f.mul(&p.x, &p.x);
+        let y2 = f.mul(&p.y, &p.y);
+        f.add(&f.mul(&self.a, &x2), &y2) == f.add(&BigInt::one(), &f.mul(&self.d, &f.mul(&x2, &y2)))
+    }
+
+    fn identity(&self) -> ExtendedPoint {
+        ExtendedPoint {
+            x: BigInt::zero(),
+            y: BigInt::one(),
+            z: BigInt::one(),
+            t: BigInt::zero(),
+        }
+    }
+
+    fn extended(&self, p: &AffinePoint) -> ExtendedPoint {
+        ExtendedPoint {
+            x: p.x.clone(),
+            y: p.y.clone(),
+            z: BigInt::one(),
+            t: self.field.mul(&p.x, &p.y),
+        }
+    }
+
+    fn to_affine(&self, p: &ExtendedPoint) -> Result<AffinePoint, Error> {
+        let f = &self.field;
+        let z_inv = f.inv(&p.z)?;
+        Ok(AffinePoint {
+            x: f.mul(&p.x, &z_inv),
+            y: f.mul(&p.y, &z_inv),
+        })
+    }
+
+    fn negate(&self, p: &ExtendedPoint) -> ExtendedPoint {
+        ExtendedPoint {
+            x: self.field.neg(&p.x),
+            y: p.y.clone(),
+            z: p.z.clone(),
+            t: self.field.neg(&p.t),
+        }
+    }
+
+    // add-2008-hwcd: 9M, one of them by d. Unified, so it also doubles.
+    fn add(&self, p: &ExtendedPoint, q: &ExtendedPoint) -> ExtendedPoint {
+        let f = &self.field;
+        let a = f.mul(&p.x, &q.x);
+        let b = f.mul(&p.y, &q.y);
+        let c = f.mul(&self.d, &f.mul(&p.t, &q.t));
+        let d = f.mul(&p.z, &q.z);
+        let e = f.sub(
+            &f.sub(&f.mul(&f.add(&p.x, &p.y), &f.add(&q.x, &q.y)), &a),
+            &b,
+        );
+        let ff = f.sub(&d, &c);
+        let g = f.add(&d, &c);
+        let h = f.sub(&b, &f.mul(&self.a, &a));
+        ExtendedPoint {
+            x: f.mul(&e, &ff),
+            y: f.mul(&g, &h),
+            z: f.mul(&ff, &g),
+            t: f.mul(&e, &h),
+        }
+    }
+
+    // dbl-2008-hwcd: cheaper than add(p, p) since it does not need T
+    fn double(&self, p: &ExtendedPoint) -> ExtendedPoint {
+        let f = &self.field;
+        let a = f.mul(&p.x, &p.x);
+        let b = f.mul(&p.y, &p.y);
+        let c = f.mul(&BigInt::from(2), &f.mul(&p.z, &p.z));
+        let d = f.mul(&self.a, &a);
+        let xy = f.add(&p.x, &p.y);
+        let e = f.sub(&f.sub(&f.mul(&xy, &xy), &a), &b);
+        let g = f.add(&d, &b);
+        let ff = f.sub(&g, &c);
+        let h = f.sub(&d, &b);
+        ExtendedPoint {
+            x: f.mul(&e, &ff),
+            y: f.mul(&g, &h),
+            z: f.mul(&ff, &g),
+            t: f.mul(&e, &h),
+        }
+    }
+
+    fn scalar_mul(&self, k: &BigInt, p: &ExtendedPoint) -> ExtendedPoint {
+        let mut result = self.identity();
+        for i in (0..k.bits()).rev() {
+            result = self.double(&result);
+            if k.bit(i) {
+                result = self.add(&result, p);
+            }
+        }
+        result
+    }
+
+    // Projective equality: X1 Z2 = X2 Z1 and Y1 Z2 = Y2 Z1
+    fn same_point(&self, p: &ExtendedPoint, q: &ExtendedPoint) -> bool {
+        let f = &self.field;
+        f.mul(&p.x, &q.z) == f.mul(&q.x, &p.z) && f.mul(&p.y, &q.z) == f.mul(&q.y, &p.z)
+    }
+
+    // E is birational to M: Bv^2 = u^3 + Au^2 + u with A = 2(a + d) / (a - d) and
+    // B = 4 / (a - d)
+    fn to_montgomery(&self) -> Result<MontgomeryCurve, Error> {
+        let f = &self.field;
+        let a_minus_d = f.sub(&self.a, &self.d);
+        let a = f.div(
+            &f.mul(&BigInt::from(2), &f.add(&self.a, &self.d)),
+            &a_minus_d,
+        )?;
+        let b = f.div(&BigInt::from(4), &a_minus_d)?;
+        Ok(MontgomeryCurve::new(f.clone(), a, b))
+    }
+
+    // (x, y) -> (u, v) = ((1 + y) / (1 - y), (1 + y) / ((1 - y) x)). The neutral element
+    // (0, 1) goes to the point at infinity and (0, -1) to the point (0, 0) of order 2.
+    fn map_to_montgomery(&self, p: &AffinePoint) -> Result<Point, Error> {
+        let f = &self.field;
+        let one = BigInt::one();
+        let one_minus_y = f.sub(&one, &p.y);
+        if one_minus_y.is_zero() {
+            return Ok(Point::Infinity);
+        }
+        if p.x.is_zero() {
+            return Ok(Point::new(BigInt::zero(), BigInt::zero()));
+        }
+        let u = f.div(&f.add(&one, &p.y), &one_minus_y)?;
+        let v = f.div(&u, &p.x)?;
+        Ok(Point::new(u, v))
+    }
+
+    // (u, v) -> (x, y) = (u / v, (u - 1) / (u + 1)). On a curve that is not complete the
+    // points with u = -1 have no image and give NotInvertible, and so do the points (u, 0)
+    // of order 2 other than (0, 0), which exist when u^2 + Au + 1 has roots.
+    fn map_from_montgomery(&self, p: &Point) -> Result<AffinePoint, Error> {
+        let f = &self.field;
+        Ok(match p.coordinates() {
+            None => AffinePoint {
+                x: BigInt::zero(),
+                y: BigInt::one(),
+            },
+            Some((u, v)) if v.is_zero() && u.is_zero() => AffinePoint {
+                x: BigInt::zero(),
+                y: f.neg(&BigInt::one()),
+            },
+            Some((_, v)) if v.is_zero() => return Err(Error::NotInvertible),
+            Some((u, v)) => AffinePoint {
+                x: f.div(u, v)?,
+                y: f.div(&f.sub(u, &BigInt::one()), &f.add(u, &BigInt::one()))?,
+            },
+        })
+    }
+}
+
+// All affine points of E over a small field with p = 3 mod 4, where square roots are powers
+fn points(curve: &EdwardsCurve) -> Vec<AffinePoint> {
+    let f = &curve.field;
+    let p = &f.p;
+    let mut points = Vec::new();
+    let mut x = BigInt::zero();
+    while &x < p {
+        // y^2 = (1 - ax^2) / (1 - dx^2)
+        let x2 = f.mul(&x, &x);
+        let denominator = f.sub(&BigInt::one(), &f.mul(&curve.d, &x2));
+        if let Ok(y2) = f.div(&f.sub(&BigInt::one(), &f.mul(&curve.a, &x2)), &denominator) {
+            let y = y2.modpow(&((p + 1) / 4), p);
+            if f.mul(&y, &y) == y2 {
+                points.push(AffinePoint {
+                    x: x.clone(),
+                    y: y.clone(),
+                });
+                if !y.is_zero() {
+                    points.push(AffinePoint {
+                        x: x.clone(),
+                        y: f.neg(&y),
+                    });
+                }
+            }
+        }
+        x += 1;
+    }
+    points
+}
+
+// The group law on E(Fp) without a single branch on the inputs, checked against the
+// Montgomery and Weierstrass models
+fn check_complete_addition(curve: &EdwardsCurve) {
+    assert!(curve.is_complete());
+    let points = points(curve);
+    let order = BigInt::from(points.len());
+    let montgomery = curve.to_montgomery().unwrap();
+    let weierstrass = montgomery.to_weierstrass().unwrap();
+    println!(
+        "{}x^2 + y^2 = 1 + {}x^2y^2 over F{}: #E = {}, Montgomery form {}v^2 = u^3 + {}u^2 + u",
+        curve.a,
+        curve.d,
+        curve.field.p,
+        points.len(),
+        montgomery.b,
+        montgomery.a
+    );
+
+    let identity = curve.identity();
+    for p in &points {
+        assert!(curve.is_on_curve(p));
+        let ep = curve.extended(p);
+
+        // P + O, P + P, P + (-P) and [#E]P all go through the same formula
+        assert!(curve.same_point(&curve.add(&ep, &identity), &ep));
+        assert!(curve.same_point(&curve.add(&ep, &ep), &curve.double(&ep)));
+        assert!(curve.same_point(&curve.add(&ep, &curve.negate(&ep)), &identity));
+        assert!(curve.same_point(&curve.scalar_mul(&order, &ep), &identity));
+
+        // Round trip through Montgomery form, and the Weierstrass model on top of it
+        let m = curve.map_to_montgomery(p).unwrap();
+        assert!(montgomery.is_on_curve(&m));
+        assert_eq!(curve.map_from_montgomery(&m), Ok(p.clone()));
+        if let Some((x, y)) = montgomery.map_to_weierstrass(&m).unwrap().coordinates() {
+            let f = &weierstrass.field;
+            let rhs = f.add(
+                &f.add(&f.mul(&f.mul(x, x), x), &f.mul(&weierstrass.a, x)),
+                &weierstrass.b,
+            );
+            assert_eq!(f.mul(y, y), rhs);
+        }
+    }
+
+    // The maps are homomorphisms: compare with the x-only ladder on the Montgomery side
+    for p in points.iter().filter(|p| !p.x.is_zero()).step_by(7) {
+        let ep = curve.extended(p);
+        for k in 1..30 {
+            let k = BigInt::from(k);
+            let sum = curve.to_affine(&curve.scalar_mul(&k, &ep)).unwrap();
+            let u = curve.map_to_montgomery(&sum).unwrap().x().cloned();
+            let m = curve.map_to_montgomery(p).unwrap();
+            let x = m.x().unwrap();
+            let ladder = montgomery.ladder(&k, x).unwrap();
+            assert_eq!(u, montgomery.to_affine_x(&ladder));
+        }
+    }
+
+    // Associativity on a sample
+    for p1 in points.iter().step_by(11) {
+        for p2 in points.iter().step_by(13) {
+            for p3 in points.iter().step_by(17) {
+                let (e1, e2, e3) = (curve.extended(p1), curve.extended(p2), curve.extended(p3));
+                assert!(curve.same_point(
+                    &curve.add(&curve.add(&e1, &e2), &e3),
+                    &curve.add(&e1, &curve.add(&e2, &e3))
+                ));
+            }
+        }
+    }
+}
+
+pub fn run() {
+    // 1019 = 3 mod 8, so 2 is not a square
+    let field = FiniteField::new(BigInt::from(1019));
+    let curve = EdwardsCurve::try_new(field.clone(), BigInt::one(), BigInt::from(2)).unwrap();
+    check_complete_addition(&curve);
+    assert!(EdwardsCurve::try_new(field.clone(), BigInt::from(2), BigInt::from(2)).is_err());
+
+    // a = -1 is a square mod 1019 only if 1019 = 1 mod 4, which it is not: not complete
+    let incomplete = EdwardsCurve::new(field, BigInt::from(-1), BigInt::from(2));
+    assert!(!incomplete.is_complete());
+
+    // ad = -2 is a square, so its Montgomery form has all three points of order 2. (0, 0)
+    // comes from (0, -1); the other two are exceptional points of the birational map.
+    let montgomery = incomplete.to_montgomery().unwrap();
+    let two_torsion: Vec<Point> = (1..1019)
+        .map(|u| Point::new(BigInt::from(u), BigInt::zero()))
+        .filter(|p| montgomery.is_on_curve(p))
+        .collect();
+    assert_eq!(two_torsion.len(), 2);
+    for p in &two_torsion {
+        assert_eq!(incomplete.map_from_montgomery(p), Err(Error::NotInvertible));
+    }
+    assert_eq!(
+        incomplete.map_from_montgomery(&Point::new(BigInt::zero(), BigInt::zero())),
+        Ok(AffinePoint {
+            x: BigInt::zero(),
+            y: BigInt::from(1018),
+        })
+    );
+
+    // Ed25519: -x^2 + y^2 = 1 - 121665/121666 x^2y^2 over 2^255 - 19, birational to
+    // Curve25519 with the base point y = 4/5 going to u = 9
+    let field = curve25519().field;
+    let d = field
+        .div(&field.neg(&BigInt::from(121665)), &BigInt::from(121666))
+        .unwrap();
+    let ed25519 = EdwardsCurve::try_new(field.clone(), BigInt::from(-1), d).unwrap();
+    assert!(ed25519.is_complete());
+    let base_x = BigInt::parse_bytes(
+        b"15112221349535400772501151409588531511454012693041857206046113283949847762202",
+        10,
+    )
+    .unwrap();
+    let base_y = field.div(&BigInt::from(4), &BigInt::from(5)).unwrap();
+    let base = ed25519.point(base_x.clone(), base_y.clone()).unwrap();
+    assert_eq!(
+        ed25519.point(base_x.clone() + 1, base_y.clone()),
+        Err(Error::NotOnCurve)
+    );
+
+    let montgomery = ed25519.to_montgomery().unwrap();
+    assert_eq!(montgomery.a, curve25519().a);
+    let u = ed25519.map_to_montgomery(&base).unwrap();
+    assert!(montgomery.is_on_curve(&u));
+    assert_eq!(u.x(), Some(&BigInt::from(9)));
+
+    // The base point has prime order l = 2^252 + 27742317777372353535851937790883648493
+    let l = (BigInt::one() << 252)
+        + BigInt::parse_bytes(b"27742317777372353535851937790883648493", 10).unwrap();
+    let eb = ed25519.extended(&base);
+    assert!(ed25519.same_point(&ed25519.scalar_mul(&l, &eb), &ed25519.identity()));
+
+    // B is in the subgroup of order l, while B + (0, -1) is on the curve but has order 2l
+    assert_eq!(
+        ed25519.subgroup_point(base_x.clone(), base_y.clone(), &l),
+        Ok(base.clone())
+    );
+    assert_eq!(
+        ed25519.subgroup_point(field.neg(&base_x), field.neg(&base_y), &l),
+        Err(Error::NotInSubgroup)
+    );
+
+    // [k]B on Ed25519 agrees with the X25519 ladder on u = 9
+    let k = BigInt::parse_bytes(b"31415926535897932384626433832795028841971", 10).unwrap();
+    let kb = ed25519.to_affine(&ed25519.scalar_mul(&k, &eb)).unwrap();
+    let curve25519 = curve25519();
+    assert_eq!(
+        ed25519.map_to_montgomery(&kb).unwrap().x().cloned(),
+        curve25519.to_affine_x(&curve25519.ladder(&k, &BigInt::from(9)).unwrap())
+    );
+    println!("Ed25519: [l]B = O and [k]B matches the Curve25519 ladder");
+}
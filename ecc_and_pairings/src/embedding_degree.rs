@@ -0,0 +1,271 @@
+use crate::error::Error;
+use crate::optimal_ate::PairingCurve;
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+
+type Point = crate::point::Point<BigUint>;
+
+// SEC 1 (section 3.1.1.2.1) rejects curves with q^B = 1 mod r for any B < 100, so this is
+// how far we search for the embedding degree before giving up
+const MAX_EMBEDDING_DEGREE: u64 = 100;
+
+// Curves over fields bigger than this are not enumerated point by point: the enumeration
+// tries all q^2 pairs (x, y), which is already a million for q = 2^10
+const MAX_ENUMERATION_FIELD: u64 = 1 << 10;
+
+// y^2 = x^3 + ax + b over Fq together with its number of points #E(Fq)
+#[derive(Clone, Debug)]
+struct ShortWeierstrassCurve {
+    q: BigUint,
+    a: BigUint,
+    b: BigUint,
+    order: BigUint,
+}
+
+#[derive(Debug)]
+struct PairingFriendliness {
+    // Smallest k with r | q^k - 1, if k < MAX_EMBEDDING_DEGREE
+    k: Option<u64>,
+    // rho = log q / log r
+    rho: f64,
+    // Whether E[r] is contained in E(Fq^k)
+    full_torsion: bool,
+    // Rough security levels (in bits) of the ECDLP in the order-r subgroup and of
+    // the DLP in Fq^k after the MOV / Frey-Ruck transfer
+    ecdlp_bits: f64,
+    fqk_dlp_bits: f64,
+    // The transfer to Fq^k makes the discrete logarithm easier
+    mov_vulnerable: bool,
+}
+
+impl ShortWeierstrassCurve {
+    fn new(q: BigUint, a: BigUint, b: BigUint, order: BigUint) -> Self {
+        ShortWeierstrassCurve { q, a, b, order }
+    }
+
+    // For small fields count the points with the Legendre symbol:
+    // #E(Fq) = q + 1 + sum_x ((x^3 + ax + b) / q)
+    fn with_point_count(q: u64, a: u64, b: u64) -> Self {
+        let mut order = q + 1;
+        for x in 0..q {
+            let rhs = (x * x % q * x + a * x + b) % q;
+            if rhs == 0 {
+                continue;
+            }
+            if BigUint::from(rhs).modpow(&BigUint::from((q - 1) / 2), &BigUint::from(q))
+                == BigUint::one()
+            {
+                order += 1;
+            } else {
+                order -= 1;
+            }
+        }
+
+        ShortWeierstrassCurve::new(
+            BigUint::from(q),
+            BigUint::from(a),
+            BigUint::from(b),
+            BigUint::from(order),
+        )
+    }
+
+    fn rhs(&self, x: &BigUint) -> BigUint {
+        (x * x * x + &self.a * x + &self.b) % &self.q
+    }
+
+    // Affine group law
+    fn add(&self, p1: &Point, p2: &Point) -> Point {
+        let q = &self.q;
+        let ((x1, y1), (x2, y2)) = match (p1.coordinates(), p2.coordinates()) {
+            (None, _) => return p2.clone(),
+            (_, None) => return p1.clone(),
+            (Some(p1), Some(p2)) => (p1, p2),
+        };
+
+        let inv = |v: BigUint| v.modpow(&(q - 2u32), q);
+        let slope = if x1 == x2 {
+            if (y1 + y2) % q == BigUint::zero() {
+                return Point::Infinity;
+            }
+            (BigUint::from(3u32) * x1 * x1 + &self.a) * inv(BigUint::from(2u32) * y1) % q
+        } else {
+            (y2 + q - y1) * inv((x2 + q - x1) % q) % q
+        };
+
+        let x3 = (&slope * &slope + q + q - x1 - x2) % q;
+        let y3 = (slope * ((x1 + q - &x3) % q) + q - y1) % q;
+        Point::new(x3, y3)
+    }
+
+    fn scalar_mul(&self, k: &BigUint, p: &Point) -> Point {
+        let mut result = Point::Infinity;
+        for i in (0..k.bits()).rev() {
+            result = self.add(&result, &result);
+            if k.bit(i) {
+                result = self.add(&result, p);
+            }
+        }
+        result
+    }
+
+    // Number of points of E(Fq) killed by r, found by enumerating E(Fq)
+    fn rational_torsion_count(&self, r: &BigUint) -> u64 {
+        let q = self.q.to_u64().unwrap();
+        let mut count = 1; // O
+        for x in 0..q {
+            let rhs = self.rhs(&BigUint::from(x));
+            for y in 0..q {
+                if BigUint::from(y * y % q) == rhs {
+                    let p = Point::new(BigUint::from(x), BigUint::from(y));
+                    if self.scalar_mul(r, &p).is_infinity() {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+}
+
+// Smallest k with r | q^k - 1, i.e. the multiplicative order of q mod r
+fn embedding_degree(q: &BigUint, r: &BigUint) -> Option<u64> {
+    let q = q % r;
+    let mut qk = q.clone();
+    for k in 1..MAX_EMBEDDING_DEGREE {
+        if qk.is_one() {
+            return Some(k);
+        }
+        qk = qk * &q % r;
+    }
+    None
+}
+
+fn log2(n: &BigUint) -> f64 {
+    // Keep 53 significant bits so the value fits an f64
+    let shift = n.bits().saturating_sub(53);
+    (n >> shift).to_f64().unwrap().log2() + shift as f64
+}
+
+// Pollard rho needs about sqrt(r) group operations
+fn ecdlp_security_bits(r: &BigUint) -> f64 {
+    log2(r) / 2.0
+}
+
+// The number field sieve in a field of size N costs L_N[1/3, (64/9)^(1/3)]
+fn ffdlp_security_bits(field_bits: f64) -> f64 {
+    let ln_n = field_bits * std::f64::consts::LN_2;
+    let cost = (64.0_f64 / 9.0).cbrt() * ln_n.cbrt() * ln_n.ln().powf(2.0 / 3.0);
+    cost / std::f64::consts::LN_2
+}
+
+// NotInSubgroup unless r | #E(Fq), i.e. unless E(Fq) has a subgroup of order r to pair on
+fn analyse(curve: &ShortWeierstrassCurve, r: &BigUint) -> Result<PairingFriendliness, Error> {
+    if r.is_zero() || !(&curve.order % r).is_zero() {
+        return Err(Error::NotInSubgroup);
+    }
+
+    let k = embedding_degree(&curve.q, r);
+    let rho = log2(&curve.q) / log2(r);
+
+    // Balasubramanian-Koblitz: for k > 1 (r not dividing q - 1), E[r] lies in E(Fq^k)
+    // exactly when r | q^k - 1. For k = 1 we need r^2 rational r-torsion points, which
+    // we can only check by enumeration; on large fields fall back to the necessary
+    // condition r^2 | #E(Fq).
+    let full_torsion = match k {
+        None => false,
+        Some(1) if curve.q < BigUint::from(MAX_ENUMERATION_FIELD) => {
+            BigUint::from(curve.rational_torsion_count(r)) == r * r
+        }
+        Some(1) => (&curve.order % (r * r)).is_zero(),
+        Some(_) => true,
+    };
+
+    let ecdlp_bits = ecdlp_security_bits(r);
+    let fqk_dlp_bits = match k {
+        Some(k) => ffdlp_security_bits(k as f64 * log2(&curve.q)),
+        None => f64::INFINITY,
+    };
+
+    Ok(PairingFriendliness {
+        k,
+        rho,
+        full_torsion,
+        ecdlp_bits,
+        fqk_dlp_bits,
+        mov_vulnerable: fqk_dlp_bits < ecdlp_bits,
+    })
+}
+
+fn print_report(name: &str, report: &PairingFriendliness) {
+    println!(
+        "{}: k = {:?}, rho = {:.3}, full r-torsion in Fq^k: {}, ECDLP ~{:.1} bits, Fq^k DLP ~{:.1} bits, MOV-vulnerable: {}",
+        name,
+        report.k,
+        report.rho,
+        report.full_torsion,
+        report.ecdlp_bits,
+        report.fqk_dlp_bits,
+        report.mov_vulnerable
+    );
+}
+
+pub fn run() {
+    // y^2 = x^3 + 1 over F59 from the r-torsion flower and y^2 = x^3 + 72 over F103 from
+    // the twisted curves demo
+    let flower = ShortWeierstrassCurve::with_point_count(59, 0, 1);
+    let report = analyse(&flower, &BigUint::from(5u32)).unwrap();
+    print_report("y^2 = x^3 + 1 over F59, r = 5", &report);
+    assert_eq!(report.k, Some(2));
+    assert!(report.full_torsion);
+    // #E(F59) = 60 has no subgroup of order 7
+    assert_eq!(
+        analyse(&flower, &BigUint::from(7u32)).err(),
+        Some(Error::NotInSubgroup)
+    );
+
+    let twisted = ShortWeierstrassCurve::with_point_count(103, 0, 72);
+    let report = analyse(&twisted, &BigUint::from(7u32)).unwrap();
+    print_report("y^2 = x^3 + 72 over F103, r = 7", &report);
+    assert_eq!(report.k, Some(6));
+    assert!(report.full_torsion);
+
+    // k = 1 with and without the full r-torsion over the base field
+    let full = ShortWeierstrassCurve::with_point_count(13, 12, 0);
+    let report = analyse(&full, &BigUint::from(2u32)).unwrap();
+    print_report("y^2 = x^3 - x over F13, r = 2", &report);
+    assert_eq!(report.k, Some(1));
+    assert!(report.full_torsion);
+
+    let cyclic = ShortWeierstrassCurve::with_point_count(23, 1, 5);
+    let report = analyse(&cyclic, &BigUint::from(11u32)).unwrap();
+    print_report("y^2 = x^3 + x + 5 over F23, r = 11", &report);
+    assert_eq!(report.k, Some(1));
+    assert!(!report.full_torsion);
+
+    // Supersingular y^2 = x^3 + x over a 127-bit prime q = 3 mod 4 has #E = q + 1 = 4r and
+    // k = 2, so its discrete logarithms transfer to a 254-bit field where the NFS wins
+    let q = BigUint::parse_bytes(b"170141183460469231731687303715884123283", 10).unwrap();
+    let r = BigUint::parse_bytes(b"42535295865117307932921825928971030821", 10).unwrap();
+    let supersingular =
+        ShortWeierstrassCurve::new(q.clone(), BigUint::one(), BigUint::zero(), q + 1u32);
+    let report = analyse(&supersingular, &r).unwrap();
+    print_report("y^2 = x^3 + x over a 127-bit field", &report);
+    assert_eq!(report.k, Some(2));
+    assert!(report.mov_vulnerable);
+
+    for (name, pairing_curve) in [
+        ("BN254", PairingCurve::bn254()),
+        ("BLS12-381", PairingCurve::bls12_381()),
+    ] {
+        let curve = ShortWeierstrassCurve::new(
+            pairing_curve.p.clone(),
+            BigUint::zero(),
+            pairing_curve.b.clone(),
+            &pairing_curve.h * &pairing_curve.r,
+        );
+        let report = analyse(&curve, &pairing_curve.r).unwrap();
+        print_report(name, &report);
+        assert_eq!(report.k, Some(12));
+        assert!(!report.mov_vulnerable);
+    }
+}
@@ -1,12 +1,9 @@
+use crate::error::Error;
 use num_bigint::BigInt;
 use num_rational::BigRational;
 use num_traits::{One, Zero};
 
-#[derive(Debug, Clone)]
-struct Point {
-    x: BigRational,
-    y: BigRational,
-}
+type Point = crate::point::Point<BigRational>;
 
 #[derive(Clone, Debug)]
 struct ExtendedPoint {
@@ -26,17 +23,46 @@ impl EllipticCurve {
         EllipticCurve { a, b }
     }
 
+    // new(), refusing 4a^3 + 27b^2 = 0
+    fn try_new(a: BigRational, b: BigRational) -> Result<Self, Error> {
+        let four = BigRational::from(BigInt::from(4));
+        let twenty_seven = BigRational::from(BigInt::from(27));
+        if (four * &a * &a * &a + twenty_seven * &b * &b).is_zero() {
+            return Err(Error::SingularCurve);
+        }
+        Ok(EllipticCurve::new(a, b))
+    }
+
+    fn is_on_curve(&self, p: &Point) -> bool {
+        let Some((x, y)) = p.coordinates() else {
+            return true;
+        };
+        y * y == x * x * x + &self.a * x + &self.b
+    }
+
+    // The rational point (x, y), if it satisfies the curve equation
+    fn point(&self, x: BigRational, y: BigRational) -> Result<Point, Error> {
+        let p = Point::new(x, y);
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
     fn add(&self, p: &Point, q: &Point) -> Point {
-        if p.x == q.x && p.y == q.y {
-            self.double(p)
-        } else if p.x == q.x {
+        let ((x1, y1), (x2, y2)) = match (p.coordinates(), q.coordinates()) {
+            (None, _) => return q.clone(),
+            (_, None) => return p.clone(),
+            (Some(p), Some(q)) => (p, q),
+        };
+
+        if x1 == x2 && *y1 == -y2 {
             // this means the points are inverse
-            Point {
-                x: BigRational::zero(),
-                y: BigRational::zero(),
-            }
+            Point::Infinity
+        } else if x1 == x2 {
+            self.double(p)
         } else {
-            let m = (&q.y - &q.y) - (&q.x - &q.x);
+            let m = (y2 - y1) / (x2 - x1);
             // y = mx + c
             // P = x, y
             // Q = x2, y2
@@ -44,26 +70,39 @@ impl EllipticCurve {
             // After substituiting y in the curve y^2 = x^3 -2
             // x3 = m^2 - x1 - x2
             // y3 = m(x1 - x3) - y1
-            let x = &m * &m - &p.x - &q.x;
-            let y = &m * (&p.x - &x) - &p.y;
-            Point { x, y }
+            let x = &m * &m - x1 - x2;
+            let y = &m * (x1 - &x) - y1;
+            Point::new(x, y)
         }
     }
 
     fn double(&self, p: &Point) -> Point {
+        let Some((x1, y1)) = p.coordinates() else {
+            return Point::Infinity;
+        };
+        if y1.is_zero() {
+            return Point::Infinity;
+        }
         // differentiating y^2 = x^3 + ax + b
         // m = ( 3x^2 + a) / ( 2y )
-        let m = (BigRational::from(BigInt::from(3)) * &p.x * &p.x + &self.a)
-            / (BigRational::from(BigInt::from(2)) * &p.y);
+        let m = (BigRational::from(BigInt::from(3)) * x1 * x1 + &self.a)
+            / (BigRational::from(BigInt::from(2)) * y1);
         // tangent intersect the curve at P twice and at the result point once. Same as add()
-        let x = &m * &m - BigRational::from(BigInt::from(2)) * &p.x;
-        let y = &m * (&p.x - &x) - &p.y;
-        Point { x, y }
+        let x = &m * &m - BigRational::from(BigInt::from(2)) * x1;
+        let y = &m * (x1 - &x) - y1;
+        Point::new(x, y)
+    }
+
+    fn negate(&self, p: &Point) -> Point {
+        match p {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x.clone(), -y),
+        }
     }
 
     pub fn multiply(&self, p: &Point, n: u32) -> Point {
-        let mut result = p.clone();
-        for _ in 1..n {
+        let mut result = Point::Infinity;
+        for _ in 0..n {
             result = self.add(&result, p)
         }
         result
@@ -72,12 +111,14 @@ impl EllipticCurve {
 
 pub fn run() {
     let q = BigRational::new(BigInt::zero(), BigInt::one());
-    let e = EllipticCurve::new(q.clone(), BigRational::from(BigInt::from(-2)));
+    let e = EllipticCurve::try_new(q.clone(), BigRational::from(BigInt::from(-2))).unwrap();
 
-    let p = Point {
-        x: BigRational::from(BigInt::from(3)),
-        y: BigRational::from(BigInt::from(5)),
-    };
+    let p = e
+        .point(
+            BigRational::from(BigInt::from(3)),
+            BigRational::from(BigInt::from(5)),
+        )
+        .unwrap();
 
     let s = ExtendedPoint {
         x: BigRational::zero(),
@@ -88,6 +129,18 @@ pub fn run() {
     let p2 = e.multiply(&p, 2);
     let p3 = e.multiply(&p, 3);
 
-    println!("2*P: ({}, {})", p2.x, p2.y);
-    println!("3*P: ({}, {})", p3.x, p3.y);
+    println!("2*P: {}", p2);
+    println!("3*P: {}", p3);
+
+    // 2P = (129/100, -383/1000), 3P - 2P = P and [0]P = O
+    let frac = |n: i64, d: i64| BigRational::new(BigInt::from(n), BigInt::from(d));
+    assert_eq!(p2, Point::new(frac(129, 100), frac(-383, 1000)));
+    assert_eq!(e.add(&p, &e.negate(&p)), Point::Infinity);
+    assert_eq!(e.add(&p3, &e.negate(&p2)), p);
+    assert_eq!(e.multiply(&p, 0), Point::Infinity);
+
+    // Multiples stay on the curve, while (3, 4) and the cusp y^2 = x^3 are rejected
+    assert!(e.is_on_curve(&p2) && e.is_on_curve(&p3));
+    assert_eq!(e.point(frac(3, 1), frac(4, 1)), Err(Error::NotOnCurve));
+    assert!(EllipticCurve::try_new(q.clone(), q).is_err());
 }
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use std::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -71,11 +72,7 @@ impl Mul for Polynomial {
     }
 }
 
-#[derive(Clone, Debug)]
-struct Point {
-    x: f64,
-    y: f64,
-}
+type Point = crate::point::Point<f64>;
 
 struct EllipticCurve<T> {
     a: T,
@@ -90,28 +87,74 @@ where
         EllipticCurve { a, b }
     }
 
+    fn negate(&self, p: &Point) -> Point {
+        match *p {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x, -y),
+        }
+    }
+}
+
+// Over the reals the checks can only hold up to rounding
+const EPSILON: f64 = 1e-9;
+
+impl EllipticCurve<f64> {
+    // new(), refusing 4a^3 + 27b^2 = 0
+    fn try_new(a: f64, b: f64) -> Result<Self, Error> {
+        if (4.0 * a * a * a + 27.0 * b * b).abs() < EPSILON {
+            return Err(Error::SingularCurve);
+        }
+        Ok(EllipticCurve::new(a, b))
+    }
+
     fn add(&self, p1: &Point, p2: &Point) -> Point {
-        if p1.x == p2.x && p1.y == p2.y {
+        let ((x1, y1), (x2, y2)) = match (*p1, *p2) {
+            (Point::Infinity, _) => return *p2,
+            (_, Point::Infinity) => return *p1,
+            (Point::Affine { x: x1, y: y1 }, Point::Affine { x: x2, y: y2 }) => {
+                ((x1, y1), (x2, y2))
+            }
+        };
+
+        if x1 == x2 && y1 == -y2 {
+            Point::Infinity // P + (-P)
+        } else if x1 == x2 {
             self.double(p1)
-        } else if p1.x == p2.x {
-            Point {
-                x: f64::INFINITY,
-                y: f64::INFINITY,
-            } // Point at infinity
         } else {
-            let m = (p2.y - p1.y) / (p2.x - p1.x);
-            let x3 = m * m - p1.x - p2.x;
-            let y3 = m * (p1.x - x3) - p1.y;
-            Point { x: x3, y: y3 }
+            let m = (y2 - y1) / (x2 - x1);
+            let x3 = m * m - x1 - x2;
+            let y3 = m * (x1 - x3) - y1;
+            Point::new(x3, y3)
         }
     }
 
     fn double(&self, p: &Point) -> Point {
-        let fp = Polynomial::new(vec![-2.0, 0.0, 3.0 * p.x]);
-        let m = fp.evaluate(p.x) / (2.0 * p.y);
-        let x3 = m * m - 2.0 * p.x;
-        let y3 = m * (p.x - x3) - p.y;
-        Point { x: x3, y: y3 }
+        let Point::Affine { x, y } = *p else {
+            return Point::Infinity;
+        };
+        if y == 0.0 {
+            return Point::Infinity; // vertical tangent
+        }
+        let m = (3.0 * x * x + self.a) / (2.0 * y); // f'(x) / 2y with f'(x) = 3x^2 + a
+        let x3 = m * m - 2.0 * x;
+        let y3 = m * (x - x3) - y;
+        Point::new(x3, y3)
+    }
+
+    fn is_on_curve(&self, p: &Point) -> bool {
+        match *p {
+            Point::Infinity => true,
+            Point::Affine { x, y } => (y * y - (x * x * x + self.a * x + self.b)).abs() < EPSILON,
+        }
+    }
+
+    // The point (x, y), if it satisfies the curve equation
+    fn point(&self, x: f64, y: f64) -> Result<Point, Error> {
+        let p = Point::new(x, y);
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
     }
 }
 
@@ -129,11 +172,11 @@ fn roots(poly: &Polynomial) -> Vec<f64> {
 
 pub fn run() {
     let f = Polynomial::new(vec![0.0, -2.0, 0.0, 1.0]); // x^3 - 2x
-    let e = EllipticCurve::new(-2.0, 0.0); // E: y^2 = x^3 + ax + b
+    let e = EllipticCurve::try_new(-2.0, 0.0).unwrap(); // E: y^2 = x^3 + ax + b
 
-    let p1 = Point { x: -1.0, y: -1.0 };
-    let p2 = Point { x: 0.0, y: 0.0 };
-    let p3 = Point { x: 2.0, y: 2.0 };
+    let p1 = e.point(-1.0, -1.0).unwrap();
+    let p2 = e.point(0.0, 0.0).unwrap();
+    let p3 = e.point(2.0, 2.0).unwrap();
 
     println!("P1 + P2 = {:?}", e.add(&p1, &p2));
     println!("P2 + P3 = {:?}", e.add(&p2, &p3));
@@ -146,4 +189,23 @@ pub fn run() {
     println!("Roots of y^2 - f: {:?}", roots(&roots_poly));
 
     println!("2*P1 = {:?}", e.double(&p1));
+
+    // (0, 0) has order 2: it is its own negative, not the identity
+    assert_eq!(e.add(&p2, &p2), Point::Infinity);
+    assert_eq!(e.add(&p2, &Point::Infinity), p2);
+    assert_eq!(e.add(&p1, &e.negate(&p1)), Point::Infinity);
+
+    // Sums stay on E; (1, 1) is not on it and y^2 = x^3 - 3x + 2 has a node at (1, 0)
+    assert!(e.is_on_curve(&e.add(&p1, &p3)) && e.is_on_curve(&e.double(&p1)));
+    assert_eq!(e.point(1.0, 1.0), Err(Error::NotOnCurve));
+    assert_eq!(
+        EllipticCurve::try_new(-3.0, 2.0).err(),
+        Some(Error::SingularCurve)
+    );
+
+    // The tangent slope uses the curve's own a: on y^2 = x^3 + x + 1, 2(0, 1) = (1/4, -9/8)
+    let e1 = EllipticCurve::try_new(1.0, 1.0).unwrap();
+    let q = e1.point(0.0, 1.0).unwrap();
+    assert_eq!(e1.double(&q), Point::new(0.25, -1.125));
+    assert!(e1.is_on_curve(&e1.double(&q)));
 }
@@ -0,0 +1,51 @@
+use std::fmt;
+
+// Everything that can go wrong when building curves and points or doing arithmetic on them.
+// Functions that can hit one of these return a Result instead of panicking, so a caller
+// feeding in untrusted input can recover.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    // Division by an element with no inverse: zero, or a zero divisor when the modulus is
+    // not prime. On a curve this is what a vertical chord or tangent turns into.
+    NotInvertible,
+    // The modulus of a prime field is not prime
+    CompositeModulus,
+    // The discriminant vanishes: the cubic has a repeated root, so the curve has a node or a
+    // cusp and the chord-and-tangent law is not a group law
+    SingularCurve,
+    // The coordinates do not satisfy the curve equation
+    NotOnCurve,
+    // On the curve, but [n]P != O for the order n of the subgroup we asked for
+    NotInSubgroup,
+    // Operands from different fields
+    MismatchedFields,
+    // Bytes or coefficients that do not parse as a field element or a point: wrong length
+    // or prefix, or a coordinate that is not reduced mod p
+    InvalidEncoding,
+    // More output requested of a hash expander than its length counters can describe
+    OutputTooLong,
+    // A window width outside the range a scalar multiplication method supports
+    InvalidWindow,
+    // A curve outside the family a set of formulas is written for, such as Lopez-Dahab
+    // arithmetic on anything but y^2 + xy = x^3 + a2 x^2 + a6 in characteristic 2
+    UnsupportedCurve,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotInvertible => write!(f, "element is not invertible"),
+            Error::CompositeModulus => write!(f, "field modulus is not prime"),
+            Error::SingularCurve => write!(f, "singular curve: the discriminant is zero"),
+            Error::NotOnCurve => write!(f, "point is not on the curve"),
+            Error::NotInSubgroup => write!(f, "point is not in the prime-order subgroup"),
+            Error::MismatchedFields => write!(f, "elements belong to different fields"),
+            Error::InvalidEncoding => write!(f, "malformed encoding"),
+            Error::OutputTooLong => write!(f, "requested hash output is too long"),
+            Error::InvalidWindow => write!(f, "unsupported window width"),
+            Error::UnsupportedCurve => write!(f, "curve not in the form these formulas need"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+// The r-torsion flower: for r coprime to q, E[r] is Z/r x Z/r and splits into r + 1 cyclic
+// subgroups of order r, the petals, which pairwise meet only at O. The r_torsion_curve_flower
+// and twisted_curves modules build it over Fq2 and Fq6 through this trait.
+pub trait TorsionCurve {
+    type Point: Clone + Debug + Eq + Hash;
+
+    fn q(&self) -> u64;
+    fn is_on_curve(&self, p: &Self::Point) -> bool;
+    fn is_identity(&self, p: &Self::Point) -> bool;
+    fn add(&self, p1: &Self::Point, p2: &Self::Point) -> Self::Point;
+    fn scalar_mul(&self, k: u64, p: &Self::Point) -> Self::Point;
+    // The q-power Frobenius endomorphism pi(x, y) = (x^q, y^q)
+    fn frobenius(&self, p: &Self::Point) -> Self::Point;
+}
+
+// Labels of the r + 1 cyclic subgroups of order r making up the petals of E[r]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PetalLabel {
+    G1,         // E(Fq)[r], where pi acts as [1]
+    G2,         // The trace-zero subgroup, where pi acts as [q]
+    Other(u64), // <P + [i]Q> for P generating G1 and Q generating G2
+}
+
+#[derive(Debug)]
+pub struct Petal<P> {
+    pub label: PetalLabel,
+    pub generator: P,
+    pub points: HashSet<P>, // All r multiples of the generator, O included
+}
+
+// Generators of the r + 1 petals: G1, G2 and the r - 1 remaining subgroups <P + [i]Q> for
+// P generating G1 and Q generating G2
+pub fn petal_generators<C: TorsionCurve>(
+    curve: &C,
+    g1: &C::Point,
+    g2: &C::Point,
+    r: u64,
+) -> Vec<(PetalLabel, C::Point)> {
+    let mut generators = vec![(PetalLabel::G1, g1.clone()), (PetalLabel::G2, g2.clone())];
+    for i in 1..r {
+        generators.push((
+            PetalLabel::Other(i),
+            curve.add(g1, &curve.scalar_mul(i, g2)),
+        ));
+    }
+    generators
+}
+
+pub fn torsion_flower<C: TorsionCurve>(
+    curve: &C,
+    g1: &C::Point,
+    g2: &C::Point,
+    r: u64,
+) -> Vec<Petal<C::Point>> {
+    petal_generators(curve, g1, g2, r)
+        .into_iter()
+        .map(|(label, generator)| {
+            let points = (0..r).map(|j| curve.scalar_mul(j, &generator)).collect();
+            Petal {
+                label,
+                generator,
+                points,
+            }
+        })
+        .collect()
+}
+
+// Checks the petals are r + 1 subgroups of order r which meet only at O and cover E[r]
+pub fn check_torsion_flower<C: TorsionCurve>(curve: &C, flower: &[Petal<C::Point>], r: u64) {
+    assert_eq!(flower.len() as u64, r + 1);
+
+    let mut union = HashSet::new();
+    for (i, petal) in flower.iter().enumerate() {
+        assert_eq!(petal.points.len() as u64, r);
+        assert!(petal
+            .points
+            .iter()
+            .all(|p| curve.is_on_curve(p) && curve.is_identity(&curve.scalar_mul(r, p))));
+        for other in &flower[i + 1..] {
+            assert_eq!(petal.points.intersection(&other.points).count(), 1);
+        }
+
+        let pi_g = curve.frobenius(&petal.generator);
+        match petal.label {
+            PetalLabel::G1 => assert_eq!(pi_g, petal.generator),
+            PetalLabel::G2 => assert_eq!(pi_g, curve.scalar_mul(curve.q(), &petal.generator)),
+            PetalLabel::Other(_) => assert!(!petal.points.contains(&pi_g)),
+        }
+        union.extend(petal.points.iter().cloned());
+    }
+    assert_eq!(union.len() as u64, r * r);
+}
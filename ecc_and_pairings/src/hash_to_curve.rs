@@ -0,0 +1,463 @@
+use crate::error::Error;
+use crate::montgomery::{curve25519, MontgomeryCurve};
+use crate::mul_by_m::{EllipticCurve, FiniteField, Point};
+use crate::sec1::sqrt_mod;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
+use sha2::digest::core_api::BlockSizeUser;
+use sha2::{Digest, Sha256, Sha512};
+
+// Hashing to elliptic curves, RFC 9380
+//
+//   hash_to_curve(msg) = clear_cofactor(map_to_curve(u0) + map_to_curve(u1))
+//
+// with (u0, u1) = hash_to_field(msg, 2). map_to_curve is a deterministic map from Fp onto a
+// large part of E, and the sum of two of them is indistinguishable from a random point.
+// Trying x = H(msg || counter) until x^3 + ax + b is a square, as reduction.rs does with
+// random x, takes a message-dependent number of steps and leaks it through timing.
+
+// Every suite below targets 128-bit security, the k of section 5
+const SECURITY_BITS: u64 = 128;
+
+// Section 5.3.1: stretches msg to len_in_bytes uniform bytes with a Merkle-Damgard hash.
+// dst separates the domains of different protocols and suites. At most 255 hash outputs
+// and 65535 bytes fit the counters, and OutputTooLong reports a request for more.
+pub fn expand_message_xmd<H: Digest + BlockSizeUser>(
+    msg: &[u8],
+    dst: &[u8],
+    len_in_bytes: usize,
+) -> Result<Vec<u8>, Error> {
+    let ell = len_in_bytes.div_ceil(<H as Digest>::output_size());
+    if ell > 255 || len_in_bytes > 65535 {
+        return Err(Error::OutputTooLong);
+    }
+    // Section 5.3.3: a DST over 255 bytes is replaced by H("H2C-OVERSIZE-DST-" || DST)
+    let long_dst;
+    let dst = if dst.len() > 255 {
+        long_dst = H::new()
+            .chain_update(b"H2C-OVERSIZE-DST-")
+            .chain_update(dst)
+            .finalize();
+        &long_dst[..]
+    } else {
+        dst
+    };
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+
+    // b0 = H(Z_pad || msg || l_i_b_str || 0 || DST'), with a block of zeros in front
+    let b0 = H::new()
+        .chain_update(vec![0u8; H::block_size()])
+        .chain_update(msg)
+        .chain_update((len_in_bytes as u16).to_be_bytes())
+        .chain_update([0])
+        .chain_update(&dst_prime)
+        .finalize();
+    // b1 = H(b0 || 1 || DST'), bi = H((b0 xor b(i - 1)) || i || DST')
+    let mut b = H::new()
+        .chain_update(&b0)
+        .chain_update([1])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = b.to_vec();
+    for i in 2..=ell {
+        let chained: Vec<u8> = b0.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect();
+        b = H::new()
+            .chain_update(chained)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b);
+    }
+    uniform_bytes.truncate(len_in_bytes);
+    Ok(uniform_bytes)
+}
+
+// Section 5.2 for prime fields: count elements of Fp, each reduced from
+// L = ceil((ceil(log2 p) + k) / 8) bytes so that the bias mod p is about 2^-k
+pub fn hash_to_field<H: Digest + BlockSizeUser>(
+    msg: &[u8],
+    dst: &[u8],
+    count: usize,
+    p: &BigInt,
+    k: u64,
+) -> Result<Vec<BigInt>, Error> {
+    let l = ((p - 1u32).bits() + k).div_ceil(8) as usize;
+    Ok(expand_message_xmd::<H>(msg, dst, count * l)?
+        .chunks(l)
+        .map(|bytes| BigInt::from_bytes_be(Sign::Plus, bytes) % p)
+        .collect())
+}
+
+// Section 4.1: the "sign" of x, its parity in a prime field
+fn sgn0(x: &BigInt) -> bool {
+    x.bit(0)
+}
+
+// Simplified SWU, section 6.6.2, for y^2 = x^3 + ax + b with ab != 0. Z is a fixed non-square
+// (section H.2), so that exactly one of g(x1) and g(x2) = Z^3 u^6 g(x1) is a square.
+pub fn map_to_curve_simple_swu(
+    curve: &EllipticCurve,
+    z: &BigInt,
+    u: &BigInt,
+) -> Result<Point, Error> {
+    let f = &curve.field;
+    let (a, b) = (&curve.a, &curve.b);
+    let zu2 = f.mul(z, &f.mul(u, u));
+    let tv1 = f.add(&f.mul(&zu2, &zu2), &zu2);
+    // x1 = -b / a (1 + 1 / (Z^2 u^4 + Z u^2)), or b / Za where that denominator vanishes
+    let x1 = if tv1.is_zero() {
+        f.div(b, &f.mul(z, a))?
+    } else {
+        f.mul(&f.neg(&f.div(b, a)?), &f.add(&BigInt::one(), &f.inv(&tv1)?))
+    };
+    let g = |x: &BigInt| f.add(&f.add(&f.mul(&f.mul(x, x), x), &f.mul(a, x)), b);
+    let (x, y) = match sqrt_mod(&g(&x1), &f.p) {
+        Some(y) => (x1, y),
+        None => {
+            let x2 = f.mul(&zu2, &x1);
+            let y = sqrt_mod(&g(&x2), &f.p).ok_or(Error::NotOnCurve)?;
+            (x2, y)
+        }
+    };
+    // y takes the sign of u
+    let y = if sgn0(u) == sgn0(&y) { y } else { f.neg(&y) };
+    curve.point(x, y)
+}
+
+// Elligator 2, section 6.7.1, for By^2 = x^3 + Ax^2 + x (K = B and J = A in the RFC) and a
+// non-square Z. x1 = -(A / B) / (1 + Z u^2) and x2 = -x1 - A / B, and again exactly one of
+// g(x1), g(x2) is a square for g(x) = x^3 + (A / B) x^2 + x / B^2.
+pub fn map_to_curve_elligator2(
+    curve: &MontgomeryCurve,
+    z: &BigInt,
+    u: &BigInt,
+) -> Result<Point, Error> {
+    let f = &curve.field;
+    let j_over_k = f.div(&curve.a, &curve.b)?;
+    let k2_inv = f.inv(&f.mul(&curve.b, &curve.b))?;
+    let g = |x: &BigInt| {
+        let x2 = f.mul(x, x);
+        f.add(
+            &f.add(&f.mul(&x2, x), &f.mul(&j_over_k, &x2)),
+            &f.mul(x, &k2_inv),
+        )
+    };
+    let tv1 = f.add(&BigInt::one(), &f.mul(z, &f.mul(u, u)));
+    let x1 = if tv1.is_zero() {
+        f.neg(&j_over_k)
+    } else {
+        f.neg(&f.div(&j_over_k, &tv1)?)
+    };
+    // The square root is odd for x1 and even for x2
+    let (x, y) = match sqrt_mod(&g(&x1), &f.p) {
+        Some(y) => (x1, if sgn0(&y) { y } else { f.neg(&y) }),
+        None => {
+            let x2 = f.sub(&f.neg(&x1), &j_over_k);
+            let y = sqrt_mod(&g(&x2), &f.p).ok_or(Error::NotOnCurve)?;
+            (x2, if sgn0(&y) { f.neg(&y) } else { y })
+        }
+    };
+    // (x, y) is on y^2 = g(x); scale back to By^2 = x^3 + Ax^2 + x
+    curve.point(f.mul(&x, &curve.b), f.mul(&y, &curve.b))
+}
+
+// A rational map E' -> E, section 6.6.3: x = x_num(x') / x_den(x') and
+// y = y' y_num(x') / y_den(x'), each polynomial by its coefficients from the constant term up
+pub struct IsogenyMap {
+    pub x_num: Vec<BigInt>,
+    pub x_den: Vec<BigInt>,
+    pub y_num: Vec<BigInt>,
+    pub y_den: Vec<BigInt>,
+}
+
+impl IsogenyMap {
+    pub fn map(&self, field: &FiniteField, p: &Point) -> Result<Point, Error> {
+        let Some((x, y)) = p.coordinates() else {
+            return Ok(Point::Infinity);
+        };
+        let evaluate = |coefficients: &[BigInt]| {
+            coefficients
+                .iter()
+                .rev()
+                .fold(BigInt::zero(), |acc, c| field.add(&field.mul(&acc, x), c))
+        };
+        let (x_den, y_den) = (evaluate(&self.x_den), evaluate(&self.y_den));
+        // The kernel of the isogeny goes to O
+        if x_den.is_zero() || y_den.is_zero() {
+            return Ok(Point::Infinity);
+        }
+        Ok(Point::new(
+            field.div(&evaluate(&self.x_num), &x_den)?,
+            field.mul(y, &field.div(&evaluate(&self.y_num), &y_den)?),
+        ))
+    }
+}
+
+// A curve with one of the mappings above
+pub trait MapToCurve {
+    fn modulus(&self) -> &BigInt;
+    fn map_to_curve(&self, u: &BigInt) -> Result<Point, Error>;
+    fn add(&self, p: &Point, q: &Point) -> Result<Point, Error>;
+    fn cofactor(&self) -> u64;
+
+    // Section 7: [h]P is always a valid clear_cofactor, though some curves have cheaper
+    // endomorphism-based ones
+    fn clear_cofactor(&self, p: &Point) -> Result<Point, Error> {
+        let h = self.cofactor();
+        let mut result = Point::Infinity;
+        for i in (0..u64::BITS - h.leading_zeros()).rev() {
+            result = self.add(&result, &result)?;
+            if (h >> i) & 1 == 1 {
+                result = self.add(&result, p)?;
+            }
+        }
+        Ok(result)
+    }
+
+    // Section 3, the random-oracle construction
+    fn hash_to_curve<H: Digest + BlockSizeUser>(
+        &self,
+        msg: &[u8],
+        dst: &[u8],
+    ) -> Result<Point, Error> {
+        let u = hash_to_field::<H>(msg, dst, 2, self.modulus(), SECURITY_BITS)?;
+        let q0 = self.map_to_curve(&u[0])?;
+        let q1 = self.map_to_curve(&u[1])?;
+        self.clear_cofactor(&self.add(&q0, &q1)?)
+    }
+}
+
+// Simplified SWU on a short Weierstrass curve. When a = 0 or b = 0 (secp256k1, BLS12-381)
+// SWU runs on an isogenous curve E' with a'b' != 0 and the isogeny brings the point over.
+pub struct SswuSuite {
+    pub curve: EllipticCurve,
+    pub isogeny: Option<(EllipticCurve, IsogenyMap)>,
+    pub z: BigInt,
+    pub cofactor: u64,
+}
+
+impl MapToCurve for SswuSuite {
+    fn modulus(&self) -> &BigInt {
+        &self.curve.field.p
+    }
+
+    fn map_to_curve(&self, u: &BigInt) -> Result<Point, Error> {
+        match &self.isogeny {
+            None => map_to_curve_simple_swu(&self.curve, &self.z, u),
+            Some((isogenous, map)) => map.map(
+                &self.curve.field,
+                &map_to_curve_simple_swu(isogenous, &self.z, u)?,
+            ),
+        }
+    }
+
+    fn add(&self, p: &Point, q: &Point) -> Result<Point, Error> {
+        self.curve.add(p, q)
+    }
+
+    fn cofactor(&self) -> u64 {
+        self.cofactor
+    }
+}
+
+pub struct Elligator2Suite {
+    pub curve: MontgomeryCurve,
+    pub z: BigInt,
+    pub cofactor: u64,
+}
+
+impl MapToCurve for Elligator2Suite {
+    fn modulus(&self) -> &BigInt {
+        &self.curve.field.p
+    }
+
+    fn map_to_curve(&self, u: &BigInt) -> Result<Point, Error> {
+        map_to_curve_elligator2(&self.curve, &self.z, u)
+    }
+
+    fn add(&self, p: &Point, q: &Point) -> Result<Point, Error> {
+        self.curve.add(p, q)
+    }
+
+    fn cofactor(&self) -> u64 {
+        self.cofactor
+    }
+}
+
+fn int(hex: &str) -> BigInt {
+    BigInt::parse_bytes(hex.as_bytes(), 16).unwrap()
+}
+
+// P256_XMD:SHA-256_SSWU_RO_, section 8.2
+fn p256() -> SswuSuite {
+    let p = int("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff");
+    let b = int("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b");
+    SswuSuite {
+        curve: EllipticCurve::try_new(FiniteField::new(p), BigInt::from(-3), b).unwrap(),
+        isogeny: None,
+        z: BigInt::from(-10),
+        cofactor: 1,
+    }
+}
+
+// secp256k1_XMD:SHA-256_SSWU_RO_, section 8.7, with the 3-isogeny of appendix E.1
+fn secp256k1() -> SswuSuite {
+    let field = FiniteField::new((BigInt::one() << 256) - (BigInt::one() << 32) - 977);
+    let isogenous = EllipticCurve::try_new(
+        field.clone(),
+        int("3f8731abdd661adca08a5558f0f5d272e953d363cb6f0e5d405447c01a444533"),
+        BigInt::from(1771),
+    )
+    .unwrap();
+    let map = IsogenyMap {
+        x_num: vec![
+            int("8e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38daaaaa8c7"),
+            int("07d3d4c80bc321d5b9f315cea7fd44c5d595d2fc0bf63b92dfff1044f17c6581"),
+            int("534c328d23f234e6e2a413deca25caece4506144037c40314ecbd0b53d9dd262"),
+            int("8e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38daaaaa88c"),
+        ],
+        x_den: vec![
+            int("d35771193d94918a9ca34ccbb7b640dd86cd409542f8487d9fe6b745781eb49b"),
+            int("edadc6f64383dc1df7c4b2d51b54225406d36b641f5e41bbc52a56612a8c6d14"),
+            BigInt::one(),
+        ],
+        y_num: vec![
+            int("4bda12f684bda12f684bda12f684bda12f684bda12f684bda12f684b8e38e23c"),
+            int("c75e0c32d5cb7c0fa9d0a54b12a0a6d5647ab046d686da6fdffc90fc201d71a3"),
+            int("29a6194691f91a73715209ef6512e576722830a201be2018a765e85a9ecee931"),
+            int("2f684bda12f684bda12f684bda12f684bda12f684bda12f684bda12f38e38d84"),
+        ],
+        y_den: vec![
+            int("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffff93b"),
+            int("7a06534bb8bdb49fd5e9e6632722c2989467c1bfc8e8d978dfb425d2685c2573"),
+            int("6484aa716545ca2cf3a70c3fa8fe337e0a3d21162f0d6299a7bf8192bfd2a76f"),
+            BigInt::one(),
+        ],
+    };
+    SswuSuite {
+        curve: EllipticCurve::new(field, BigInt::zero(), BigInt::from(7)),
+        isogeny: Some((isogenous, map)),
+        z: BigInt::from(-11),
+        cofactor: 1,
+    }
+}
+
+// curve25519_XMD:SHA-512_ELL2_RO_, section 8.5
+fn curve25519_suite() -> Elligator2Suite {
+    Elligator2Suite {
+        curve: curve25519(),
+        z: BigInt::from(2),
+        cofactor: 8,
+    }
+}
+
+fn check_vector(name: &str, point: &Point, x: &str, y: &str) {
+    println!("{}: {}", name, point);
+    assert_eq!(point, &Point::new(int(x), int(y)));
+}
+
+pub fn run() {
+    // Appendix K.1
+    let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+    let uniform_bytes = expand_message_xmd::<Sha256>(b"", dst, 0x20).unwrap();
+    assert_eq!(
+        uniform_bytes,
+        int("68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235")
+            .to_bytes_be()
+            .1
+    );
+    // The long-DST vector of K.1: 256 bytes, hashed down to 32 first
+    let long_dst = [
+        &b"QUUX-V01-CS02-with-expander-SHA256-128-long-DST-"[..],
+        &[b'1'; 208],
+    ]
+    .concat();
+    assert_eq!(
+        expand_message_xmd::<Sha256>(b"", &long_dst, 0x20).unwrap(),
+        int("e8dc0c8b686b7ef2074086fbdd2f30e3f8bfbd3bdf177f73f04b97ce618a3ed3")
+            .to_bytes_be()
+            .1
+    );
+    // 255 SHA-256 blocks are 8160 bytes
+    assert!(expand_message_xmd::<Sha256>(b"", dst, 8160).is_ok());
+    assert_eq!(
+        expand_message_xmd::<Sha256>(b"", dst, 8161),
+        Err(Error::OutputTooLong)
+    );
+
+    // Appendix J.1.1
+    let suite = p256();
+    let dst = b"QUUX-V01-CS02-with-P256_XMD:SHA-256_SSWU_RO_";
+    check_vector(
+        "P-256, msg = \"\"",
+        &suite.hash_to_curve::<Sha256>(b"", dst).unwrap(),
+        "2c15230b26dbc6fc9a37051158c95b79656e17a1a920b11394ca91c44247d3e4",
+        "8a7a74985cc5c776cdfe4b1f19884970453912e9d31528c060be9ab5c43e8415",
+    );
+    check_vector(
+        "P-256, msg = \"abc\"",
+        &suite.hash_to_curve::<Sha256>(b"abc", dst).unwrap(),
+        "0bb8b87485551aa43ed54f009230450b492fead5f1cc91658775dac4a3388a0f",
+        "5c41b3d0731a27a7b14bc0bf0ccded2d8751f83493404c84a88e71ffd424212e",
+    );
+
+    // Appendix J.8.1, through the 3-isogeny since secp256k1 has a = 0
+    let suite = secp256k1();
+    let dst = b"QUUX-V01-CS02-with-secp256k1_XMD:SHA-256_SSWU_RO_";
+    check_vector(
+        "secp256k1, msg = \"\"",
+        &suite.hash_to_curve::<Sha256>(b"", dst).unwrap(),
+        "c1cae290e291aee617ebaef1be6d73861479c48b841eaba9b7b5852ddfeb1346",
+        "64fa678e07ae116126f08b022a94af6de15985c996c3a91b64c406a960e51067",
+    );
+    check_vector(
+        "secp256k1, msg = \"abc\"",
+        &suite.hash_to_curve::<Sha256>(b"abc", dst).unwrap(),
+        "3377e01eab42db296b512293120c6cee72b6ecf9f9205760bd9ff11fb3cb2c4b",
+        "7f95890f33efebd1044d382a01b1bee0900fb6116f94688d487c6c7b9c8371f6",
+    );
+    // The isogeny takes points of E' to points of E
+    let (isogenous, map) = suite.isogeny.as_ref().unwrap();
+    for u in 1..20 {
+        let p = map_to_curve_simple_swu(isogenous, &suite.z, &BigInt::from(u)).unwrap();
+        assert!(suite
+            .curve
+            .is_on_curve(&map.map(&suite.curve.field, &p).unwrap()));
+    }
+
+    // Appendix J.5.1, including the field elements u0 and u1
+    let suite = curve25519_suite();
+    let dst = b"QUUX-V01-CS02-with-curve25519_XMD:SHA-512_ELL2_RO_";
+    let u = hash_to_field::<Sha512>(b"", dst, 2, suite.modulus(), SECURITY_BITS).unwrap();
+    assert_eq!(
+        u,
+        [
+            int("005fe8a7b8fef0a16c105e6cadf5a6740b3365e18692a9c05bfbb4d97f645a6a"),
+            int("1347edbec6a2b5d8c02e058819819bee177077c9d10a4ce165aab0fd0252261a"),
+        ]
+    );
+    let p = suite.hash_to_curve::<Sha512>(b"", dst).unwrap();
+    check_vector(
+        "curve25519, msg = \"\"",
+        &p,
+        "2de3780abb67e861289f5749d16d3e217ffa722192d16bbd9d1bfb9d112b98c0",
+        "3b5dc2a498941a1033d176567d457845637554a2fe7a3507d21abd1c1bd6e878",
+    );
+
+    // Clearing the cofactor 8 lands in the subgroup of prime order l, where the ladder
+    // gives [l]P = O. A single Elligator 2 output usually does not.
+    let l = (BigInt::one() << 252)
+        + BigInt::parse_bytes(b"27742317777372353535851937790883648493", 10).unwrap();
+    let in_subgroup = |p: &Point| {
+        let x = p.x().unwrap();
+        suite
+            .curve
+            .to_affine_x(&suite.curve.ladder(&l, x).unwrap())
+            .is_none()
+    };
+    assert!(in_subgroup(&p));
+    let q0 = suite.map_to_curve(&u[0]).unwrap();
+    assert!(!in_subgroup(&q0));
+    assert!(in_subgroup(&suite.clear_cofactor(&q0).unwrap()));
+    println!("RFC 9380 vectors match for P-256, secp256k1 and curve25519");
+}
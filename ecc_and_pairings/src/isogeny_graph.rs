@@ -0,0 +1,549 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::ToPrimitive;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+// Supersingular j-invariants all live in Fp2, and the l-isogenies between supersingular
+// curves are again supersingular, so for each l the supersingular j-invariants form a
+// finite (l + 1)-regular graph. We walk it with the classical modular polynomials: the
+// l-isogenous neighbours of j are the roots of Phi_l(j, Y).
+
+// c0 + c1 i in Fp2 = Fp[i]/(i^2 - beta)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Fp2 {
+    c0: u64,
+    c1: u64,
+}
+
+#[derive(Debug)]
+struct Field {
+    p: u64,
+    beta: u64, // The smallest non-square mod p
+}
+
+// Classical modular polynomials Phi_l(X, Y) as terms (c, i, k) of c X^i Y^k with i >= k.
+// Phi_l is symmetric, so each term with i > k also stands for c X^k Y^i. The coefficients
+// outgrow i128 from l = 5 on and are kept as decimal strings.
+type ModularPolynomial = &'static [(&'static str, u32, u32)];
+
+const PHI_2: ModularPolynomial = &[
+    ("1", 3, 0),
+    ("-1", 2, 2),
+    ("1488", 2, 1),
+    ("-162000", 2, 0),
+    ("40773375", 1, 1),
+    ("8748000000", 1, 0),
+    ("-157464000000000", 0, 0),
+];
+
+const PHI_3: ModularPolynomial = &[
+    ("1", 4, 0),
+    ("-1", 3, 3),
+    ("2232", 3, 2),
+    ("-1069956", 3, 1),
+    ("36864000", 3, 0),
+    ("2587918086", 2, 2),
+    ("8900222976000", 2, 1),
+    ("452984832000000", 2, 0),
+    ("-770845966336000000", 1, 1),
+    ("1855425871872000000000", 1, 0),
+];
+
+const PHI_5: ModularPolynomial = &[
+    ("1", 6, 0),
+    ("-1", 5, 5),
+    ("3720", 5, 4),
+    ("-4550940", 5, 3),
+    ("1665999364600", 4, 4),
+    ("2028551200", 5, 2),
+    ("107878928185336800", 4, 3),
+    ("-246683410950", 5, 1),
+    ("383083609779811215375", 4, 2),
+    ("-441206965512914835246100", 3, 3),
+    ("1963211489280", 5, 0),
+    ("128541798906828816384000", 4, 1),
+    ("26898488858380731577417728000", 3, 2),
+    ("1284733132841424456253440", 4, 0),
+    ("-192457934618928299655108231168000", 3, 1),
+    ("5110941777552418083110765199360000", 2, 2),
+    ("280244777828439527804321565297868800", 3, 0),
+    ("36554736583949629295706472332656640000", 2, 1),
+    ("6692500042627997708487149415015068467200", 2, 0),
+    ("-264073457076620596259715790247978782949376", 1, 1),
+    ("53274330803424425450420160273356509151232000", 1, 0),
+    ("141359947154721358697753474691071362751004672000", 0, 0),
+];
+
+// Position of a vertex in its l-isogeny volcano
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VolcanoLevel {
+    Surface,       // End(E) is maximal at l
+    Interior(u32), // That many levels below the surface
+    Floor,         // End(E) is as small as Z[pi] allows at l
+}
+
+impl fmt::Display for Fp2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.c1 == 0 {
+            write!(f, "{}", self.c0)
+        } else {
+            write!(f, "{} + {}i", self.c0, self.c1)
+        }
+    }
+}
+
+impl Field {
+    fn new(p: u64) -> Self {
+        let beta = (2..p).find(|b| (0..p).all(|x| x * x % p != *b)).unwrap();
+        Field { p, beta }
+    }
+
+    fn embed(&self, v: u64) -> Fp2 {
+        Fp2 {
+            c0: v % self.p,
+            c1: 0,
+        }
+    }
+
+    fn embed_signed(&self, v: &BigInt) -> Fp2 {
+        self.embed(v.mod_floor(&BigInt::from(self.p)).to_u64().unwrap())
+    }
+
+    fn zero(&self) -> Fp2 {
+        self.embed(0)
+    }
+
+    fn one(&self) -> Fp2 {
+        self.embed(1)
+    }
+
+    fn add(&self, a: &Fp2, b: &Fp2) -> Fp2 {
+        Fp2 {
+            c0: (a.c0 + b.c0) % self.p,
+            c1: (a.c1 + b.c1) % self.p,
+        }
+    }
+
+    fn sub(&self, a: &Fp2, b: &Fp2) -> Fp2 {
+        Fp2 {
+            c0: (a.c0 + self.p - b.c0) % self.p,
+            c1: (a.c1 + self.p - b.c1) % self.p,
+        }
+    }
+
+    fn mul(&self, a: &Fp2, b: &Fp2) -> Fp2 {
+        let p = self.p;
+        Fp2 {
+            c0: (a.c0 * b.c0 + a.c1 * b.c1 % p * self.beta) % p,
+            c1: (a.c0 * b.c1 + a.c1 * b.c0) % p,
+        }
+    }
+
+    fn pow(&self, a: &Fp2, mut exp: u64) -> Fp2 {
+        let mut base = *a;
+        let mut result = self.one();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(&result, &base);
+            }
+            base = self.mul(&base, &base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    fn elements(&self) -> impl Iterator<Item = Fp2> + '_ {
+        (0..self.p * self.p).map(|v| Fp2 {
+            c0: v % self.p,
+            c1: v / self.p,
+        })
+    }
+
+    // Coefficients, constant term first, of Phi_l(j, Y) as a polynomial in Y
+    fn specialise(&self, phi: ModularPolynomial, j: &Fp2) -> Vec<Fp2> {
+        let degree = phi.iter().map(|(_, i, _)| *i).max().unwrap() as usize;
+        let mut coeffs = vec![self.zero(); degree + 1];
+        for (c, i, k) in phi {
+            let c = self.embed_signed(&c.parse().unwrap());
+            let mut add_term = |x: u32, y: u32| {
+                let term = self.mul(&c, &self.pow(j, x as u64));
+                coeffs[y as usize] = self.add(&coeffs[y as usize], &term);
+            };
+            add_term(*i, *k);
+            if i != k {
+                add_term(*k, *i);
+            }
+        }
+        coeffs
+    }
+
+    // Roots of a polynomial over Fp2, repeated according to their multiplicity
+    fn roots(&self, poly: &[Fp2]) -> Vec<Fp2> {
+        self.roots_among(poly, self.elements())
+    }
+
+    // Roots in Fp only
+    fn base_roots(&self, poly: &[Fp2]) -> Vec<Fp2> {
+        self.roots_among(poly, (0..self.p).map(|v| self.embed(v)))
+    }
+
+    fn roots_among(&self, poly: &[Fp2], candidates: impl Iterator<Item = Fp2>) -> Vec<Fp2> {
+        let mut roots = Vec::new();
+        for x in candidates {
+            let mut poly = poly.to_vec();
+            loop {
+                // Synthetic division by Y - x
+                let mut quotient = vec![self.zero(); poly.len() - 1];
+                let mut carry = self.zero();
+                for k in (1..poly.len()).rev() {
+                    carry = self.add(&poly[k], &self.mul(&carry, &x));
+                    quotient[k - 1] = carry;
+                }
+                let remainder = self.add(&poly[0], &self.mul(&carry, &x));
+                if remainder != self.zero() || quotient.is_empty() {
+                    break;
+                }
+                roots.push(x);
+                poly = quotient;
+            }
+        }
+        roots
+    }
+
+    // y^2 = x^3 + ax + b with j-invariant j: y^2 = x^3 + 1 for j = 0, y^2 = x^3 + x for
+    // j = 1728 and y^2 = x^3 + 3k x + 2k (1728 - j) with k = j (1728 - j) otherwise
+    fn curve_with_j(&self, j: &Fp2) -> (Fp2, Fp2) {
+        let c1728 = self.embed(1728);
+        if *j == self.zero() {
+            (self.zero(), self.one())
+        } else if *j == c1728 {
+            (self.one(), self.zero())
+        } else {
+            let m = self.sub(&c1728, j);
+            let k = self.mul(j, &m);
+            (
+                self.mul(&self.embed(3), &k),
+                self.mul(&self.mul(&self.embed(2), &k), &m),
+            )
+        }
+    }
+
+    // Hasse invariant of y^2 = x^3 + ax + b: the coefficient of x^(p-1) in
+    // (x^3 + ax + b)^((p-1)/2). It vanishes exactly for supersingular curves.
+    fn is_supersingular(&self, j: &Fp2) -> bool {
+        let (a, b) = self.curve_with_j(j);
+        let f = [b, a, self.zero(), self.one()];
+        let mut power = vec![self.one()];
+        for _ in 0..(self.p - 1) / 2 {
+            let mut product = vec![self.zero(); power.len() + 3];
+            for (i, c) in power.iter().enumerate() {
+                for (k, d) in f.iter().enumerate() {
+                    product[i + k] = self.add(&product[i + k], &self.mul(c, d));
+                }
+            }
+            power = product;
+        }
+        power[self.p as usize - 1] == self.zero()
+    }
+
+    // Trace of Frobenius of a curve over Fp with j-invariant j in Fp. It is only defined up
+    // to sign, as the quadratic twist has the same j.
+    fn trace(&self, j: &Fp2) -> i64 {
+        let (a, b) = self.curve_with_j(j);
+        let mut order = self.p as i64 + 1;
+        for x in 0..self.p {
+            let x = self.embed(x);
+            let rhs = self.add(&self.mul(&self.add(&self.mul(&x, &x), &a), &x), &b);
+            if rhs == self.zero() {
+                continue;
+            }
+            if self.pow(&rhs, (self.p - 1) / 2) == self.one() {
+                order += 1;
+            } else {
+                order -= 1;
+            }
+        }
+        self.p as i64 + 1 - order
+    }
+
+    // The l-isogenous neighbours of j over Fp: the roots of Phi_l(j, Y) in Fp. None if
+    // Phi_l is not tabulated.
+    fn neighbours(&self, l: u64, j: &Fp2) -> Option<Vec<Fp2>> {
+        Some(self.base_roots(&self.specialise(modular_polynomial(l)?, j)))
+    }
+}
+
+// Only Phi_2, Phi_3 and Phi_5 are tabulated
+fn modular_polynomial(l: u64) -> Option<ModularPolynomial> {
+    match l {
+        2 => Some(PHI_2),
+        3 => Some(PHI_3),
+        5 => Some(PHI_5),
+        _ => None,
+    }
+}
+
+// The supersingular j-invariants: find one in Fp by the Hasse invariant, then walk the
+// 2-isogeny graph, which is connected, from it
+fn supersingular_j_invariants(field: &Field) -> Vec<Fp2> {
+    let start = (0..field.p)
+        .map(|j| field.embed(j))
+        .find(|j| field.is_supersingular(j))
+        .unwrap();
+
+    let mut found = vec![start];
+    let mut queue = VecDeque::from([start]);
+    while let Some(j) = queue.pop_front() {
+        for neighbour in field.roots(&field.specialise(PHI_2, &j)) {
+            if !found.contains(&neighbour) {
+                found.push(neighbour);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+// Adjacency lists of the supersingular l-isogeny graph. Multiple edges are repeated.
+fn isogeny_graph(field: &Field, l: u64, vertices: &[Fp2]) -> Option<BTreeMap<Fp2, Vec<Fp2>>> {
+    let phi = modular_polynomial(l)?;
+    Some(
+        vertices
+            .iter()
+            .map(|j| (*j, field.roots(&field.specialise(phi, j))))
+            .collect(),
+    )
+}
+
+// Splits a discriminant as f^2 D_K with D_K fundamental, returning (f, D_K)
+fn conductor(disc: i64) -> (i64, i64) {
+    (1..)
+        .take_while(|f| f * f <= disc.abs())
+        .filter(|f| disc % (f * f) == 0 && (disc / (f * f)).rem_euclid(4) <= 1)
+        .map(|f| (f, disc / (f * f)))
+        .last()
+        .unwrap()
+}
+
+fn valuation(mut n: i64, l: u64) -> u32 {
+    let mut v = 0;
+    while n % l as i64 == 0 {
+        n /= l as i64;
+        v += 1;
+    }
+    v
+}
+
+// The depth of the l-volcano of an ordinary j is the l-adic valuation of the conductor of
+// Z[pi] in End(E) (x) Q, read off t^2 - 4p = f^2 D_K
+fn volcano_depth(field: &Field, l: u64, j: &Fp2) -> u32 {
+    let t = field.trace(j);
+    valuation(conductor(t * t - 4 * field.p as i64).0, l)
+}
+
+// Kronecker symbol (D / l) for a prime l
+fn kronecker(d: i64, l: u64) -> i64 {
+    if l == 2 {
+        return match d.rem_euclid(8) {
+            1 | 7 => 1,
+            3 | 5 => -1,
+            _ => 0,
+        };
+    }
+    let l = l as i64;
+    match BigInt::from(d.rem_euclid(l)).modpow(&BigInt::from((l - 1) / 2), &BigInt::from(l)) {
+        v if v == BigInt::from(1) => 1,
+        v if v == BigInt::from(0) => 0,
+        _ => -1,
+    }
+}
+
+// Distance from j down to the floor of its l-volcano. Floor vertices have at most two
+// neighbours and all others have l + 1. At most two edges from j lead up or along the
+// surface, so of three walks without backtracking at least one descends and keeps
+// descending; the first walk to hit the floor has taken the shortest route.
+fn distance_to_floor(field: &Field, l: u64, j: &Fp2) -> Option<u32> {
+    let neighbours = field.neighbours(l, j)?;
+    if neighbours.len() <= 2 {
+        return Some(0);
+    }
+
+    let mut walks: Vec<(Fp2, Fp2)> = neighbours.iter().take(3).map(|n| (*j, *n)).collect();
+    let mut steps = 1;
+    loop {
+        let mut next_walks = Vec::new();
+        for (previous, current) in &walks {
+            let mut next = field.neighbours(l, current)?;
+            if next.len() <= 2 {
+                return Some(steps);
+            }
+            let back = next.iter().position(|n| n == previous).unwrap();
+            next.remove(back);
+            next_walks.push((*current, next[0]));
+        }
+        walks = next_walks;
+        steps += 1;
+    }
+}
+
+// Classifies an ordinary j by its level in the l-volcano: the surface is level 0 and the
+// floor level depth. Returns the level as a number too.
+fn volcano_level(field: &Field, l: u64, j: &Fp2) -> Option<(u32, VolcanoLevel)> {
+    let depth = volcano_depth(field, l, j);
+    let level = depth - distance_to_floor(field, l, j)?.min(depth);
+    let label = if level == 0 {
+        VolcanoLevel::Surface
+    } else if level == depth {
+        VolcanoLevel::Floor
+    } else {
+        VolcanoLevel::Interior(level)
+    };
+    Some((level, label))
+}
+
+// The connected component of j in the l-isogeny graph over Fp
+fn volcano_vertices(field: &Field, l: u64, j: &Fp2) -> Option<Vec<Fp2>> {
+    let mut found = vec![*j];
+    let mut queue = VecDeque::from([*j]);
+    while let Some(v) = queue.pop_front() {
+        for n in field.neighbours(l, &v)? {
+            if !found.contains(&n) {
+                found.push(n);
+                queue.push_back(n);
+            }
+        }
+    }
+    Some(found)
+}
+
+// Walks the whole l-volcano of j over Fp, classifying every vertex and checking the shape:
+// levels of neighbours differ by at most one, every vertex below the surface has exactly
+// one parent, floor vertices have at most two neighbours, and surface vertices have
+// 1 + (D_K / l) horizontal neighbours
+fn check_volcano(field: &Field, l: u64, j: &Fp2) {
+    let t = field.trace(j);
+    let (_, fundamental) = conductor(t * t - 4 * field.p as i64);
+    let depth = volcano_depth(field, l, j);
+
+    let levels: BTreeMap<Fp2, u32> = volcano_vertices(field, l, j)
+        .unwrap()
+        .into_iter()
+        .map(|v| (v, volcano_level(field, l, &v).unwrap().0))
+        .collect();
+
+    for (v, level) in &levels {
+        // Isogenous curves have the same number of points up to twisting
+        assert_eq!(field.trace(v).abs(), t.abs());
+        let neighbours = field.neighbours(l, v).unwrap();
+        let neighbour_levels: Vec<u32> = neighbours.iter().map(|n| levels[n]).collect();
+        assert!(neighbour_levels.iter().all(|n| n.abs_diff(*level) <= 1));
+
+        if *level == depth {
+            assert!(neighbours.len() <= 2);
+        } else {
+            assert_eq!(neighbours.len() as u64, l + 1);
+        }
+        if *level > 0 {
+            assert_eq!(neighbour_levels.iter().filter(|n| **n < *level).count(), 1);
+        } else {
+            let horizontal = neighbour_levels.iter().filter(|n| **n == 0).count() as i64;
+            assert_eq!(horizontal, 1 + kronecker(fundamental, l));
+        }
+    }
+
+    let mut per_level = vec![0; depth as usize + 1];
+    for level in levels.values() {
+        per_level[*level as usize] += 1;
+    }
+    let surface: Vec<String> = levels
+        .iter()
+        .filter(|(_, level)| **level == 0)
+        .map(|(v, _)| v.to_string())
+        .collect();
+    println!(
+        "  {}-volcano of j = {} over F{}: t = {}, D_K = {}, depth {}, vertices per level {:?}, surface [{}]",
+        l,
+        j,
+        field.p,
+        t,
+        fundamental,
+        depth,
+        per_level,
+        surface.join(", ")
+    );
+    println!(
+        "    j = {} is {:?}",
+        j,
+        volcano_level(field, l, j).unwrap().1
+    );
+}
+
+// Number of supersingular j-invariants in characteristic p
+fn expected_count(p: u64) -> usize {
+    let extra = match p % 12 {
+        1 => 0,
+        5 | 7 => 1,
+        _ => 2,
+    };
+    (p / 12 + extra) as usize
+}
+
+pub fn run() {
+    for p in [59, 103] {
+        let field = Field::new(p);
+        let js = supersingular_j_invariants(&field);
+        println!(
+            "p = {}: {} supersingular j-invariants (i^2 = {})",
+            p,
+            js.len(),
+            field.beta
+        );
+
+        assert_eq!(js.len(), expected_count(p));
+        assert!(js.iter().all(|j| field.is_supersingular(j)));
+        // They are closed under Frobenius j -> j^p
+        assert!(js.iter().all(|j| js.contains(&field.pow(j, p))));
+
+        for l in [2, 3, 5] {
+            let graph = isogeny_graph(&field, l, &js).unwrap();
+            println!("  {}-isogeny graph:", l);
+            for (j, neighbours) in &graph {
+                // Every vertex has l + 1 neighbours counted with multiplicity, all supersingular
+                assert_eq!(neighbours.len() as u64, l + 1);
+                assert!(neighbours.iter().all(|n| graph.contains_key(n)));
+                let neighbours: Vec<String> = neighbours.iter().map(|n| n.to_string()).collect();
+                println!("    {} -> [{}]", j, neighbours.join(", "));
+            }
+        }
+
+        // Phi_7 is not tabulated, so there is no 7-isogeny graph to build
+        assert!(isogeny_graph(&field, 7, &js).is_none());
+        assert!(field.neighbours(7, &js[0]).is_none());
+
+        // j = 1728 is supersingular for p = 3 mod 4 and j = 0 for p = 2 mod 3
+        assert_eq!(js.contains(&field.embed(1728)), p % 4 == 3);
+        assert_eq!(js.contains(&field.zero()), p % 3 == 2);
+    }
+
+    // Ordinary curves (t != 0 for p > 3): for each l take the first j over Fp with a
+    // volcano of the given depth. Volcanoes through j = 0 or 1728 are skipped since their
+    // extra automorphisms bend the shape. t^2 - 4p is too small for 5^4 to divide it, so
+    // the 5-volcano has depth 1.
+    let field = Field::new(1009);
+    let special = [field.zero(), field.embed(1728)];
+    for (l, depth) in [(2, 2), (3, 2), (5, 1)] {
+        let j = (0..field.p)
+            .map(|j| field.embed(j))
+            .filter(|j| field.trace(j) != 0)
+            .filter(|j| volcano_depth(&field, l, j) == depth)
+            .find(|j| {
+                let vertices = volcano_vertices(&field, l, j).unwrap();
+                !special.iter().any(|s| vertices.contains(s))
+            })
+            .unwrap();
+        check_volcano(&field, l, &j);
+    }
+}
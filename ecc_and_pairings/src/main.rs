@@ -2,28 +2,50 @@ mod char_frob;
 mod e_count_points;
 mod e_over_f23_generalised;
 mod e_over_finite_fields;
+mod edwards;
 mod elliptic_cirve_divison_poly_and_root_finding;
+mod embedding_degree;
 mod endomorphis_extension_field;
 mod eover_q;
 mod eover_real_field;
+mod error;
+mod flower;
+mod hash_to_curve;
+mod isogeny_graph;
+mod montgomery;
 mod mul_by_m;
+mod optimal_ate;
+mod point;
 mod projective_point;
 mod r_torsion_curve_flower;
 mod reduction;
+mod scalar_mul;
+mod sec1;
 mod twisted_curves;
+mod twists;
+mod weierstrass;
 use char_frob::run as char_frob;
 use e_count_points::run as e_count_points;
 use e_over_f23_generalised::run as e_over_f23_generalised;
 use e_over_finite_fields::run as e_over_finite_fields;
+use edwards::run as edwards;
 use elliptic_cirve_divison_poly_and_root_finding::run as elliptic_cirve_divison_poly_and_root_finding;
+use embedding_degree::run as embedding_degree;
 use endomorphis_extension_field::run as endomorphis_extension_field;
 use eover_q::run as addElipticCurvePoints;
 use eover_real_field::run as addOverRings;
+use hash_to_curve::run as hash_to_curve;
+use isogeny_graph::run as isogeny_graph;
+use montgomery::run as montgomery;
 use mul_by_m::run as mul_by_m;
+use optimal_ate::run as optimal_ate;
 use projective_point::run as projective_point;
 use r_torsion_curve_flower::run as r_torsion_curve_flower;
 use reduction::run as reduction;
+use scalar_mul::run as scalar_mul;
 use twisted_curves::run as twisted_curves;
+use twists::run as twists;
+use weierstrass::run as weierstrass;
 fn main() {
     println!("Some cryptography ....");
     println!("\nAdd Eliptic curve points...");
@@ -38,9 +60,24 @@ fn main() {
     println!("\n P+Q using generalised formula.....");
     e_over_f23_generalised();
 
+    println!("\n Long Weierstrass form in any characteristic.....");
+    weierstrass();
+
+    println!("\n wNAF and windowed scalar multiplication.....");
+    scalar_mul();
+
     println!("\n [m]P.....");
     mul_by_m();
 
+    println!("\n Montgomery curves and the x-only ladder.....");
+    montgomery();
+
+    println!("\n Twisted Edwards curves with complete addition.....");
+    edwards();
+
+    println!("\n Hashing to curves (RFC 9380).....");
+    hash_to_curve();
+
     println!("\n Counting Points in a field...");
     e_count_points();
 
@@ -54,13 +91,24 @@ fn main() {
     // println!("\n Division polynomial calculation and roots.....");
     // elliptic_cirve_divison_poly_and_root_finding();
 
-    // TODO: Bug fix can fail on some runs due to randome numbers being used causing pancics sometimes or sometimes computation keeps going if random number causes a huge amount of compuation.)
-    // println!("Reduction of divisors.... ");
-    // reduction();
+    println!("\n Reduction of divisors.....");
+    reduction();
 
-    // println!("\n r torsion flowers...");
-    // r_torsion_curve_flower();
+    println!("\n r torsion flowers...");
+    r_torsion_curve_flower();
 
     println!("\n Twisted curves....");
     twisted_curves();
+
+    println!("\n Quadratic, cubic, quartic and sextic twists....");
+    twists();
+
+    println!("\n Optimal ate pairing on BN254 and BLS12-381....");
+    optimal_ate();
+
+    println!("\n Embedding degree and pairing-friendliness....");
+    embedding_degree();
+
+    println!("\n Supersingular isogeny graphs....");
+    isogeny_graph();
 }
@@ -0,0 +1,397 @@
+use crate::error::Error;
+use crate::mul_by_m::{EllipticCurve, FiniteField, Point};
+use num_bigint::BigInt;
+use num_traits::{Euclid, One, Zero};
+
+// Montgomery curves
+//
+//   M: By^2 = x^3 + Ax^2 + x,   B(A^2 - 4) != 0
+//
+// The x-coordinate of P + Q only depends on x(P), x(Q) and x(P - Q), so [k]P can be computed
+// from x(P) alone with the Montgomery ladder, which does the same work for every bit of k.
+
+#[derive(Clone, Debug)]
+pub struct MontgomeryCurve {
+    pub field: FiniteField,
+    pub a: BigInt,
+    pub b: BigInt,
+}
+
+// x = X / Z in projective coordinates, Z = 0 being the point at infinity
+#[derive(Clone, Debug)]
+pub struct XPoint {
+    x: BigInt,
+    z: BigInt,
+}
+
+impl XPoint {
+    fn from_affine(x: &BigInt) -> Self {
+        XPoint {
+            x: x.clone(),
+            z: BigInt::one(),
+        }
+    }
+
+    fn infinity() -> Self {
+        XPoint {
+            x: BigInt::one(),
+            z: BigInt::zero(),
+        }
+    }
+}
+
+impl MontgomeryCurve {
+    pub fn new(field: FiniteField, a: BigInt, b: BigInt) -> Self {
+        MontgomeryCurve { field, a, b }
+    }
+
+    // new(), refusing B = 0 and A = +-2, where x^3 + Ax^2 + x = x(x +- 1)^2 has a double root
+    pub fn try_new(field: FiniteField, a: BigInt, b: BigInt) -> Result<Self, Error> {
+        let curve = MontgomeryCurve::new(field, a, b);
+        let f = &curve.field;
+        let a2_minus_4 = f.sub(&f.mul(&curve.a, &curve.a), &BigInt::from(4));
+        if f.mul(&curve.b, &a2_minus_4).is_zero() {
+            return Err(Error::SingularCurve);
+        }
+        Ok(curve)
+    }
+
+    // The affine point (x, y), if it satisfies the curve equation
+    pub fn point(&self, x: BigInt, y: BigInt) -> Result<Point, Error> {
+        let p = Point::new(x.rem_euclid(&self.field.p), y.rem_euclid(&self.field.p));
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
+    pub fn is_on_curve(&self, p: &Point) -> bool {
+        let Some((x, y)) = p.coordinates() else {
+            return true;
+        };
+        let f = &self.field;
+        let x2 = f.mul(x, x);
+        let rhs = f.add(&f.add(&f.mul(&x2, x), &f.mul(&self.a, &x2)), x);
+        f.mul(&self.b, &f.mul(y, y)) == rhs
+    }
+
+    // The constant (A - 2) / 4 of the doubling formula, 121665 for Curve25519
+    fn a24(&self) -> Result<BigInt, Error> {
+        let f = &self.field;
+        f.div(&f.sub(&self.a, &BigInt::from(2)), &BigInt::from(4))
+    }
+
+    // x([2]P) = (X + Z)^2 (X - Z)^2 / 4XZ((X + Z)^2 + a24 * 4XZ)
+    fn x_double(&self, p: &XPoint, a24: &BigInt) -> XPoint {
+        let f = &self.field;
+        let aa = f.mul(&f.add(&p.x, &p.z), &f.add(&p.x, &p.z));
+        let bb = f.mul(&f.sub(&p.x, &p.z), &f.sub(&p.x, &p.z));
+        let e = f.sub(&aa, &bb); // 4XZ
+        XPoint {
+            x: f.mul(&aa, &bb),
+            z: f.mul(&e, &f.add(&aa, &f.mul(a24, &e))),
+        }
+    }
+
+    // Differential addition: x(P + Q) from x(P), x(Q) and x(P - Q)
+    fn x_add(&self, p: &XPoint, q: &XPoint, difference: &XPoint) -> XPoint {
+        let f = &self.field;
+        let da = f.mul(&f.sub(&q.x, &q.z), &f.add(&p.x, &p.z));
+        let cb = f.mul(&f.add(&q.x, &q.z), &f.sub(&p.x, &p.z));
+        let sum = f.add(&da, &cb);
+        let diff = f.sub(&da, &cb);
+        XPoint {
+            x: f.mul(&difference.z, &f.mul(&sum, &sum)),
+            z: f.mul(&difference.x, &f.mul(&diff, &diff)),
+        }
+    }
+
+    // Montgomery ladder: keeps (R0, R1) = ([m]P, [m + 1]P) for the leading bits m of k, so
+    // R1 - R0 = P throughout. Every bit costs one differential addition and one doubling,
+    // with the bit only deciding which of the two registers is which. (BigInt arithmetic is
+    // not constant time, so this only shows the shape of the computation.)
+    pub fn ladder(&self, k: &BigInt, x: &BigInt) -> Result<XPoint, Error> {
+        let a24 = self.a24()?;
+        let p = XPoint::from_affine(x);
+        let mut r0 = XPoint::infinity();
+        let mut r1 = p.clone();
+        let mut swap = false;
+
+        for i in (0..k.bits()).rev() {
+            let bit = k.bit(i);
+            swap ^= bit;
+            if swap {
+                std::mem::swap(&mut r0, &mut r1);
+            }
+            swap = bit;
+
+            r1 = self.x_add(&r0, &r1, &p);
+            r0 = self.x_double(&r0, &a24);
+        }
+        if swap {
+            std::mem::swap(&mut r0, &mut r1);
+        }
+        Ok(r0)
+    }
+
+    // The affine chord-and-tangent law: with slope l through P and Q (or the tangent at P),
+    // x3 = Bl^2 - A - x1 - x2 and y3 = l(x1 - x3) - y1
+    pub fn add(&self, p: &Point, q: &Point) -> Result<Point, Error> {
+        let ((x1, y1), (x2, y2)) = match (p.coordinates(), q.coordinates()) {
+            (None, _) => return Ok(q.clone()),
+            (_, None) => return Ok(p.clone()),
+            (Some(p), Some(q)) => (p, q),
+        };
+        let f = &self.field;
+        let slope = if x1 != x2 {
+            f.div(&f.sub(y2, y1), &f.sub(x2, x1))?
+        } else if y1 == y2 && !y1.is_zero() {
+            // l = (3x^2 + 2Ax + 1) / 2By
+            let numerator = f.add(
+                &f.add(
+                    &f.mul(&BigInt::from(3), &f.mul(x1, x1)),
+                    &f.mul(&BigInt::from(2), &f.mul(&self.a, x1)),
+                ),
+                &BigInt::one(),
+            );
+            f.div(&numerator, &f.mul(&BigInt::from(2), &f.mul(&self.b, y1)))?
+        } else {
+            // Q = -P, including doubling a point of order 2
+            return Ok(Point::Infinity);
+        };
+        let x3 = f.sub(
+            &f.sub(&f.sub(&f.mul(&self.b, &f.mul(&slope, &slope)), &self.a), x1),
+            x2,
+        );
+        let y3 = f.sub(&f.mul(&slope, &f.sub(x1, &x3)), y1);
+        Ok(Point::new(x3, y3))
+    }
+
+    // x = X / Z, None for the point at infinity (or a Z with no inverse, if p is not prime)
+    pub fn to_affine_x(&self, p: &XPoint) -> Option<BigInt> {
+        self.field.div(&p.x, &p.z).ok()
+    }
+
+    // (x, y) -> (x / B + A / 3B, y / B) takes M to
+    //   y^2 = x^3 + (3 - A^2) / 3B^2 x + (2A^3 - 9A) / 27B^3
+    pub fn to_weierstrass(&self) -> Result<EllipticCurve, Error> {
+        let f = &self.field;
+        let a2 = f.mul(&self.a, &self.a);
+        let b2 = f.mul(&self.b, &self.b);
+        let a = f.div(&f.sub(&BigInt::from(3), &a2), &f.mul(&BigInt::from(3), &b2))?;
+        let b = f.div(
+            &f.sub(
+                &f.mul(&BigInt::from(2), &f.mul(&a2, &self.a)),
+                &f.mul(&BigInt::from(9), &self.a),
+            ),
+            &f.mul(&BigInt::from(27), &f.mul(&b2, &self.b)),
+        )?;
+        Ok(EllipticCurve::new(f.clone(), a, b))
+    }
+
+    pub fn map_to_weierstrass(&self, p: &Point) -> Result<Point, Error> {
+        let Some((x, y)) = p.coordinates() else {
+            return Ok(Point::Infinity);
+        };
+        let f = &self.field;
+        let shift = f.div(&self.a, &f.mul(&BigInt::from(3), &self.b))?;
+        Ok(Point::new(
+            f.add(&f.div(x, &self.b)?, &shift),
+            f.div(y, &self.b)?,
+        ))
+    }
+
+    // (x, y) -> (Bx - A / 3, By)
+    fn map_from_weierstrass(&self, p: &Point) -> Result<Point, Error> {
+        let Some((x, y)) = p.coordinates() else {
+            return Ok(Point::Infinity);
+        };
+        let f = &self.field;
+        let shift = f.div(&self.a, &BigInt::from(3))?;
+        Ok(Point::new(
+            f.sub(&f.mul(&self.b, x), &shift),
+            f.mul(&self.b, y),
+        ))
+    }
+
+    // A short Weierstrass curve has a Montgomery model when x^3 + ax + b has a root alpha
+    // and 3 alpha^2 + a = 1 / s^2 is a square. Then A = 3 alpha s and B = s. Both are found
+    // by search, so this is only for small fields.
+    fn from_weierstrass(curve: &EllipticCurve) -> Option<Self> {
+        let f = &curve.field;
+        let elements = || field_elements(&f.p);
+        let alpha = elements().find(|x| {
+            let x3 = f.mul(&f.mul(x, x), x);
+            f.add(&f.add(&x3, &f.mul(&curve.a, x)), &curve.b).is_zero()
+        })?;
+        let t = f.add(&f.mul(&BigInt::from(3), &f.mul(&alpha, &alpha)), &curve.a);
+        let root = elements().find(|r| !r.is_zero() && f.mul(r, r) == t)?;
+        let s = f.inv(&root).ok()?;
+        Some(MontgomeryCurve::new(
+            f.clone(),
+            f.mul(&BigInt::from(3), &f.mul(&alpha, &s)),
+            s,
+        ))
+    }
+}
+
+fn field_elements(p: &BigInt) -> impl Iterator<Item = BigInt> + '_ {
+    std::iter::successors(Some(BigInt::zero()), |x| Some(x + 1)).take_while(move |x| x < p)
+}
+
+// Compares the ladder on x(P) with repeated affine additions on the Weierstrass model. The
+// ladder has no affine x for [k]P = O, and neither does the affine chain.
+fn check_ladder(curve: &MontgomeryCurve, p: &Point, max_k: u64) {
+    assert!(curve.is_on_curve(p));
+    let weierstrass = curve.to_weierstrass().unwrap();
+    let wp = curve.map_to_weierstrass(p).unwrap();
+    assert!(weierstrass.is_on_curve(&wp));
+    let back = curve.map_from_weierstrass(&wp).unwrap();
+    assert_eq!(back, *p);
+
+    let x = p.x().expect("P is an affine point");
+    let mut multiple = wp.clone();
+    let mut montgomery_multiple = p.clone();
+    for k in 1..=max_k {
+        let expected = curve.map_from_weierstrass(&multiple).unwrap();
+        assert!(curve.is_on_curve(&expected));
+        // Affine addition in Montgomery form agrees too
+        assert_eq!(montgomery_multiple, expected);
+        montgomery_multiple = curve.add(&montgomery_multiple, p).unwrap();
+        assert_eq!(
+            curve.to_affine_x(&curve.ladder(&BigInt::from(k), x).unwrap()),
+            expected.x().cloned()
+        );
+        multiple = weierstrass.add(&multiple, &wp).unwrap();
+    }
+}
+
+// RFC 7748: scalars are little-endian with the low 3 bits and the top bit cleared and
+// bit 254 set, u-coordinates are little-endian with the top bit ignored
+fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+    let mut u = *u;
+    u[31] &= 127;
+
+    let curve = curve25519();
+    let k = BigInt::from_bytes_le(num_bigint::Sign::Plus, &k);
+    let u = BigInt::from_bytes_le(num_bigint::Sign::Plus, &u);
+    let result = curve
+        .to_affine_x(&curve.ladder(&k, &u).unwrap())
+        .unwrap_or_else(BigInt::zero);
+
+    let mut out = [0u8; 32];
+    let (_, bytes) = result.to_bytes_le();
+    out[..bytes.len()].copy_from_slice(&bytes);
+    out
+}
+
+// y^2 = x^3 + 486662x^2 + x over p = 2^255 - 19
+pub fn curve25519() -> MontgomeryCurve {
+    let p = (BigInt::one() << 255) - 19;
+    MontgomeryCurve::new(FiniteField::new(p), BigInt::from(486662), BigInt::one())
+}
+
+fn hex32(s: &str) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+    }
+    out
+}
+
+pub fn run() {
+    // A small curve over F1019, compared against the affine Weierstrass group law
+    let field = FiniteField::new(BigInt::from(1019));
+    let curve =
+        MontgomeryCurve::try_new(field.clone(), BigInt::from(117), BigInt::from(5)).unwrap();
+    let weierstrass = curve.to_weierstrass().unwrap();
+    println!(
+        "{}y^2 = x^3 + {}x^2 + x over F1019 is y^2 = x^3 + {}x + {}",
+        curve.b, curve.a, weierstrass.a, weierstrass.b
+    );
+    let mut checked = 0;
+    for x in field_elements(&field.p).step_by(37) {
+        // 1019 = 3 mod 4, so square roots are powers
+        let rhs = field
+            .div(
+                &field.add(&field.mul(&field.mul(&x, &x), &field.add(&x, &curve.a)), &x),
+                &curve.b,
+            )
+            .unwrap();
+        let y = rhs.modpow(&BigInt::from(255), &field.p);
+        let on_x_axis = y.is_zero();
+        let p = Point::new(x, y);
+        if !on_x_axis && curve.is_on_curve(&p) {
+            check_ladder(&curve, &p, 60);
+            checked += 1;
+        }
+    }
+    println!("Ladder agrees with double-and-add on {} points", checked);
+    assert!(checked > 5);
+
+    // ...and the curve we get back from its Weierstrass model has the same x-only arithmetic
+    let back = MontgomeryCurve::from_weierstrass(&weierstrass).unwrap();
+    println!(
+        "Montgomery model recovered from the Weierstrass curve: {}y^2 = x^3 + {}x^2 + x",
+        back.b, back.a
+    );
+    assert_eq!(back.to_weierstrass(), Ok(weierstrass));
+
+    // The [m]P curve y^2 = x^3 - 3x - 3 over F1021 has no point of order 2
+    let fq = FiniteField::new(BigInt::from(1021));
+    let e = EllipticCurve::new(fq, BigInt::from(-3), BigInt::from(-3));
+    assert!(MontgomeryCurve::from_weierstrass(&e).is_none());
+
+    // Curve25519 with the base point u = 9
+    let curve = curve25519();
+    let y = BigInt::parse_bytes(
+        b"14781619447589544791020593568409986887264606134616475288964881837755586237401",
+        10,
+    )
+    .unwrap();
+    let base = curve.point(BigInt::from(9), y.clone()).unwrap();
+    check_ladder(&curve, &base, 30);
+    assert_eq!(curve.point(BigInt::from(9), y + 1), Err(Error::NotOnCurve));
+
+    // A = 2 gives y^2 = x(x + 1)^2, which is singular at (-1, 0)
+    assert_eq!(
+        MontgomeryCurve::try_new(curve.field.clone(), BigInt::from(2), BigInt::one()).err(),
+        Some(Error::SingularCurve)
+    );
+
+    // RFC 7748 section 5.2 and section 6.1
+    let out = x25519(
+        &hex32("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4"),
+        &hex32("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c"),
+    );
+    assert_eq!(
+        out,
+        hex32("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552")
+    );
+
+    let mut nine = [0u8; 32];
+    nine[0] = 9;
+    let alice = hex32("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+    let bob = hex32("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+    let alice_public = x25519(&alice, &nine);
+    let bob_public = x25519(&bob, &nine);
+    assert_eq!(
+        alice_public,
+        hex32("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+    );
+    assert_eq!(
+        bob_public,
+        hex32("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+    );
+    let shared = x25519(&alice, &bob_public);
+    assert_eq!(shared, x25519(&bob, &alice_public));
+    assert_eq!(
+        shared,
+        hex32("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+    );
+    println!("X25519 shared secret matches RFC 7748");
+}
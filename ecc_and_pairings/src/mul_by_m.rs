@@ -1,48 +1,86 @@
+use crate::error::Error;
+use crate::sec1;
 use num_bigint::BigInt;
 use num_traits::{Euclid, ToBytes};
-use num_traits::{One, Zero};
+use num_traits::{One, Signed, Zero};
 use std::ops::Rem;
 
 #[derive(Clone, Debug, PartialEq)]
-struct FiniteField {
-    p: BigInt,
+pub struct FiniteField {
+    pub p: BigInt,
 }
 
 impl FiniteField {
-    fn new(p: BigInt) -> Self {
+    pub fn new(p: BigInt) -> Self {
         FiniteField { p }
     }
 
-    fn add(&self, a: &BigInt, b: &BigInt) -> BigInt {
+    // new(), refusing a composite p, where some non-zero elements have no inverse
+    pub fn try_new(p: BigInt) -> Result<Self, Error> {
+        if !is_probable_prime(&p) {
+            return Err(Error::CompositeModulus);
+        }
+        Ok(FiniteField::new(p))
+    }
+
+    pub fn add(&self, a: &BigInt, b: &BigInt) -> BigInt {
         (a + b).rem_euclid(&self.p)
     }
 
-    fn sub(&self, a: &BigInt, b: &BigInt) -> BigInt {
+    pub fn sub(&self, a: &BigInt, b: &BigInt) -> BigInt {
         (a - b + &self.p).rem_euclid(&self.p)
     }
 
-    fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+    pub fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
         (a * b).rem_euclid(&self.p)
     }
 
-    fn inv(&self, a: &BigInt) -> Option<BigInt> {
+    pub fn inv(&self, a: &BigInt) -> Result<BigInt, Error> {
         let (g, x, _) = extended_gcd(a, &self.p);
         if g.is_one() {
-            Some(x.rem_euclid(&self.p))
+            Ok(x.rem_euclid(&self.p))
         } else {
-            None
+            Err(Error::NotInvertible)
         }
     }
 
-    fn div(&self, a: &BigInt, b: &BigInt) -> Option<BigInt> {
+    pub fn div(&self, a: &BigInt, b: &BigInt) -> Result<BigInt, Error> {
         self.inv(b).map(|b_inv| self.mul(a, &b_inv))
     }
 
-    fn neg(&self, a: &BigInt) -> BigInt {
+    pub fn neg(&self, a: &BigInt) -> BigInt {
         (&self.p - a).rem_euclid(&self.p)
     }
 }
 
+// Miller-Rabin with the first twelve primes as bases: deterministic below 3.3 * 10^24, and
+// wrong beyond that with probability at most 4^-12
+fn is_probable_prime(n: &BigInt) -> bool {
+    let bases = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37].map(BigInt::from);
+    if n < &bases[0] {
+        return false;
+    }
+    if let Some(base) = bases.iter().find(|base| (n % *base).is_zero()) {
+        return n == base;
+    }
+    let n_minus_1: BigInt = n - 1;
+    let s = n_minus_1.trailing_zeros().unwrap();
+    let d = &n_minus_1 >> s;
+    bases.iter().all(|base| {
+        let mut x = base.modpow(&d, n);
+        if x.is_one() || x == n_minus_1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = x.modpow(&BigInt::from(2), n);
+            if x == n_minus_1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
 fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
     // Bézout's identity
     // a⋅x+b⋅y=gcd(a,b)
@@ -57,27 +95,141 @@ fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct EllipticCurve {
-    field: FiniteField,
-    a: BigInt,
-    b: BigInt,
+// How points are represented during scalar multiplication
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coordinates {
+    Affine,
+    // (X : Y : Z) for (X / Z^2, Y / Z^3): no inversions, cheap doubling
+    Jacobian,
+    // (X : Y : Z : aZ^4): Jacobian with aZ^4 cached, so doubling never multiplies by a
+    ModifiedJacobian,
 }
 
 #[derive(Clone, Debug, PartialEq)]
-struct Point {
+pub struct EllipticCurve {
+    pub field: FiniteField,
+    pub a: BigInt,
+    pub b: BigInt,
+    pub coordinates: Coordinates,
+}
+
+pub type Point = crate::point::Point<BigInt>;
+
+// Z = 0 is the point at infinity. `az4` is only kept in modified Jacobian coordinates.
+#[derive(Clone, Debug)]
+struct JacobianPoint {
     x: BigInt,
     y: BigInt,
+    z: BigInt,
+    az4: Option<BigInt>,
 }
 
 impl EllipticCurve {
-    fn new(field: FiniteField, a: BigInt, b: BigInt) -> Self {
-        EllipticCurve { field, a, b }
+    pub fn new(field: FiniteField, a: BigInt, b: BigInt) -> Self {
+        EllipticCurve {
+            field,
+            a,
+            b,
+            coordinates: Coordinates::Affine,
+        }
+    }
+
+    pub fn with_coordinates(mut self, coordinates: Coordinates) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    // new(), refusing singular curves
+    pub fn try_new(field: FiniteField, a: BigInt, b: BigInt) -> Result<Self, Error> {
+        let curve = EllipticCurve::new(field, a, b);
+        if curve.discriminant().is_zero() {
+            return Err(Error::SingularCurve);
+        }
+        Ok(curve)
+    }
+
+    // -16(4a^3 + 27b^2)
+    pub fn discriminant(&self) -> BigInt {
+        let f = &self.field;
+        let a3 = f.mul(&f.mul(&self.a, &self.a), &self.a);
+        let b2 = f.mul(&self.b, &self.b);
+        let sum = f.add(
+            &f.mul(&BigInt::from(4), &a3),
+            &f.mul(&BigInt::from(27), &b2),
+        );
+        f.mul(&BigInt::from(-16), &sum)
+    }
+
+    pub fn is_on_curve(&self, p: &Point) -> bool {
+        let Some((x, y)) = p.coordinates() else {
+            return true;
+        };
+        let f = &self.field;
+        let x3 = f.mul(&f.mul(x, x), x);
+        f.mul(y, y) == f.add(&f.add(&x3, &f.mul(&self.a, x)), &self.b)
+    }
+
+    // The affine point (x, y), if it satisfies the curve equation
+    pub fn point(&self, x: BigInt, y: BigInt) -> Result<Point, Error> {
+        let p = Point::new(x.rem_euclid(&self.field.p), y.rem_euclid(&self.field.p));
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
+    // point(), and also [n]P = O so that P lies in the subgroup of order n. Points of small
+    // order outside it are what invalid-curve and small-subgroup attacks feed in. A subgroup
+    // order is positive, and [0]P = O would accept every point, so n <= 0 is rejected.
+    pub fn subgroup_point(&self, x: BigInt, y: BigInt, n: &BigInt) -> Result<Point, Error> {
+        if !n.is_positive() {
+            return Err(Error::NotInSubgroup);
+        }
+        let p = self.point(x, y)?;
+        // Double-and-add straight over the bits of n, most significant first
+        let mut np = Point::Infinity;
+        for i in (0..n.bits()).rev() {
+            np = self.double(&np)?;
+            if n.bit(i) {
+                np = self.add(&np, &p)?;
+            }
+        }
+        if !np.is_infinity() {
+            return Err(Error::NotInSubgroup);
+        }
+        Ok(p)
     }
 
-    fn add(&self, p: &Point, q: &Point) -> Point {
-        if p == q {
-            return self.double(p);
+    // SEC 1 encodings, see sec1.rs
+    pub fn encode_point(&self, p: &Point, compressed: bool) -> Vec<u8> {
+        sec1::encode_point(p, &self.field.p, compressed)
+    }
+
+    // Recovers y with the field square root for compressed input, and checks the curve
+    // equation for uncompressed input
+    pub fn decode_point(&self, bytes: &[u8]) -> Result<Point, Error> {
+        let f = &self.field;
+        let rhs = |x: &BigInt| f.add(&f.add(&f.mul(&f.mul(x, x), x), &f.mul(&self.a, x)), &self.b);
+        match sec1::decode_point(bytes, &f.p, rhs)? {
+            Point::Infinity => Ok(Point::Infinity),
+            Point::Affine { x, y } => self.point(x, y),
+        }
+    }
+
+    // Vertical lines give O, so this only fails when p is not prime
+    pub fn add(&self, p: &Point, q: &Point) -> Result<Point, Error> {
+        let ((px, py), (qx, qy)) = match (p.coordinates(), q.coordinates()) {
+            (None, _) => return Ok(q.clone()),
+            (_, None) => return Ok(p.clone()),
+            (Some(p), Some(q)) => (p, q),
+        };
+        if px == qx {
+            // Either P = Q, or P = -Q and the line is vertical
+            return if *py == self.field.neg(qy) {
+                Ok(Point::Infinity)
+            } else {
+                self.double(p)
+            };
         }
 
         // y = lambda*x + v
@@ -85,50 +237,51 @@ impl EllipticCurve {
         // lambda = Yq - Yp / Xq-Xp
         let lambda = self
             .field
-            .div(&self.field.sub(&q.y, &p.y), &self.field.sub(&q.x, &p.x))
-            .unwrap();
+            .div(&self.field.sub(qy, py), &self.field.sub(qx, px))?;
 
         // v = Yp - lambda*Xp
-        let nu = self.field.sub(&p.y, &self.field.mul(&lambda, &p.x));
+        let nu = self.field.sub(py, &self.field.mul(&lambda, px));
 
         // (Xr, Yr ) = (lambda^2 - Xp - Xq, - (lambda * Xr +v))
-        let x = self.field.sub(
-            &self.field.sub(&self.field.mul(&lambda, &lambda), &p.x),
-            &q.x,
-        );
+        let x = self
+            .field
+            .sub(&self.field.sub(&self.field.mul(&lambda, &lambda), px), qx);
         let y = self
             .field
             .neg(&self.field.add(&self.field.mul(&lambda, &x), &nu));
 
-        Point { x, y }
+        Ok(Point::new(x, y))
     }
 
-    fn double(&self, p: &Point) -> Point {
-        let lambda = self
-            .field
-            .div(
-                &self.field.add(
-                    &self
-                        .field
-                        .mul(&BigInt::from(3), &self.field.mul(&p.x, &p.x)),
-                    &self.a,
-                ),
-                &self.field.mul(&BigInt::from(2), &p.y),
-            )
-            .unwrap();
+    fn double(&self, p: &Point) -> Result<Point, Error> {
+        let Some((px, py)) = p.coordinates() else {
+            return Ok(Point::Infinity);
+        };
+        if py.is_zero() {
+            // Points of order 2 have a vertical tangent
+            return Ok(Point::Infinity);
+        }
+
+        let lambda = self.field.div(
+            &self.field.add(
+                &self.field.mul(&BigInt::from(3), &self.field.mul(px, px)),
+                &self.a,
+            ),
+            &self.field.mul(&BigInt::from(2), py),
+        )?;
 
-        let nu = self.field.sub(&p.y, &self.field.mul(&lambda, &p.x));
+        let nu = self.field.sub(py, &self.field.mul(&lambda, px));
 
         let x = self.field.sub(
             &self.field.mul(&lambda, &lambda),
-            &self.field.mul(&BigInt::from(2), &p.x),
+            &self.field.mul(&BigInt::from(2), px),
         );
 
         let y = self
             .field
             .neg(&self.field.add(&self.field.mul(&lambda, &x), &nu));
 
-        Point { x, y }
+        Ok(Point::new(x, y))
     }
 
     //     How the Double-and-Add Algorithm Works
@@ -163,11 +316,20 @@ impl EllipticCurve {
     // 𝑃
     // mP.
 
-    fn scalar(&self, m: i32, p: &Point) -> Point {
-        let mut r = p.clone(); // Initialize r with the point p itself
-        let mut first_bit = false;
+    fn scalar(&self, m: i32, p: &Point) -> Result<Point, Error> {
         let binary_string = format!("{:b}", m);
         println!("Binary representation of {}: {}", m, binary_string);
+        match self.coordinates {
+            Coordinates::Affine => self.scalar_affine(&binary_string, p),
+            Coordinates::Jacobian | Coordinates::ModifiedJacobian => {
+                self.to_affine(&self.scalar_jacobian(&binary_string, p))
+            }
+        }
+    }
+
+    fn scalar_affine(&self, binary_string: &str, p: &Point) -> Result<Point, Error> {
+        let mut r = p.clone(); // Initialize r with the point p itself
+        let mut first_bit = false;
         // Iterate through each byte in the integer
         for char in binary_string.chars() {
             // Iterate through bytes in reverse
@@ -179,28 +341,325 @@ impl EllipticCurve {
 
             // Iterate through each bit in the byte
             // Double the point regardless of the bit
-            r = self.double(&r);
+            r = self.double(&r)?;
 
             if char == '1' {
-                r = self.add(&r, p); // Add point p to r if bit is 1
+                r = self.add(&r, p)?; // Add point p to r if bit is 1
             }
         }
 
+        Ok(r)
+    }
+
+    pub fn negate(&self, p: &Point) -> Point {
+        match p {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x.clone(), self.field.neg(y)),
+        }
+    }
+
+    // Same double-and-add, but the running point stays in (modified) Jacobian coordinates and
+    // P is added in affine form (mixed addition), so the only inversion is the final one
+    fn scalar_jacobian(&self, binary_string: &str, p: &Point) -> JacobianPoint {
+        let mut r = self.to_jacobian(p);
+        for char in binary_string.chars().skip(1) {
+            r = self.double_jacobian(&r);
+            if char == '1' {
+                r = self.add_mixed(&r, p);
+            }
+        }
         r
     }
+
+    fn to_jacobian(&self, p: &Point) -> JacobianPoint {
+        let Some((x, y)) = p.coordinates() else {
+            return self.infinity_jacobian();
+        };
+        JacobianPoint {
+            x: x.clone(),
+            y: y.clone(),
+            z: BigInt::one(),
+            az4: self.az4(&BigInt::one()),
+        }
+    }
+
+    fn infinity_jacobian(&self) -> JacobianPoint {
+        JacobianPoint {
+            x: BigInt::one(),
+            y: BigInt::one(),
+            z: BigInt::zero(),
+            az4: self.az4(&BigInt::zero()),
+        }
+    }
+
+    // aZ^4 in modified Jacobian coordinates, nothing otherwise
+    fn az4(&self, z: &BigInt) -> Option<BigInt> {
+        if self.coordinates != Coordinates::ModifiedJacobian {
+            return None;
+        }
+        let zz = self.field.mul(z, z);
+        Some(self.field.mul(&self.a, &self.field.mul(&zz, &zz)))
+    }
+
+    // (X / Z^2, Y / Z^3), or O when Z = 0
+    fn to_affine(&self, p: &JacobianPoint) -> Result<Point, Error> {
+        if p.z.is_zero() {
+            return Ok(Point::Infinity);
+        }
+        let z_inv = self.field.inv(&p.z)?;
+        let z_inv2 = self.field.mul(&z_inv, &z_inv);
+        Ok(Point::new(
+            self.field.mul(&p.x, &z_inv2),
+            self.field.mul(&p.y, &self.field.mul(&z_inv2, &z_inv)),
+        ))
+    }
+
+    // dbl-2007-bl in Jacobian coordinates, with M = 3X^2 + aZ^4 read off the cached aZ^4 in
+    // modified Jacobian coordinates, which then updates it as a(2YZ)^4 = 2 * 8Y^4 * aZ^4
+    fn double_jacobian(&self, p: &JacobianPoint) -> JacobianPoint {
+        let f = &self.field;
+        if p.z.is_zero() || p.y.is_zero() {
+            return self.infinity_jacobian();
+        }
+
+        let xx = f.mul(&p.x, &p.x);
+        let yy = f.mul(&p.y, &p.y);
+        let yyyy = f.mul(&yy, &yy);
+        let zz = f.mul(&p.z, &p.z);
+        let x_yy = f.add(&p.x, &yy);
+        let s = f.mul(
+            &BigInt::from(2),
+            &f.sub(&f.sub(&f.mul(&x_yy, &x_yy), &xx), &yyyy),
+        );
+        let az4 = match &p.az4 {
+            Some(az4) => az4.clone(),
+            None => f.mul(&self.a, &f.mul(&zz, &zz)),
+        };
+        let m = f.add(&f.mul(&BigInt::from(3), &xx), &az4);
+        let t = f.sub(&f.mul(&m, &m), &f.mul(&BigInt::from(2), &s));
+        let eight_yyyy = f.mul(&BigInt::from(8), &yyyy);
+        let y3 = f.sub(&f.mul(&m, &f.sub(&s, &t)), &eight_yyyy);
+        let y_z = f.add(&p.y, &p.z);
+        let z3 = f.sub(&f.sub(&f.mul(&y_z, &y_z), &yy), &zz);
+
+        JacobianPoint {
+            x: t,
+            y: y3,
+            z: z3,
+            az4: p
+                .az4
+                .as_ref()
+                .map(|az4| f.mul(&BigInt::from(2), &f.mul(&eight_yyyy, az4))),
+        }
+    }
+
+    // madd-2007-bl: P in Jacobian coordinates plus Q with Z = 1
+    fn add_mixed(&self, p: &JacobianPoint, q: &Point) -> JacobianPoint {
+        let f = &self.field;
+        let Some((qx, qy)) = q.coordinates() else {
+            return p.clone();
+        };
+        if p.z.is_zero() {
+            return self.to_jacobian(q);
+        }
+
+        let z1z1 = f.mul(&p.z, &p.z);
+        let u2 = f.mul(qx, &z1z1);
+        let s2 = f.mul(qy, &f.mul(&p.z, &z1z1));
+        let h = f.sub(&u2, &p.x);
+        let r = f.mul(&BigInt::from(2), &f.sub(&s2, &p.y));
+        if h.is_zero() {
+            // Same x: P = Q or P = -Q
+            return if r.is_zero() {
+                self.double_jacobian(p)
+            } else {
+                self.infinity_jacobian()
+            };
+        }
+
+        let hh = f.mul(&h, &h);
+        let i = f.mul(&BigInt::from(4), &hh);
+        let j = f.mul(&h, &i);
+        let v = f.mul(&p.x, &i);
+        let x3 = f.sub(&f.sub(&f.mul(&r, &r), &j), &f.mul(&BigInt::from(2), &v));
+        let y3 = f.sub(
+            &f.mul(&r, &f.sub(&v, &x3)),
+            &f.mul(&BigInt::from(2), &f.mul(&p.y, &j)),
+        );
+        let z_h = f.add(&p.z, &h);
+        let z3 = f.sub(&f.sub(&f.mul(&z_h, &z_h), &z1z1), &hh);
+        JacobianPoint {
+            az4: self.az4(&z3),
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
 }
 
 pub fn run() {
-    let fq = FiniteField::new(BigInt::from(1021));
+    let fq = FiniteField::try_new(BigInt::from(1021)).unwrap();
     let a = BigInt::from(-3);
     let b = BigInt::from(-3);
-    let e = EllipticCurve::new(fq.clone(), a, b);
+    let e = EllipticCurve::try_new(fq.clone(), a, b).unwrap();
 
-    let p = Point {
-        x: BigInt::from(379),
-        y: BigInt::from(1011),
-    };
+    let p = e.point(BigInt::from(379), BigInt::from(1011)).unwrap();
     let m = 655;
-    let r = e.scalar(m, &p);
-    println!("[655]P {:?}  = ({:?}, {:?})", p, r.x, r.y);
+    let r = e.scalar(m, &p).unwrap();
+    println!("[655]P {}  = {}", p, r);
+
+    // The same computation in Jacobian and modified Jacobian coordinates
+    for coordinates in [Coordinates::Jacobian, Coordinates::ModifiedJacobian] {
+        let e = e.clone().with_coordinates(coordinates);
+        let r_projective = e.scalar(m, &p).unwrap();
+        println!("[655]P in {:?} coordinates = {}", coordinates, r_projective);
+        assert_eq!(r_projective, r);
+        for m in 2..200 {
+            let binary_string = format!("{:b}", m);
+            assert_eq!(
+                e.to_affine(&e.scalar_jacobian(&binary_string, &p)),
+                e.scalar_affine(&binary_string, &p)
+            );
+        }
+    }
+
+    // Adding P until we reach O gives its order n, and [n]P = O in every coordinate system
+    let mut multiple = p.clone();
+    let mut order = 1;
+    while !multiple.is_infinity() {
+        multiple = e.add(&multiple, &p).unwrap();
+        order += 1;
+    }
+    assert_eq!(e.add(&p, &e.negate(&p)), Ok(Point::Infinity));
+    for coordinates in [
+        Coordinates::Affine,
+        Coordinates::Jacobian,
+        Coordinates::ModifiedJacobian,
+    ] {
+        let e = e.clone().with_coordinates(coordinates);
+        let binary_string = format!("{:b}", order);
+        assert_eq!(
+            e.to_affine(&e.scalar_jacobian(&binary_string, &p)),
+            Ok(Point::Infinity)
+        );
+        assert_eq!(e.scalar_affine(&binary_string, &p), Ok(Point::Infinity));
+    }
+    println!("P has order {}", order);
+
+    // The checked constructors: y^2 = x^3 - 3x + 2 = (x - 1)^2 (x + 2) has a node at (1, 0),
+    // (379, 1012) is off the curve and P only lies in its own subgroup
+    assert_eq!(
+        EllipticCurve::try_new(fq, BigInt::from(-3), BigInt::from(2)),
+        Err(Error::SingularCurve)
+    );
+    assert_eq!(
+        e.point(BigInt::from(379), BigInt::from(1012)),
+        Err(Error::NotOnCurve)
+    );
+    let n = BigInt::from(order);
+    assert_eq!(
+        e.subgroup_point(BigInt::from(379), BigInt::from(1011), &n),
+        Ok(p.clone())
+    );
+    assert_eq!(
+        e.subgroup_point(BigInt::from(379), BigInt::from(1011), &(&n - 1)),
+        Err(Error::NotInSubgroup)
+    );
+    for bad in [BigInt::zero(), -n] {
+        assert_eq!(
+            e.subgroup_point(BigInt::from(379), BigInt::from(1011), &bad),
+            Err(Error::NotInSubgroup)
+        );
+    }
+
+    // SEC 1 round trips for every multiple of P. 1021 = 1 mod 4, so decompression takes the
+    // Tonelli-Shanks path rather than a single power.
+    let mut multiple = Point::Infinity;
+    for _ in 0..order {
+        for compressed in [false, true] {
+            let bytes = e.encode_point(&multiple, compressed);
+            assert_eq!(e.decode_point(&bytes), Ok(multiple.clone()));
+        }
+        multiple = e.add(&multiple, &p).unwrap();
+    }
+    let compressed = e.encode_point(&p, true);
+    println!("P compressed: {}", hex(&compressed));
+    assert_eq!(compressed, [0x03, 0x01, 0x7b]);
+    assert_eq!(e.encode_point(&p, false), [0x04, 0x01, 0x7b, 0x03, 0xf3]);
+
+    // Malformed input: x = 1021 is not reduced, a bad prefix, a short string, an off-curve
+    // point, and an x with no y above it (5^3 - 15 - 3 = 107 is not a square mod 1021)
+    for bytes in [
+        &[0x02, 0x03, 0xfd][..],
+        &[0x05, 0x01, 0x7b],
+        &[0x04, 0x01, 0x7b, 0x03],
+        &[],
+    ] {
+        assert_eq!(e.decode_point(bytes), Err(Error::InvalidEncoding));
+    }
+    assert_eq!(
+        e.decode_point(&[0x04, 0x01, 0x7b, 0x03, 0xf4]),
+        Err(Error::NotOnCurve)
+    );
+    assert_eq!(e.decode_point(&[0x02, 0x00, 0x05]), Err(Error::NotOnCurve));
+
+    // The generators of secp256k1 (p = 3 mod 4) and P-224 (p = 1 mod 2^96), from their
+    // compressed forms
+    for (p, a, b, compressed, y) in [
+        (
+            (BigInt::one() << 256) - (BigInt::one() << 32) - 977,
+            BigInt::zero(),
+            BigInt::from(7),
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+        ),
+        (
+            (BigInt::one() << 224) - (BigInt::one() << 96) + 1,
+            BigInt::from(-3),
+            BigInt::parse_bytes(
+                b"b4050a850c04b3abf54132565044b0b7d7bfd8ba270b39432355ffb4",
+                16,
+            )
+            .unwrap(),
+            "02b70e0cbd6bb4bf7f321390b94a03c1d356c21122343280d6115c1d21",
+            "bd376388b5f723fb4c22dfe6cd4375a05a07476444d5819985007e34",
+        ),
+    ] {
+        let e = EllipticCurve::try_new(FiniteField::try_new(p).unwrap(), a, b).unwrap();
+        let g = e.decode_point(&unhex(compressed)).unwrap();
+        assert_eq!(g.coordinates().unwrap().1.to_str_radix(16), y);
+        assert_eq!(hex(&e.encode_point(&g, true)), compressed);
+        assert_eq!(
+            hex(&e.encode_point(&g, false)),
+            format!("04{}{}", &compressed[2..], y)
+        );
+    }
+    println!("secp256k1 and P-224 generators decompress correctly");
+
+    // 1023 = 3 * 11 * 31 is refused, 2^255 - 19 is accepted, and over Z/15 the chord
+    // through (0, 1) and (3, 1) on y^2 = x^3 + x + 1 needs 1 / 3, which is reported
+    assert_eq!(
+        FiniteField::try_new(BigInt::from(1023)),
+        Err(Error::CompositeModulus)
+    );
+    assert!(FiniteField::try_new((BigInt::one() << 255) - 19).is_ok());
+    let z15 = FiniteField::new(BigInt::from(15));
+    assert_eq!(z15.inv(&BigInt::from(5)), Err(Error::NotInvertible));
+    let e15 = EllipticCurve::new(z15, BigInt::one(), BigInt::one());
+    let (p, q) = (
+        e15.point(BigInt::zero(), BigInt::one()),
+        e15.point(BigInt::from(3), BigInt::one()),
+    );
+    assert_eq!(e15.add(&p.unwrap(), &q.unwrap()), Err(Error::NotInvertible));
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
 }
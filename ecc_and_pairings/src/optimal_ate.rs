@@ -0,0 +1,765 @@
+use crate::point::Point;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{Fp2, Fp2Config, PrimeField};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{One, Zero};
+
+// Optimal ate pairing on BN254 and BLS12-381 over the tower
+//   Fq2  = Fq[u] / (u^2 + 1)
+//   Fq6  = Fq2[v] / (v^3 - xi)
+//   Fq12 = Fq6[w] / (w^2 - v)
+// which is the same tower arkworks uses, so the outputs can be compared coefficient-wise.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fq2 {
+    c0: BigUint,
+    c1: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fq6 {
+    c0: Fq2,
+    c1: Fq2,
+    c2: Fq2,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fq12 {
+    c0: Fq6,
+    c1: Fq6,
+}
+
+// Affine point on E(Fq). The pairing takes a Point<BigUint> and only builds one of these,
+// and the G2Point below, once O has been dealt with.
+#[derive(Clone, Debug, PartialEq)]
+struct G1Point {
+    x: BigUint,
+    y: BigUint,
+}
+
+// Affine point on the twist E'(Fq2)
+#[derive(Clone, Debug, PartialEq)]
+struct G2Point {
+    x: Fq2,
+    y: Fq2,
+}
+
+// D-type twists E': y^2 = x^3 + b / xi untwist with (x, y) -> (x w^2, y w^3),
+// M-type twists E': y^2 = x^3 + b * xi untwist with (x, y) -> (x / w^2, y / w^3)
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TwistType {
+    D,
+    M,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Family {
+    Bn,
+    Bls12,
+}
+
+#[derive(Clone, Debug)]
+pub struct PairingCurve {
+    family: Family,
+    pub p: BigUint,
+    pub r: BigUint,
+    // Cofactor of G1, #E(Fq) = h r
+    pub h: BigUint,
+    // Curve parameter x (called u or z in some papers), stored as |x|
+    x: BigUint,
+    x_is_negative: bool,
+    pub b: BigUint,
+    b_twist: Fq2,
+    xi: Fq2,
+    twist: TwistType,
+    // gamma[i] = xi^(i (p - 1) / 6), so that (w^i)^p = gamma[i] w^i
+    gamma: Vec<Fq2>,
+}
+
+fn biguint(s: &str) -> BigUint {
+    BigUint::parse_bytes(s.as_bytes(), 10).unwrap()
+}
+
+impl Fq2 {
+    fn new(c0: BigUint, c1: BigUint) -> Self {
+        Fq2 { c0, c1 }
+    }
+
+    fn zero() -> Self {
+        Fq2::new(BigUint::zero(), BigUint::zero())
+    }
+
+    fn one() -> Self {
+        Fq2::new(BigUint::one(), BigUint::zero())
+    }
+
+    fn from_fq(c0: &BigUint) -> Self {
+        Fq2::new(c0.clone(), BigUint::zero())
+    }
+
+    fn add(&self, other: &Fq2, p: &BigUint) -> Fq2 {
+        Fq2::new((&self.c0 + &other.c0) % p, (&self.c1 + &other.c1) % p)
+    }
+
+    fn sub(&self, other: &Fq2, p: &BigUint) -> Fq2 {
+        Fq2::new(
+            (&self.c0 + p - &other.c0) % p,
+            (&self.c1 + p - &other.c1) % p,
+        )
+    }
+
+    fn neg(&self, p: &BigUint) -> Fq2 {
+        Fq2::zero().sub(self, p)
+    }
+
+    // (a + bu)(c + du) = (ac - bd) + (ad + bc)u
+    fn mul(&self, other: &Fq2, p: &BigUint) -> Fq2 {
+        let ac = &self.c0 * &other.c0;
+        let bd = &self.c1 * &other.c1;
+        let ad_bc = &self.c0 * &other.c1 + &self.c1 * &other.c0;
+        // bd < p^2, so adding p^2 keeps the real part non-negative
+        Fq2::new((ac + p * p - bd) % p, ad_bc % p)
+    }
+
+    fn square(&self, p: &BigUint) -> Fq2 {
+        self.mul(self, p)
+    }
+
+    fn scale(&self, k: &BigUint, p: &BigUint) -> Fq2 {
+        Fq2::new((&self.c0 * k) % p, (&self.c1 * k) % p)
+    }
+
+    // The q-power Frobenius maps u to -u
+    fn conjugate(&self, p: &BigUint) -> Fq2 {
+        Fq2::new(self.c0.clone(), (p - &self.c1) % p)
+    }
+
+    // (a + bu)^-1 = (a - bu) / (a^2 + b^2)
+    fn inv(&self, p: &BigUint) -> Fq2 {
+        let norm = (&self.c0 * &self.c0 + &self.c1 * &self.c1) % p;
+        let norm_inv = norm.modpow(&(p - 2u32), p);
+        self.conjugate(p).scale(&norm_inv, p)
+    }
+
+    fn pow(&self, exp: &BigUint, p: &BigUint) -> Fq2 {
+        let mut result = Fq2::one();
+        for i in (0..exp.bits()).rev() {
+            result = result.square(p);
+            if exp.bit(i) {
+                result = result.mul(self, p);
+            }
+        }
+        result
+    }
+}
+
+impl Fq6 {
+    fn new(c0: Fq2, c1: Fq2, c2: Fq2) -> Self {
+        Fq6 { c0, c1, c2 }
+    }
+
+    fn zero() -> Self {
+        Fq6::new(Fq2::zero(), Fq2::zero(), Fq2::zero())
+    }
+
+    fn one() -> Self {
+        Fq6::new(Fq2::one(), Fq2::zero(), Fq2::zero())
+    }
+
+    fn add(&self, other: &Fq6, curve: &PairingCurve) -> Fq6 {
+        let p = &curve.p;
+        Fq6::new(
+            self.c0.add(&other.c0, p),
+            self.c1.add(&other.c1, p),
+            self.c2.add(&other.c2, p),
+        )
+    }
+
+    fn sub(&self, other: &Fq6, curve: &PairingCurve) -> Fq6 {
+        let p = &curve.p;
+        Fq6::new(
+            self.c0.sub(&other.c0, p),
+            self.c1.sub(&other.c1, p),
+            self.c2.sub(&other.c2, p),
+        )
+    }
+
+    fn neg(&self, curve: &PairingCurve) -> Fq6 {
+        Fq6::zero().sub(self, curve)
+    }
+
+    // (a0 + a1 v + a2 v^2)(b0 + b1 v + b2 v^2) reduced with v^3 = xi
+    fn mul(&self, other: &Fq6, curve: &PairingCurve) -> Fq6 {
+        let (p, xi) = (&curve.p, &curve.xi);
+        let a0b0 = self.c0.mul(&other.c0, p);
+        let a1b1 = self.c1.mul(&other.c1, p);
+        let a2b2 = self.c2.mul(&other.c2, p);
+        let a1b2_a2b1 = self.c1.mul(&other.c2, p).add(&self.c2.mul(&other.c1, p), p);
+        let a0b1_a1b0 = self.c0.mul(&other.c1, p).add(&self.c1.mul(&other.c0, p), p);
+        let a0b2_a2b0 = self.c0.mul(&other.c2, p).add(&self.c2.mul(&other.c0, p), p);
+
+        Fq6::new(
+            a0b0.add(&a1b2_a2b1.mul(xi, p), p),
+            a0b1_a1b0.add(&a2b2.mul(xi, p), p),
+            a0b2_a2b0.add(&a1b1, p),
+        )
+    }
+
+    // v * (a0 + a1 v + a2 v^2) = xi a2 + a0 v + a1 v^2
+    fn mul_by_v(&self, curve: &PairingCurve) -> Fq6 {
+        Fq6::new(
+            self.c2.mul(&curve.xi, &curve.p),
+            self.c0.clone(),
+            self.c1.clone(),
+        )
+    }
+
+    fn inv(&self, curve: &PairingCurve) -> Fq6 {
+        let (p, xi) = (&curve.p, &curve.xi);
+        let t0 = self
+            .c0
+            .square(p)
+            .sub(&self.c1.mul(&self.c2, p).mul(xi, p), p);
+        let t1 = self
+            .c2
+            .square(p)
+            .mul(xi, p)
+            .sub(&self.c0.mul(&self.c1, p), p);
+        let t2 = self.c1.square(p).sub(&self.c0.mul(&self.c2, p), p);
+        let det = self.c0.mul(&t0, p).add(
+            &self.c2.mul(&t1, p).add(&self.c1.mul(&t2, p), p).mul(xi, p),
+            p,
+        );
+        let det_inv = det.inv(p);
+
+        Fq6::new(
+            t0.mul(&det_inv, p),
+            t1.mul(&det_inv, p),
+            t2.mul(&det_inv, p),
+        )
+    }
+}
+
+impl Fq12 {
+    fn new(c0: Fq6, c1: Fq6) -> Self {
+        Fq12 { c0, c1 }
+    }
+
+    fn one() -> Self {
+        Fq12::new(Fq6::one(), Fq6::zero())
+    }
+
+    // (a0 + a1 w)(b0 + b1 w) = (a0 b0 + a1 b1 v) + (a0 b1 + a1 b0) w
+    fn mul(&self, other: &Fq12, curve: &PairingCurve) -> Fq12 {
+        let a0b0 = self.c0.mul(&other.c0, curve);
+        let a1b1 = self.c1.mul(&other.c1, curve);
+        let cross = self
+            .c0
+            .mul(&other.c1, curve)
+            .add(&self.c1.mul(&other.c0, curve), curve);
+
+        Fq12::new(a0b0.add(&a1b1.mul_by_v(curve), curve), cross)
+    }
+
+    fn square(&self, curve: &PairingCurve) -> Fq12 {
+        self.mul(self, curve)
+    }
+
+    // The p^6-power Frobenius maps w to -w. On the cyclotomic subgroup (everything
+    // after the easy part of the final exponentiation) this is the inverse.
+    fn conjugate(&self, curve: &PairingCurve) -> Fq12 {
+        Fq12::new(self.c0.clone(), self.c1.neg(curve))
+    }
+
+    // (a0 + a1 w)^-1 = (a0 - a1 w) / (a0^2 - a1^2 v)
+    fn inv(&self, curve: &PairingCurve) -> Fq12 {
+        let norm = self
+            .c0
+            .mul(&self.c0, curve)
+            .sub(&self.c1.mul(&self.c1, curve).mul_by_v(curve), curve);
+        let norm_inv = norm.inv(curve);
+
+        Fq12::new(
+            self.c0.mul(&norm_inv, curve),
+            self.c1.neg(curve).mul(&norm_inv, curve),
+        )
+    }
+
+    fn pow(&self, exp: &BigUint, curve: &PairingCurve) -> Fq12 {
+        let mut result = Fq12::one();
+        for i in (0..exp.bits()).rev() {
+            result = result.square(curve);
+            if exp.bit(i) {
+                result = result.mul(self, curve);
+            }
+        }
+        result
+    }
+
+    // Writing f = sum g_i w^i with g_i in Fq2, the p-power Frobenius is
+    // sum conj(g_i) gamma[i] w^i. In the tower, w^0, w^2, w^4 live in c0 and
+    // w^1, w^3, w^5 in c1.
+    fn frobenius(&self, power: usize, curve: &PairingCurve) -> Fq12 {
+        let p = &curve.p;
+        let mut result = self.clone();
+        for _ in 0..power {
+            let g = [
+                &result.c0.c0,
+                &result.c1.c0,
+                &result.c0.c1,
+                &result.c1.c1,
+                &result.c0.c2,
+                &result.c1.c2,
+            ];
+            let h: Vec<Fq2> = g
+                .iter()
+                .enumerate()
+                .map(|(i, g_i)| g_i.conjugate(p).mul(&curve.gamma[i], p))
+                .collect();
+            result = Fq12::new(
+                Fq6::new(h[0].clone(), h[2].clone(), h[4].clone()),
+                Fq6::new(h[1].clone(), h[3].clone(), h[5].clone()),
+            );
+        }
+        result
+    }
+}
+
+impl PairingCurve {
+    fn new(
+        family: Family,
+        p: BigUint,
+        r: BigUint,
+        x: BigInt,
+        b: BigUint,
+        xi: Fq2,
+        twist: TwistType,
+    ) -> Self {
+        let b_twist = match twist {
+            TwistType::D => xi.inv(&p).scale(&b, &p),
+            TwistType::M => xi.scale(&b, &p),
+        };
+        let gamma_1 = xi.pow(&((&p - 1u32) / 6u32), &p);
+        let mut gamma = vec![Fq2::one()];
+        for i in 1..6 {
+            gamma.push(gamma[i - 1].mul(&gamma_1, &p));
+        }
+
+        PairingCurve {
+            family,
+            p,
+            r,
+            h: BigUint::one(),
+            x: x.magnitude().clone(),
+            x_is_negative: x.sign() == Sign::Minus,
+            b,
+            b_twist,
+            xi,
+            twist,
+            gamma,
+        }
+    }
+
+    // BN254 (alt_bn128): y^2 = x^3 + 3, xi = 9 + u, D-type sextic twist
+    pub fn bn254() -> Self {
+        PairingCurve::new(
+            Family::Bn,
+            biguint(
+                "21888242871839275222246405745257275088696311157297823662689037894645226208583",
+            ),
+            biguint(
+                "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+            ),
+            BigInt::from(4965661367192848881u64),
+            BigUint::from(3u32),
+            Fq2::new(BigUint::from(9u32), BigUint::one()),
+            TwistType::D,
+        )
+    }
+
+    // BLS12-381: y^2 = x^3 + 4, xi = 1 + u, M-type sextic twist, x = -0xd201000000010000
+    pub fn bls12_381() -> Self {
+        PairingCurve {
+            h: biguint("76329603384216526031706109802092473003"),
+            ..PairingCurve::new(
+                Family::Bls12,
+                biguint("4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559787"),
+                biguint("52435875175126190479447740508185965837690552500527637822603658699938581184513"),
+                -BigInt::from(0xd201000000010000u64),
+                BigUint::from(4u32),
+                Fq2::new(BigUint::one(), BigUint::one()),
+                TwistType::M,
+            )
+        }
+    }
+
+    fn is_on_curve(&self, point: &Point<BigUint>) -> bool {
+        let Some((x, y)) = point.coordinates() else {
+            return true;
+        };
+        let p = &self.p;
+        (y * y) % p == (x.modpow(&BigUint::from(3u32), p) + &self.b) % p
+    }
+
+    fn is_on_twist(&self, point: &Point<Fq2>) -> bool {
+        let Some((x, y)) = point.coordinates() else {
+            return true;
+        };
+        let p = &self.p;
+        y.square(p) == x.square(p).mul(x, p).add(&self.b_twist, p)
+    }
+
+    fn g2_neg(&self, point: &G2Point) -> G2Point {
+        G2Point {
+            x: point.x.clone(),
+            y: point.y.neg(&self.p),
+        }
+    }
+
+    // pi(Q) computed on the twist as untwist -> Frobenius -> twist. For a D-type twist
+    // (x w^2)^p = conj(x) gamma[2] w^2 and (y w^3)^p = conj(y) gamma[3] w^3.
+    fn g2_frobenius(&self, point: &G2Point) -> G2Point {
+        let p = &self.p;
+        G2Point {
+            x: point.x.conjugate(p).mul(&self.gamma[2], p),
+            y: point.y.conjugate(p).mul(&self.gamma[3], p),
+        }
+    }
+
+    // Embeds l(P) = y_P - y_T - lambda (x_P - x_T), for a line on the twist with slope
+    // lambda through T, into Fq12 via the untwisting isomorphism. For an M-type twist
+    // the line is scaled by w^3, which the final exponentiation removes.
+    fn evaluate_line(&self, lambda: &Fq2, t: &G2Point, point: &G1Point) -> Fq12 {
+        let p = &self.p;
+        let constant = lambda.mul(&t.x, p).sub(&t.y, p);
+        let x_term = lambda.scale(&point.x, p).neg(p);
+        let y_term = Fq2::from_fq(&point.y);
+
+        match self.twist {
+            // y_P - lambda x_P w + (lambda x_T - y_T) w^3
+            TwistType::D => Fq12::new(
+                Fq6::new(y_term, Fq2::zero(), Fq2::zero()),
+                Fq6::new(x_term, constant, Fq2::zero()),
+            ),
+            // (lambda x_T - y_T) - lambda x_P w^2 + y_P w^3
+            TwistType::M => Fq12::new(
+                Fq6::new(constant, x_term, Fq2::zero()),
+                Fq6::new(Fq2::zero(), y_term, Fq2::zero()),
+            ),
+        }
+    }
+
+    // One Miller step: returns T + S on the twist and the line through T and S
+    // (the tangent if T == S) evaluated at P. Vertical lines lie in a proper subfield
+    // of Fq12 and are killed by the final exponentiation, so they are dropped.
+    fn line_step(&self, t: &G2Point, s: &G2Point, point: &G1Point) -> (Option<G2Point>, Fq12) {
+        let p = &self.p;
+
+        let lambda = if t == s {
+            let three_x2 = t.x.square(p).scale(&BigUint::from(3u32), p);
+            three_x2.mul(&t.y.scale(&BigUint::from(2u32), p).inv(p), p)
+        } else if t.x == s.x {
+            return (None, Fq12::one());
+        } else {
+            s.y.sub(&t.y, p).mul(&s.x.sub(&t.x, p).inv(p), p)
+        };
+
+        let x3 = lambda.square(p).sub(&t.x, p).sub(&s.x, p);
+        let y3 = lambda.mul(&t.x.sub(&x3, p), p).sub(&t.y, p);
+
+        (
+            Some(G2Point { x: x3, y: y3 }),
+            self.evaluate_line(&lambda, t, point),
+        )
+    }
+
+    // Miller loop of the optimal ate pairing.
+    //   BLS12: f_{|x|,Q}(P), conjugated when x < 0
+    //   BN:    f_{6x+2,Q}(P) * l_{[6x+2]Q, pi(Q)}(P) * l_{[6x+2]Q + pi(Q), -pi^2(Q)}(P)
+    // Q != O has order r and the loop count is below r, so every T is [k]Q with 0 < k < r
+    // and no doubling or addition meets a vertical line. Only the last BN step reaches O,
+    // and that T is dropped. optimal_ate deals with O before calling this.
+    fn miller_loop(&self, point: &G1Point, q: &G2Point) -> Fq12 {
+        let loop_count = match self.family {
+            Family::Bn => &self.x * 6u32 + 2u32,
+            Family::Bls12 => self.x.clone(),
+        };
+
+        let mut t = q.clone();
+        let mut f = Fq12::one();
+        for i in (0..loop_count.bits() - 1).rev() {
+            let (doubled, l) = self.line_step(&t, &t, point);
+            f = f.square(self).mul(&l, self);
+            t = doubled.expect("[2k]Q != O");
+
+            if loop_count.bit(i) {
+                let (added, l) = self.line_step(&t, q, point);
+                f = f.mul(&l, self);
+                t = added.expect("[k + 1]Q != O");
+            }
+        }
+
+        if self.x_is_negative {
+            f = f.conjugate(self);
+        }
+
+        if self.family == Family::Bn {
+            let q1 = self.g2_frobenius(q);
+            let q2 = self.g2_neg(&self.g2_frobenius(&q1));
+
+            let (added, l) = self.line_step(&t, &q1, point);
+            f = f.mul(&l, self);
+            let (_, l) = self.line_step(&added.expect("[6x + 2]Q + pi(Q) = pi^2(Q)"), &q2, point);
+            f = f.mul(&l, self);
+        }
+
+        f
+    }
+
+    // f^x for the signed curve parameter x
+    fn exp_by_x(&self, f: &Fq12) -> Fq12 {
+        let result = f.pow(&self.x, self);
+        if self.x_is_negative {
+            result.conjugate(self)
+        } else {
+            result
+        }
+    }
+
+    // Final exponentiation by (p^12 - 1) / r = (p^6 - 1)(p^2 + 1) * (p^4 - p^2 + 1) / r.
+    // The easy part is a conjugation, an inversion and a Frobenius. The hard part uses
+    // the x-adic addition chains of Fuentes-Castaneda et al. (BN) and Hayashida et al.
+    // (BLS12); like arkworks these raise to a fixed multiple of (p^4 - p^2 + 1) / r
+    // coprime to r, which is still a non-degenerate bilinear pairing.
+    fn final_exponentiation(&self, f: &Fq12) -> Fq12 {
+        // f^(p^6 - 1) = conj(f) / f, then ^(p^2 + 1)
+        let f1 = f.conjugate(self).mul(&f.inv(self), self);
+        let r = f1.frobenius(2, self).mul(&f1, self);
+
+        match self.family {
+            Family::Bn => self.final_exponentiation_hard_bn(&r),
+            Family::Bls12 => self.final_exponentiation_hard_bls12(&r),
+        }
+    }
+
+    // Computes r^(2x (6x^2 + 3x + 1) (p^4 - p^2 + 1) / r)
+    fn final_exponentiation_hard_bn(&self, r: &Fq12) -> Fq12 {
+        let exp_by_neg_x = |f: &Fq12| self.exp_by_x(f).conjugate(self);
+
+        let y0 = exp_by_neg_x(r);
+        let y1 = y0.square(self);
+        let y2 = y1.square(self);
+        let y3 = y2.mul(&y1, self);
+        let y4 = exp_by_neg_x(&y3);
+        let y5 = y4.square(self);
+        let y6 = exp_by_neg_x(&y5);
+        let y3 = y3.conjugate(self);
+        let y6 = y6.conjugate(self);
+        let y7 = y6.mul(&y4, self);
+        let y8 = y7.mul(&y3, self);
+        let y9 = y8.mul(&y1, self);
+        let y10 = y8.mul(&y4, self);
+        let y11 = y10.mul(r, self);
+        let y12 = y9.frobenius(1, self);
+        let y13 = y12.mul(&y11, self);
+        let y8 = y8.frobenius(2, self);
+        let y14 = y8.mul(&y13, self);
+        let y15 = r.conjugate(self).mul(&y9, self).frobenius(3, self);
+
+        y15.mul(&y14, self)
+    }
+
+    // Computes r^(3 (p^4 - p^2 + 1) / r)
+    fn final_exponentiation_hard_bls12(&self, r: &Fq12) -> Fq12 {
+        let y0 = r.square(self);
+        let y1 = self.exp_by_x(r);
+        let y2 = r.conjugate(self);
+        let y1 = y1.mul(&y2, self);
+        let y2 = self.exp_by_x(&y1);
+        let y1 = y1.conjugate(self).mul(&y2, self);
+        let y2 = self.exp_by_x(&y1);
+        let y1 = y1.frobenius(1, self).mul(&y2, self);
+        let r = r.mul(&y0, self);
+        let y0 = self.exp_by_x(&y1);
+        let y2 = self.exp_by_x(&y0);
+        let y0 = y1.frobenius(2, self);
+        let y1 = y1.conjugate(self).mul(&y2, self).mul(&y0, self);
+
+        r.mul(&y1, self)
+    }
+
+    // P in G1 and Q in G2. e(O, Q) = e(P, O) = 1: the Miller function of O is constant,
+    // and so is every line evaluated at O.
+    fn optimal_ate(&self, point: &Point<BigUint>, q: &Point<Fq2>) -> Fq12 {
+        let (Some((px, py)), Some((qx, qy))) = (point.coordinates(), q.coordinates()) else {
+            return Fq12::one();
+        };
+        let point = G1Point {
+            x: px.clone(),
+            y: py.clone(),
+        };
+        let q = G2Point {
+            x: qx.clone(),
+            y: qy.clone(),
+        };
+        self.final_exponentiation(&self.miller_loop(&point, &q))
+    }
+}
+
+fn from_ark_fq<F: PrimeField>(x: &F) -> BigUint {
+    x.into_bigint().into()
+}
+
+fn from_ark_fq2<P: Fp2Config>(x: &Fp2<P>) -> Fq2 {
+    Fq2::new(from_ark_fq(&x.c0), from_ark_fq(&x.c1))
+}
+
+// arkworks flags O rather than giving it coordinates
+fn from_ark_g1<P: SWCurveConfig>(point: &Affine<P>) -> Point<BigUint>
+where
+    P::BaseField: PrimeField,
+{
+    match point.xy() {
+        Some((x, y)) => Point::new(from_ark_fq(x), from_ark_fq(y)),
+        None => Point::Infinity,
+    }
+}
+
+fn from_ark_g2<C: Fp2Config, P: SWCurveConfig<BaseField = Fp2<C>>>(
+    point: &Affine<P>,
+) -> Point<Fq2> {
+    match point.xy() {
+        Some((x, y)) => Point::new(from_ark_fq2(x), from_ark_fq2(y)),
+        None => Point::Infinity,
+    }
+}
+
+// e(G1, G2) has order r, and O on either side pairs to 1
+fn check_pairing(name: &str, curve: &PairingCurve, g1: &Point<BigUint>, g2: &Point<Fq2>) {
+    assert!(curve.is_on_curve(g1));
+    assert!(curve.is_on_twist(g2));
+
+    let e = curve.optimal_ate(g1, g2);
+    assert_eq!(e.pow(&curve.r, curve), Fq12::one());
+    assert_ne!(e, Fq12::one());
+    assert_eq!(curve.optimal_ate(&Point::Infinity, g2), Fq12::one());
+    assert_eq!(curve.optimal_ate(g1, &Point::Infinity), Fq12::one());
+
+    println!("{}: e(G1, G2) has order r, e(O, G2) = e(G1, O) = 1", name);
+}
+
+pub fn run() {
+    check_pairing(
+        "BN254",
+        &PairingCurve::bn254(),
+        &from_ark_g1(&ark_bn254::G1Affine::generator()),
+        &from_ark_g2(&ark_bn254::G2Affine::generator()),
+    );
+    check_pairing(
+        "BLS12-381",
+        &PairingCurve::bls12_381(),
+        &from_ark_g1(&ark_bls12_381::G1Affine::generator()),
+        &from_ark_g2(&ark_bls12_381::G2Affine::generator()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::Bn254;
+    use ark_ec::pairing::Pairing;
+    use ark_ec::CurveGroup;
+    use ark_ff::{Fp12, Fp12Config, Fp6};
+
+    fn from_ark_fq12<P: Fp12Config>(f: &Fp12<P>) -> Fq12 {
+        let fq6 = |c: &Fp6<P::Fp6Config>| {
+            Fq6::new(
+                from_ark_fq2(&c.c0),
+                from_ark_fq2(&c.c1),
+                from_ark_fq2(&c.c2),
+            )
+        };
+        Fq12::new(fq6(&f.c0), fq6(&f.c1))
+    }
+
+    // Fixed multiples P = [a]G1, Q = [b]G2 of the generators, together with ab and their
+    // pairing as computed by arkworks
+    fn ark_sample<E: Pairing>(
+        a: u64,
+        b: u64,
+    ) -> (E::G1Affine, E::G2Affine, BigUint, E::TargetField) {
+        let (a, b) = (E::ScalarField::from(a), E::ScalarField::from(b));
+        let p = (E::G1Affine::generator() * a).into_affine();
+        let q = (E::G2Affine::generator() * b).into_affine();
+
+        (p, q, from_ark_fq(&(a * b)), E::pairing(p, q).0)
+    }
+
+    // Checks our pairing of P = [a]G1, Q = [b]G2 agrees with arkworks, and that it is
+    // bilinear and of order r
+    fn check_against_arkworks(
+        curve: &PairingCurve,
+        (g1, g2): (&Point<BigUint>, &Point<Fq2>),
+        (p, q): (&Point<BigUint>, &Point<Fq2>),
+        ab: &BigUint,
+        e_ark: &Fq12,
+    ) {
+        assert!(curve.is_on_curve(p));
+        assert!(curve.is_on_twist(q));
+
+        let e = curve.optimal_ate(p, q);
+        assert_eq!(e, *e_ark);
+
+        let e_gen = curve.optimal_ate(g1, g2);
+        assert_eq!(e, e_gen.pow(ab, curve));
+        assert_eq!(e.pow(&curve.r, curve), Fq12::one());
+        assert_ne!(e, Fq12::one());
+    }
+
+    #[test]
+    fn bn254_matches_arkworks() {
+        let (p, q, ab, e_ark) = ark_sample::<Bn254>(0x1234_5678_9abc_def0, 0x0fed_cba9_8765_4321);
+        check_against_arkworks(
+            &PairingCurve::bn254(),
+            (
+                &from_ark_g1(&ark_bn254::G1Affine::generator()),
+                &from_ark_g2(&ark_bn254::G2Affine::generator()),
+            ),
+            (&from_ark_g1(&p), &from_ark_g2(&q)),
+            &ab,
+            &from_ark_fq12(&e_ark),
+        );
+    }
+
+    #[test]
+    fn bls12_381_matches_arkworks() {
+        let (p, q, ab, e_ark) =
+            ark_sample::<Bls12_381>(0x1234_5678_9abc_def0, 0x0fed_cba9_8765_4321);
+        check_against_arkworks(
+            &PairingCurve::bls12_381(),
+            (
+                &from_ark_g1(&ark_bls12_381::G1Affine::generator()),
+                &from_ark_g2(&ark_bls12_381::G2Affine::generator()),
+            ),
+            (&from_ark_g1(&p), &from_ark_g2(&q)),
+            &ab,
+            &from_ark_fq12(&e_ark),
+        );
+    }
+
+    // arkworks' O converts to Point::Infinity and pairs to 1, as it does in arkworks
+    #[test]
+    fn infinity_pairs_to_one() {
+        let curve = PairingCurve::bn254();
+        let o1 = from_ark_g1(&ark_bn254::G1Affine::zero());
+        let o2 = from_ark_g2(&ark_bn254::G2Affine::zero());
+        assert_eq!(o1, Point::Infinity);
+        assert_eq!(o2, Point::Infinity);
+        let g1 = from_ark_g1(&ark_bn254::G1Affine::generator());
+        let g2 = from_ark_g2(&ark_bn254::G2Affine::generator());
+        assert_eq!(curve.optimal_ate(&o1, &g2), Fq12::one());
+        assert_eq!(curve.optimal_ate(&g1, &o2), Fq12::one());
+    }
+}
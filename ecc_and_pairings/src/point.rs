@@ -0,0 +1,41 @@
+use std::fmt::{self, Display};
+
+// A point on an elliptic curve: either the point at infinity O, the identity of the group
+// law, or an affine point (x, y). Keeping O out of the coordinates means no affine point,
+// such as (0, 0) on y^2 = x^3 - 2x, can be mistaken for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Point<T> {
+    Infinity,
+    Affine { x: T, y: T },
+}
+
+impl<T> Point<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Point::Affine { x, y }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        matches!(self, Point::Infinity)
+    }
+
+    // (x, y), None for O
+    pub fn coordinates(&self) -> Option<(&T, &T)> {
+        match self {
+            Point::Infinity => None,
+            Point::Affine { x, y } => Some((x, y)),
+        }
+    }
+
+    pub fn x(&self) -> Option<&T> {
+        self.coordinates().map(|(x, _)| x)
+    }
+}
+
+impl<T: Display> Display for Point<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Point::Infinity => write!(f, "O"),
+            Point::Affine { x, y } => write!(f, "({}, {})", x, y),
+        }
+    }
+}
@@ -1,5 +1,11 @@
-use crate::e_over_finite_fields::{inverse_mod, EllipticCurve, Fq, Point};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 
+use crate::e_over_finite_fields::{isomorphism_classes, EllipticCurve, Fq, Isomorphism, Point};
+use crate::error::Error;
+
+// (X : Y : Z) on Y^2 Z = X^3 + aXZ^2 + bZ^3, standing for the affine point (X / Z, Y / Z)
+// when Z != 0 and for the point at infinity (0 : 1 : 0) otherwise
 #[derive(Copy, Clone, Debug)]
 struct ProjectivePoint {
     x: Fq,
@@ -13,42 +19,247 @@ impl ProjectivePoint {
         ProjectivePoint { x, y, z }
     }
 
+    fn infinity(q: u8) -> Self {
+        ProjectivePoint::new(Fq::new(0, q), Fq::new(1, q), Fq::new(0, q))
+    }
+
+    fn from_affine(p: &Point, q: u8) -> Self {
+        match *p {
+            Point::Infinity => ProjectivePoint::infinity(q),
+            Point::Affine { x, y } => ProjectivePoint::new(x, y, Fq::new(1, q)),
+        }
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.value == 0
+    }
+
+    // Two triples are the same point when (X1, Y1, Z1) and (X2, Y2, Z2) are proportional,
+    // i.e. all 2x2 minors vanish. This needs no inversions and works for zero coordinates.
     fn is_isomorphic(&self, other: &ProjectivePoint) -> bool {
-        // Check if both points are at infinity
-        if self.z == Fq::new(0, FIELD) && other.z == Fq::new(0, FIELD) {
-            return true; // Both points are at infinity, hence isomorphic
+        self.x * other.y == other.x * self.y
+            && self.x * other.z == other.x * self.z
+            && self.y * other.z == other.y * self.z
+    }
+
+    // (X / Z, Y / Z), or O when Z = 0
+    fn to_affine(self) -> Result<Point, Error> {
+        if self.is_infinity() {
+            return Ok(Point::Infinity);
+        }
+        let z_inv = self.z.inv()?;
+        Ok(Point::new(self.x * z_inv, self.y * z_inv))
+    }
+
+    // The representative whose last non-zero coordinate is 1
+    fn normalize(&self) -> Result<Self, Error> {
+        let last = [self.z, self.y, self.x]
+            .into_iter()
+            .find(|c| c.value != 0)
+            .ok_or(Error::NotOnCurve)?;
+        let inv = last.inv()?;
+        Ok(ProjectivePoint::new(
+            self.x * inv,
+            self.y * inv,
+            self.z * inv,
+        ))
+    }
+
+    fn negate(&self) -> Self {
+        ProjectivePoint::new(self.x, -self.y, self.z)
+    }
+
+    // add-1998-cmo-2: 12M + 2S. The formula divides by nothing, but it needs P != Q and
+    // neither input at infinity.
+    fn add(&self, other: &ProjectivePoint, curve: &EllipticCurve) -> Self {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
         }
 
-        // Ensure other point is not at infinity
-        if self.z == Fq::new(0, FIELD) || other.z == Fq::new(0, FIELD) {
-            return false; // One point at infinity, not isomorphic
+        let y1z2 = self.y * other.z;
+        let x1z2 = self.x * other.z;
+        let z1z2 = self.z * other.z;
+        let u = other.y * self.z - y1z2;
+        let v = other.x * self.z - x1z2;
+        if v.value == 0 {
+            // Same x: P = Q or P = -Q
+            return if u.value == 0 {
+                self.double(curve)
+            } else {
+                ProjectivePoint::infinity(curve.q)
+            };
         }
 
-        // Compare the ratios of the coordinates
-        let x_ratio = self.x * inverse_mod(other.x.value, 41);
-        let y_ratio = self.y * inverse_mod(other.y.value, 41);
-        let z_ratio = self.z * inverse_mod(other.z.value, 41);
+        let uu = u * u;
+        let vv = v * v;
+        let vvv = v * vv;
+        let r = vv * x1z2;
+        let a = uu * z1z2 - vvv - Fq::new(2, curve.q) * r;
+        ProjectivePoint::new(v * a, u * (r - a) - vvv * y1z2, vvv * z1z2)
+    }
+
+    // dbl-2007-bl: 5M + 6S. A point with Y = 0 has order 2 and doubles to Z3 = 0.
+    fn double(&self, curve: &EllipticCurve) -> Self {
+        if self.is_infinity() {
+            return *self;
+        }
 
-        // The points are isomorphic if all ratios are equal
-        x_ratio == y_ratio && y_ratio == z_ratio
+        let two = Fq::new(2, curve.q);
+        let xx = self.x * self.x;
+        let zz = self.z * self.z;
+        let w = curve.a * zz + Fq::new(3, curve.q) * xx;
+        let s = two * self.y * self.z;
+        let ss = s * s;
+        let sss = s * ss;
+        let r = self.y * s;
+        let rr = r * r;
+        let b = (self.x + r) * (self.x + r) - xx - rr;
+        let h = w * w - two * b;
+        ProjectivePoint::new(h * s, w * (b - h) - two * rr, sss)
+    }
+
+    fn scalar_mul(&self, k: u32, curve: &EllipticCurve) -> Self {
+        let mut result = ProjectivePoint::infinity(curve.q);
+        for i in (0..32 - k.leading_zeros()).rev() {
+            result = result.double(curve);
+            if k >> i & 1 == 1 {
+                result = result.add(self, curve);
+            }
+        }
+        result
+    }
+
+    fn is_on_curve(&self, curve: &EllipticCurve) -> bool {
+        let (x, y, z) = (self.x, self.y, self.z);
+        y * y * z == x * x * x + curve.a * x * z * z + curve.b * z * z * z
     }
 }
 
-pub fn run() {
-    let field = 41;
-    let p1 = Point {
-        x: Fq::new(1, field),
-        y: Fq::new(2, field),
-    };
+impl PartialEq for ProjectivePoint {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_isomorphic(other)
+    }
+}
+
+impl Eq for ProjectivePoint {}
+
+// Equal points have the same normalized representative, so they hash the same. Triples
+// that cannot be normalized (all zero, or q not prime) all hash alike.
+impl Hash for ProjectivePoint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Ok(n) = self.normalize() {
+            (n.x.value, n.y.value, n.z.value).hash(state);
+        }
+    }
+}
+
+// Checks the projective group law on E(Fq) against the affine one
+fn check_projective_arithmetic(curve: &EllipticCurve) {
+    let q = curve.q;
+    let mut affine = Vec::new();
+    for x in 0..q {
+        for y in 0..q {
+            let p = Point::new(Fq::new(x as i32, q), Fq::new(y as i32, q));
+            if ProjectivePoint::from_affine(&p, q).is_on_curve(curve) {
+                affine.push(p);
+            }
+        }
+    }
+    let order = affine.len() as u32 + 1;
+    let infinity = ProjectivePoint::infinity(q);
+
+    let mut classes = HashSet::new();
+    for p in &affine {
+        let pp = ProjectivePoint::from_affine(p, q);
+        assert_eq!(pp.add(&infinity, curve), pp);
+        assert_eq!(pp.add(&pp.negate(), curve), infinity);
+        assert_eq!(pp.scalar_mul(order, curve), infinity);
+        assert_eq!(pp.add(&pp, curve), pp.double(curve));
+
+        // Every rescaling (lX : lY : lZ) is the same class
+        for l in 1..q {
+            let l = Fq::new(l as i32, q);
+            classes.insert(ProjectivePoint::new(l * pp.x, l * pp.y, l * pp.z));
+        }
+
+        for other in affine.iter().step_by(5) {
+            let sum = pp.add(&ProjectivePoint::from_affine(other, q), curve);
+            assert!(sum.is_on_curve(curve));
+            assert_eq!(sum.to_affine(), curve.add(p, other));
+        }
+    }
+    for l in 1..q {
+        classes.insert(ProjectivePoint::new(
+            Fq::new(0, q),
+            Fq::new(l as i32, q),
+            Fq::new(0, q),
+        ));
+    }
+    assert_eq!(classes.len() as u32, order);
+    println!(
+        "#E(F{}) = {}, {} classes among all rescaled projective triples",
+        q,
+        order,
+        classes.len()
+    );
+}
 
-    let p2 = Point {
-        x: Fq::new(5, field),
-        y: Fq::new(6, field),
+// Checks that E' = E^(u, 0, 0, 0) by mapping every point of E'(Fq) to E(Fq)
+fn check_isomorphism(e: &EllipticCurve, e_prime: &EllipticCurve) -> Fq {
+    let Ok(Isomorphism::Rational { u, .. }) = e.isomorphism(e_prime) else {
+        panic!("curves are not isomorphic over F{}", e.q);
     };
+    let on_curve = |curve: &EllipticCurve, x: Fq, y: Fq| y * y == x * x * x + curve.a * x + curve.b;
+    for x in 0..e.q {
+        for y in 0..e.q {
+            let (x, y) = (Fq::new(x as i32, e.q), Fq::new(y as i32, e.q));
+            if on_curve(e_prime, x, y) {
+                assert!(on_curve(e, u * u * x, u * u * u * y));
+            }
+        }
+    }
+    u
+}
+
+// Over Fq with q > 3 there are 2q + 6, 2q + 2, 2q + 4 or 2q classes for q = 1, 5, 7, 11
+// mod 12: two for every j, plus extra twists of j = 0 and j = 1728
+fn check_isomorphism_classes(q: u8) {
+    let classes = isomorphism_classes(q).unwrap();
+    let extra = [6, 2, 4, 0][(q as usize % 12) / 3];
+    assert_eq!(classes.len(), 2 * q as usize + extra);
+
+    // Each class is an orbit of (a, b) -> (u^4 a, u^6 b), of size (q - 1) / #Aut(E)
+    for class in &classes {
+        assert_eq!(class.size * class.automorphisms, q as usize - 1);
+    }
+    let curves: usize = classes.iter().map(|class| class.size).sum();
+    assert_eq!(curves, q as usize * (q as usize - 1));
 
-    let eq = EllipticCurve::new(Fq::new(4, field), Fq::new(-1, field), field); // E: y^2 = x^3 + ax + b => y^2 = x^3 + 4x -1
-    let result = eq.add(&p1, &p2);
-    println!("Result of P1 + P2: {:?}", result);
+    for j in [Fq::new(0, q), Fq::new(1728, q)] {
+        let special: Vec<_> = classes.iter().filter(|class| class.j == j).collect();
+        println!(
+            "F{}: j = {} has {} classes with #Aut = {:?}",
+            q,
+            j.value,
+            special.len(),
+            special.iter().map(|c| c.automorphisms).collect::<Vec<_>>()
+        );
+    }
+    println!("F{}: {} isomorphism classes", q, classes.len());
+}
+
+pub fn run() {
+    let field = FIELD;
+    let p1 = Point::new(Fq::new(1, field), Fq::new(2, field));
+
+    let p2 = Point::new(Fq::new(5, field), Fq::new(6, field));
+
+    let eq = EllipticCurve::try_new(Fq::new(4, field), Fq::new(-1, field), field).unwrap(); // E: y^2 = x^3 + ax + b => y^2 = x^3 + 4x -1
+    let result = eq.add(&p1, &p2).unwrap();
+    println!("Result of P1 + P2: {}", result);
 
     // Projective points
     let p_proj1 = ProjectivePoint::new(Fq::new(1, field), Fq::new(2, field), Fq::new(1, field));
@@ -70,4 +281,64 @@ pub fn run() {
         (p_proj1.x.value, p_proj1.y.value, p_proj1.z.value),
         (p_proj2.x.value, p_proj2.y.value, p_proj2.z.value)
     );
+
+    // (0 : 1 : 0) and (0 : 5 : 0) are both the point at infinity
+    let infinity = ProjectivePoint::infinity(field);
+    let scaled = ProjectivePoint::new(Fq::new(0, field), Fq::new(5, field), Fq::new(0, field));
+    assert!(infinity.is_isomorphic(&scaled));
+    assert_eq!(infinity.to_affine(), Ok(Point::Infinity));
+    check_projective_arithmetic(&eq);
+
+    // y^2 = x^3 + 4x - 1 rescaled by u = 3 is the same curve in other coordinates...
+    let u = Fq::new(3, field);
+    let u_inv = u.inv().unwrap();
+    let e_prime = EllipticCurve::new(eq.a * u_inv.pow(4), eq.b * u_inv.pow(6), field);
+    println!(
+        "j(E) = {}, j(E') = {}",
+        eq.j_invariant().unwrap(),
+        e_prime.j_invariant().unwrap()
+    );
+    let found = check_isomorphism(&eq, &e_prime);
+    assert!(found.pow(2) == u.pow(2));
+
+    // ...while scaling by a non-square d only gives the quadratic twist
+    let d = Fq::new(3, field);
+    let twist = EllipticCurve::new(eq.a * d * d, eq.b * d * d * d, field);
+    assert_eq!(eq.j_invariant(), twist.j_invariant());
+    assert_eq!(eq.isomorphism(&twist), Ok(Isomorphism::Extension(2)));
+
+    // 41 = 1 mod 4, so y^2 = x^3 + x has four automorphisms and quartic twists
+    let e1728 = EllipticCurve::new(Fq::new(1, field), Fq::new(0, field), field);
+    assert_eq!(e1728.automorphisms().len(), 4);
+    let square = EllipticCurve::new(Fq::new(2, field), Fq::new(0, field), field);
+    assert_eq!(e1728.isomorphism(&square), Ok(Isomorphism::Extension(2)));
+    let non_square = EllipticCurve::new(Fq::new(3, field), Fq::new(0, field), field);
+    assert_eq!(
+        e1728.isomorphism(&non_square),
+        Ok(Isomorphism::Extension(4))
+    );
+    assert_eq!(e1728.isomorphism(&eq), Ok(Isomorphism::NotIsomorphic));
+
+    // 37 = 1 mod 3, so y^2 = x^3 + 1 has six automorphisms and cubic and sextic twists
+    let e0 = EllipticCurve::new(Fq::new(0, 37), Fq::new(1, 37), 37);
+    assert_eq!(e0.automorphisms().len(), 6);
+    let mut degrees: Vec<u32> = (2..37)
+        .filter_map(|b| {
+            match e0.isomorphism(&EllipticCurve::new(Fq::new(0, 37), Fq::new(b, 37), 37)) {
+                Ok(Isomorphism::Extension(d)) => Some(d),
+                _ => None,
+            }
+        })
+        .collect();
+    degrees.sort();
+    degrees.dedup();
+    println!(
+        "y^2 = x^3 + b over F37 is a twist of y^2 = x^3 + 1 of degree {:?}",
+        degrees
+    );
+    assert_eq!(degrees, vec![2, 3, 6]);
+
+    for q in [37, 41, 43, 47] {
+        check_isomorphism_classes(q);
+    }
 }
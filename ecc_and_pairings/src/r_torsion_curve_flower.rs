@@ -1,3 +1,4 @@
+use crate::flower::{self, Petal, PetalLabel};
 use rand::Rng;
 use std::{collections::HashSet, hash::Hash};
 
@@ -17,11 +18,31 @@ struct Point {
     z: Fq2, // For projective coordinates
 }
 
+// The contribution of a kernel point Q to Velu's formulas: Q, g^x_Q = 3x_Q^2 + a,
+// g^y_Q = -2y_Q, v_Q = 2g^x_Q (just g^x_Q when Q has order 2) and u_Q = (g^y_Q)^2
+#[derive(Debug)]
+struct VeluTerm {
+    x: Fq2,
+    y: Fq2,
+    gx: Fq2,
+    gy: Fq2,
+    v: Fq2,
+    u: Fq2,
+}
+
+#[derive(Debug)]
+struct Isogeny {
+    kernel: HashSet<Point>,
+    terms: Vec<VeluTerm>,
+    codomain: EllipticCurve,
+}
+
 #[derive(Debug)]
 struct EllipticCurve {
-    a: Fq,
-    b: Fq,
+    a: Fq2, // Curves from the demos are over Fq, Velu codomains may need Fq2
+    b: Fq2,
     q: u64,
+    zeta: Option<Fq2>, // Primitive cube root of unity, found once for the distortion map
 }
 
 impl Fq {
@@ -84,6 +105,14 @@ impl Fq2 {
         Fq2 { real, imag }
     }
 
+    fn one() -> Self {
+        Fq2::new(Fq(1), Fq(0))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.real.0 == 0 && self.imag.0 == 0
+    }
+
     fn add(&self, other: &Fq2, q: u64) -> Fq2 {
         Fq2 {
             real: self.real.add(&other.real, q),
@@ -134,6 +163,41 @@ impl Fq2 {
             imag: Fq((q - self.imag.0) % q).mul(&norm_inv, q),
         })
     }
+
+    fn pow(&self, exp: u64, q: u64) -> Fq2 {
+        let mut result = Fq2::one();
+        let mut base = *self;
+        let mut exp = exp;
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base, q);
+            }
+            base = base.square(q);
+            exp >>= 1;
+        }
+        result
+    }
+
+    // A primitive cube root of unity a^((q^2 - 1) / 3) for the first a in Fq2 that is not a
+    // cube. Fq2* is cyclic of order q^2 - 1, so there is one unless 3 divides q, and it lies
+    // outside Fq when q = 2 mod 3.
+    fn primitive_cube_root(q: u64) -> Option<Fq2> {
+        if q.is_multiple_of(3) {
+            return None;
+        }
+        (1..q * q)
+            .map(|v| Fq2::new(Fq(v % q), Fq(v / q)).pow((q * q - 1) / 3, q))
+            .find(|z| *z != Fq2::one())
+    }
+
+    // The q-power Frobenius on Fq2 = Fq(i) with q = 3 mod 4 maps i to -i
+    fn frobenius(&self, q: u64) -> Fq2 {
+        Fq2 {
+            real: self.real,
+            imag: Fq((q - self.imag.0) % q),
+        }
+    }
 }
 
 impl Point {
@@ -156,13 +220,43 @@ impl Point {
 
 impl EllipticCurve {
     fn new(a: Fq, b: Fq, q: u64) -> Self {
-        EllipticCurve { a, b, q }
+        EllipticCurve {
+            a: Fq2::new(a, Fq(0)),
+            b: Fq2::new(b, Fq(0)),
+            q,
+            zeta: Fq2::primitive_cube_root(q),
+        }
     }
 
+    // Hasse invariant: the coefficient of x^(q-1) in (x^3 + ax + b)^((q-1)/2) for prime q.
+    // The curve may be defined over Fq or Fq2.
+    fn hasse_invariant(&self) -> Fq2 {
+        let q = self.q;
+        let zero = Fq2::new(Fq(0), Fq(0));
+        let f = [self.b, self.a, zero, Fq2::one()];
+        let mut power = vec![Fq2::one()];
+        for _ in 0..(q - 1) / 2 {
+            let mut product = vec![zero; power.len() + 3];
+            for (i, c) in power.iter().enumerate() {
+                for (j, d) in f.iter().enumerate() {
+                    product[i + j] = product[i + j].add(&c.mul(d, q), q);
+                }
+            }
+            power = product;
+        }
+        power[q as usize - 1]
+    }
+
+    // E is supersingular exactly when its Hasse invariant vanishes, or equivalently when
+    // q divides the trace of Frobenius. This replaces the old shortcut (a = 0, b != 0 and
+    // q = 3 mod 4), which is wrong: y^2 = x^3 + b is supersingular iff q = 2 mod 3.
     fn is_supersingular(&self) -> bool {
-        // For prime q ≡ 3 mod 4, y^2 = x^3 + ax + b is supersingular
-        // if and only if a = 0 and b ≠ 0
-        self.a.0 == 0 && self.b.0 != 0 && self.q % 4 == 3
+        self.hasse_invariant().is_zero()
+    }
+
+    // Trace of Frobenius t = q + 1 - #E(Fq)
+    fn trace(&self) -> i64 {
+        self.q as i64 + 1 - self.base_field_points().len() as i64
     }
 
     fn is_on_curve(&self, point: &Point) -> bool {
@@ -171,8 +265,8 @@ impl EllipticCurve {
         }
 
         let x3 = point.x.mul(&point.x, self.q).mul(&point.x, self.q);
-        let ax = Fq2::new(self.a, Fq(0)).mul(&point.x, self.q);
-        let rhs = x3.add(&ax, self.q).add(&Fq2::new(self.b, Fq(0)), self.q);
+        let ax = self.a.mul(&point.x, self.q);
+        let rhs = x3.add(&ax, self.q).add(&self.b, self.q);
         let y2 = point.y.square(self.q);
 
         y2 == rhs
@@ -228,9 +322,7 @@ impl EllipticCurve {
         let x_squared = x.square(self.q);
         let three_x_squared = x_squared.mul(&Fq2::new(Fq(3), Fq(0)), self.q);
 
-        let slope = three_x_squared
-            .add(&Fq2::new(self.a, Fq(0)), self.q)
-            .mul(&two_y_inv, self.q);
+        let slope = three_x_squared.add(&self.a, self.q).mul(&two_y_inv, self.q);
 
         let x3 = slope
             .square(self.q)
@@ -257,6 +349,265 @@ impl EllipticCurve {
         result
     }
 
+    fn negate(&self, p: &Point) -> Point {
+        if p.z.is_zero() {
+            return p.clone();
+        }
+        Point::new(p.x, Fq2::new(Fq(0), Fq(0)).sub(&p.y, self.q))
+    }
+
+    // The q-power Frobenius endomorphism pi(x, y) = (x^q, y^q)
+    fn frobenius(&self, p: &Point) -> Point {
+        if p.z.is_zero() {
+            return p.clone();
+        }
+        Point::new(p.x.frobenius(self.q), p.y.frobenius(self.q))
+    }
+
+    // Smallest k with r | q^k - 1, None when there is none (r and q not coprime). The order
+    // of q mod r is below r, so r steps suffice. Coordinates live in Fq2, so E[r] is only
+    // all here when k <= 2, which the callers check.
+    fn embedding_degree(&self, r: u64) -> Option<u64> {
+        let mut qk = 1 % r;
+        (1..r).find(|_| {
+            qk = qk * (self.q % r) % r;
+            qk == 1
+        })
+    }
+
+    // Tr(P) = P + pi(P) + ... + pi^(k-1)(P) for the embedding degree k. It maps E[r] onto
+    // G1 = E(Fq)[r], the eigenspace where pi acts as [1].
+    fn trace_map(&self, p: &Point, k: u64) -> Point {
+        let mut sum = Point::identity();
+        let mut pi_i = p.clone();
+        for _ in 0..k {
+            sum = self.add(&sum, &pi_i);
+            pi_i = self.frobenius(&pi_i);
+        }
+        sum
+    }
+
+    // aTr(P) = [k]P - Tr(P). It maps E[r] onto the trace-zero subgroup G2, the
+    // eigenspace where pi acts as [q].
+    fn anti_trace_map(&self, p: &Point, k: u64) -> Point {
+        self.add(&self.scalar_mul(k, p), &self.negate(&self.trace_map(p, k)))
+    }
+
+    // Generator of G1: the trace of any point of E[r] outside G2
+    fn g1_generator(&self, tors_pts: &[Point], k: u64) -> Point {
+        tors_pts
+            .iter()
+            .map(|p| self.trace_map(p, k))
+            .find(|g| !g.z.is_zero())
+            .unwrap()
+    }
+
+    // Generator of G2: the anti-trace of any point of E[r] outside G1
+    fn g2_generator(&self, tors_pts: &[Point], k: u64) -> Point {
+        tors_pts
+            .iter()
+            .map(|p| self.anti_trace_map(p, k))
+            .find(|g| !g.z.is_zero())
+            .unwrap()
+    }
+
+    // Velu's formulas (Washington, Theorem 12.16) for the separable isogeny with the given
+    // finite kernel. Each pair {Q, -Q} of non-zero kernel points contributes one term, and
+    // the codomain is y^2 = x^3 + (a - 5v)x + (b - 7w) with v = sum v_Q and
+    // w = sum (u_Q + x_Q v_Q).
+    fn velu(&self, kernel: &HashSet<Point>) -> Isogeny {
+        let q = self.q;
+        let zero = Fq2::new(Fq(0), Fq(0));
+
+        let mut terms = Vec::new();
+        let mut seen = HashSet::new();
+        for point in kernel {
+            if point.z.is_zero() || seen.contains(point) {
+                continue;
+            }
+            seen.insert(self.negate(point));
+
+            let gx = point
+                .x
+                .square(q)
+                .mul(&Fq2::new(Fq(3), Fq(0)), q)
+                .add(&self.a, q);
+            let gy = zero.sub(&point.y.add(&point.y, q), q);
+            let v = if point.y.is_zero() {
+                gx
+            } else {
+                gx.add(&gx, q)
+            };
+            let u = gy.square(q);
+            terms.push(VeluTerm {
+                x: point.x,
+                y: point.y,
+                gx,
+                gy,
+                v,
+                u,
+            });
+        }
+
+        let v = terms.iter().fold(zero, |acc, t| acc.add(&t.v, q));
+        let w = terms
+            .iter()
+            .fold(zero, |acc, t| acc.add(&t.u.add(&t.x.mul(&t.v, q), q), q));
+        let codomain = EllipticCurve {
+            a: self.a.sub(&v.mul(&Fq2::new(Fq(5), Fq(0)), q), q),
+            b: self.b.sub(&w.mul(&Fq2::new(Fq(7), Fq(0)), q), q),
+            q,
+            zeta: self.zeta,
+        };
+
+        Isogeny {
+            kernel: kernel.clone(),
+            terms,
+            codomain,
+        }
+    }
+
+    // Evaluates at s the line through t and p (the tangent when t == p) divided by the
+    // vertical line through t + p, and returns t + p alongside it. These are the
+    // building blocks of Miller's algorithm: div(l / v) = (t) + (p) - (t + p) - (O).
+    fn line_function(&self, t: &Point, p: &Point, s: &Point) -> (Point, Fq2) {
+        let sum = self.add(t, p);
+
+        // t = -p: the line is the vertical x - x_t and there is no vertical through O
+        if sum.z.is_zero() {
+            return (sum, s.x.sub(&t.x, self.q));
+        }
+
+        let slope = if t == p {
+            let numerator =
+                t.x.square(self.q)
+                    .mul(&Fq2::new(Fq(3), Fq(0)), self.q)
+                    .add(&self.a, self.q);
+            let denominator = t.y.mul(&Fq2::new(Fq(2), Fq(0)), self.q);
+            numerator.mul(&denominator.inv(self.q).unwrap(), self.q)
+        } else {
+            p.y.sub(&t.y, self.q)
+                .mul(&p.x.sub(&t.x, self.q).inv(self.q).unwrap(), self.q)
+        };
+
+        // l(s) = y_s - y_t - slope * (x_s - x_t)
+        let line =
+            s.y.sub(&t.y, self.q)
+                .sub(&slope.mul(&s.x.sub(&t.x, self.q), self.q), self.q);
+        // v(s) = x_s - x_(t+p)
+        let vertical = s.x.sub(&sum.x, self.q);
+
+        (sum, line.mul(&vertical.inv(self.q).unwrap(), self.q))
+    }
+
+    // Miller's algorithm: evaluates at s the normalised function f_{r,p} with divisor
+    // r(p) - r(O), building it up with double-and-add over the bits of r.
+    fn miller(&self, p: &Point, s: &Point, r: u64) -> Fq2 {
+        let mut t = p.clone();
+        let mut f = Fq2::one();
+
+        for i in (0..63 - r.leading_zeros()).rev() {
+            let (doubled, l) = self.line_function(&t, &t, s);
+            f = f.square(self.q).mul(&l, self.q);
+            t = doubled;
+
+            if (r >> i) & 1 == 1 {
+                let (added, l) = self.line_function(&t, p, s);
+                f = f.mul(&l, self.q);
+                t = added;
+            }
+        }
+
+        f
+    }
+
+    // Weil pairing e_r(p, s) = (-1)^r f_{r,p}(s) / f_{r,s}(p) for p, s in E[r].
+    // The result is an r-th root of unity in Fq2. Linearly dependent inputs pair to 1
+    // (the pairing is alternating), which also keeps Miller's algorithm away from the
+    // zeros and poles of its line functions.
+    fn weil_pairing(&self, p: &Point, s: &Point, r: u64) -> Fq2 {
+        if (0..r).any(|i| self.scalar_mul(i, p) == *s)
+            || (0..r).any(|i| self.scalar_mul(i, s) == *p)
+        {
+            return Fq2::one();
+        }
+
+        let pairing = self
+            .miller(p, s, r)
+            .mul(&self.miller(s, p, r).inv(self.q).unwrap(), self.q);
+
+        if r % 2 == 1 {
+            Fq2::new(Fq(0), Fq(0)).sub(&pairing, self.q)
+        } else {
+            pairing
+        }
+    }
+
+    // Final exponentiation for k = 2: (q^2 - 1) / r = (q - 1) * (q + 1) / r.
+    // The easy part f^(q - 1) is a Frobenius and an inversion, the hard part
+    // (q + 1) / r is a plain exponentiation.
+    fn final_exponentiation(&self, f: &Fq2, r: u64) -> Fq2 {
+        let easy = f.frobenius(self.q).mul(&f.inv(self.q).unwrap(), self.q);
+        easy.pow((self.q + 1) / r, self.q)
+    }
+
+    // Reduced Tate pairing t_r(p, s) = f_{r,p}(s)^((q^2 - 1) / r) for p in E[r] and
+    // s in E(Fq2). If s lies in <p> the pairing is trivial and Miller's algorithm
+    // would hit a zero of its line functions, so 1 is returned directly.
+    fn tate_pairing(&self, p: &Point, s: &Point, r: u64) -> Fq2 {
+        if (0..r).any(|i| self.scalar_mul(i, p) == *s) {
+            return Fq2::one();
+        }
+
+        self.final_exponentiation(&self.miller(p, s, r), r)
+    }
+
+    // Distortion maps of the standard supersingular families: (x, y) -> (zeta x, y) on
+    // y^2 = x^3 + b with q = 2 mod 3, and (x, y) -> (-x, iy) on y^2 = x^3 + ax with
+    // q = 3 mod 4. Both are endomorphisms defined over Fq2 but not over Fq, so they move
+    // E(Fq)[r] onto another petal of E[r].
+    fn distortion_map(&self, p: &Point) -> Option<Point> {
+        let q = self.q;
+        let (x, y) = if self.a.is_zero() && q % 3 == 2 {
+            (p.x.mul(&self.zeta?, q), p.y)
+        } else if self.b.is_zero() && q % 4 == 3 {
+            let i = Fq2::new(Fq(0), Fq(1));
+            (Fq2::new(Fq(0), Fq(0)).sub(&p.x, q), p.y.mul(&i, q))
+        } else {
+            return None;
+        };
+
+        if p.z.is_zero() {
+            return Some(Point::identity());
+        }
+        Some(Point::new(x, y))
+    }
+
+    // Symmetric pairing e^(P, Q) = t_r(P, phi(Q)) on G1 x G1. Unlike the Tate pairing
+    // restricted to G1, it is non-degenerate: e^(P, P) != 1.
+    fn symmetric_pairing(&self, p: &Point, s: &Point, r: u64) -> Fq2 {
+        self.tate_pairing(p, &self.distortion_map(s).unwrap(), r)
+    }
+
+    fn base_field_points(&self) -> Vec<Point> {
+        let mut points = vec![Point::identity()];
+        for x in 0..self.q {
+            let x = Fq2::new(Fq(x), Fq(0));
+            let rhs = x
+                .square(self.q)
+                .mul(&x, self.q)
+                .add(&self.a.mul(&x, self.q), self.q)
+                .add(&self.b, self.q);
+            for y in 0..self.q {
+                let y = Fq2::new(Fq(y), Fq(0));
+                if y.square(self.q) == rhs {
+                    points.push(Point::new(x, y));
+                }
+            }
+        }
+        points
+    }
+
     fn points(&self) -> Vec<Point> {
         let mut points = Vec::new();
         points.push(Point::identity());
@@ -265,8 +616,8 @@ impl EllipticCurve {
             for x_imag in 0..self.q {
                 let x = Fq2::new(Fq(x_real), Fq(x_imag));
                 let x3 = x.mul(&x, self.q).mul(&x, self.q);
-                let ax = Fq2::new(self.a, Fq(0)).mul(&x, self.q);
-                let rhs = x3.add(&ax, self.q).add(&Fq2::new(self.b, Fq(0)), self.q);
+                let ax = self.a.mul(&x, self.q);
+                let rhs = x3.add(&ax, self.q).add(&self.b, self.q);
 
                 for y_real in 0..self.q {
                     for y_imag in 0..self.q {
@@ -283,6 +634,34 @@ impl EllipticCurve {
     }
 }
 
+impl flower::TorsionCurve for EllipticCurve {
+    type Point = Point;
+
+    fn q(&self) -> u64 {
+        self.q
+    }
+
+    fn is_on_curve(&self, p: &Point) -> bool {
+        EllipticCurve::is_on_curve(self, p)
+    }
+
+    fn is_identity(&self, p: &Point) -> bool {
+        p.z.is_zero()
+    }
+
+    fn add(&self, p1: &Point, p2: &Point) -> Point {
+        EllipticCurve::add(self, p1, p2)
+    }
+
+    fn scalar_mul(&self, k: u64, p: &Point) -> Point {
+        EllipticCurve::scalar_mul(self, k, p)
+    }
+
+    fn frobenius(&self, p: &Point) -> Point {
+        EllipticCurve::frobenius(self, p)
+    }
+}
+
 fn find_torsion_points(curve: &EllipticCurve, points: &Vec<Point>, r: u64) -> HashSet<Point> {
     let mut tors_pts = HashSet::new();
     for point in points {
@@ -294,40 +673,268 @@ fn find_torsion_points(curve: &EllipticCurve, points: &Vec<Point>, r: u64) -> Ha
     tors_pts
 }
 
-fn flower_generator(tors_pts: HashSet<Point>) -> Vec<HashSet<Point>> {
-    let mut petals = Vec::new();
-    let mut remaining_points = tors_pts.clone();
-    let petals_count = (tors_pts.len() as f64).sqrt() as usize + 1;
-    let points_per_petal = petals_count - 1;
+impl Isogeny {
+    // phi(x, y) = (x + sum [v_Q / (x - x_Q) + u_Q / (x - x_Q)^2],
+    //              y - sum [u_Q 2y / (x - x_Q)^3 + v_Q (y - y_Q) / (x - x_Q)^2
+    //                       - g^x_Q g^y_Q / (x - x_Q)^2])
+    fn evaluate(&self, p: &Point) -> Point {
+        if p.z.is_zero() || self.kernel.contains(p) {
+            return Point::identity();
+        }
+
+        let q = self.codomain.q;
+        let mut x = p.x;
+        let mut y = p.y;
+        for t in &self.terms {
+            let d = p.x.sub(&t.x, q).inv(q).unwrap();
+            let d2 = d.square(q);
+            let d3 = d2.mul(&d, q);
+
+            x = x.add(&t.v.mul(&d, q), q).add(&t.u.mul(&d2, q), q);
+            let two_y = p.y.add(&p.y, q);
+            y = y
+                .sub(&t.u.mul(&two_y, q).mul(&d3, q), q)
+                .sub(&t.v.mul(&p.y.sub(&t.y, q), q).mul(&d2, q), q)
+                .add(&t.gx.mul(&t.gy, q).mul(&d2, q), q);
+        }
+        Point::new(x, y)
+    }
+}
 
+// Splits E[r] into its r + 1 cyclic subgroups of order r. E[r] is enumerated in the
+// fixed order of curve.points() so the same generators come out on every run. None unless
+// the embedding degree is at most 2, so that E[r] lies over Fq2.
+fn torsion_flower(curve: &EllipticCurve, r: u64) -> Option<Vec<Petal<Point>>> {
+    let k = curve.embedding_degree(r).filter(|&k| k <= 2)?;
+    let tors_pts: Vec<Point> = curve
+        .points()
+        .into_iter()
+        .filter(|p| curve.scalar_mul(r, p).z.is_zero())
+        .collect();
+
+    let g1 = curve.g1_generator(&tors_pts, k);
+    let g2 = curve.g2_generator(&tors_pts, k);
+    Some(flower::torsion_flower(curve, &g1, &g2, r))
+}
+
+// Checks the defining properties of the Weil pairing on a set of r-torsion points
+fn check_weil_pairing(curve: &EllipticCurve, tors_pts: &[Point], r: u64) {
+    let q = curve.q;
     let mut rng = rand::thread_rng();
 
-    for _ in 0..petals_count {
-        if remaining_points.is_empty() {
-            break;
-        }
+    for _ in 0..10 {
+        let p = &tors_pts[rng.gen_range(0..tors_pts.len())];
+        let s = &tors_pts[rng.gen_range(0..tors_pts.len())];
+        let e = curve.weil_pairing(p, s, r);
+
+        // e_r(P, Q) is an r-th root of unity
+        assert_eq!(e.pow(r, q), Fq2::one());
+
+        // Alternation: e_r(P, P) = 1 and e_r(Q, P) = e_r(P, Q)^-1
+        assert_eq!(curve.weil_pairing(p, p, r), Fq2::one());
+        assert_eq!(curve.weil_pairing(s, p, r).mul(&e, q), Fq2::one());
 
-        let random_point = remaining_points
+        // Bilinearity: e_r([a]P, [b]Q) = e_r(P, Q)^(ab) and e_r(P + P', Q) = e_r(P, Q) e_r(P', Q)
+        let a = rng.gen_range(1..r);
+        let b = rng.gen_range(1..r);
+        let e_ab = curve.weil_pairing(&curve.scalar_mul(a, p), &curve.scalar_mul(b, s), r);
+        assert_eq!(e_ab, e.pow(a * b, q));
+
+        let p2 = &tors_pts[rng.gen_range(0..tors_pts.len())];
+        let e_sum = curve.weil_pairing(&curve.add(p, p2), s, r);
+        assert_eq!(e_sum, e.mul(&curve.weil_pairing(p2, s, r), q));
+
+        println!("e_{}(P, Q) = {:?}", r, e);
+    }
+
+    // Non-degeneracy: every P != O pairs non-trivially with some Q in E[r]
+    for p in tors_pts.iter().filter(|p| !p.z.is_zero()) {
+        assert!(tors_pts
             .iter()
-            .filter(|p| p.z.real.0 != 0 || p.z.imag.0 != 0)
-            .nth(rng.gen_range(0..remaining_points.len()))
-            .unwrap()
-            .clone();
+            .any(|s| curve.weil_pairing(p, s, r) != Fq2::one()));
+    }
+    println!(
+        "Weil pairing is bilinear, alternating and non-degenerate on E[{}]",
+        r
+    );
+}
+
+// Checks the reduced Tate pairing with P in G1 = E(Fq)[r] against the full torsion set
+fn check_tate_pairing(curve: &EllipticCurve, tors_pts: &[Point], r: u64) {
+    let q = curve.q;
+    let g1: Vec<&Point> = tors_pts
+        .iter()
+        .filter(|p| !p.z.is_zero() && p.x.imag.0 == 0 && p.y.imag.0 == 0)
+        .collect();
+    let p = g1[0];
+
+    for s in tors_pts.iter().filter(|s| !s.z.is_zero()).take(10) {
+        let t = curve.tate_pairing(p, s, r);
+
+        // The easy/hard split agrees with a single exponentiation by (q^2 - 1) / r, and
+        // since P pairs trivially with G1 when k > 1, shifting Q by G1 leaves t_r unchanged
+        if !(0..r).any(|i| curve.scalar_mul(i, p) == *s) {
+            let f = curve.miller(p, s, r);
+            assert_eq!(t, f.pow((q * q - 1) / r, q));
+            assert_eq!(curve.tate_pairing(p, &curve.add(s, g1[1]), r), t);
+        }
+        assert_eq!(t.pow(r, q), Fq2::one());
+
+        // Bilinearity
+        let t_ab = curve.tate_pairing(&curve.scalar_mul(2, p), &curve.scalar_mul(3, s), r);
+        assert_eq!(t_ab, t.pow(6, q));
+
+        println!("t_{}(P, Q) = {:?} with Q = {:?}", r, t, s);
+    }
+
+    assert!(tors_pts
+        .iter()
+        .any(|s| curve.tate_pairing(p, s, r) != Fq2::one()));
+    println!(
+        "Reduced Tate pairing is bilinear and non-degenerate on E[{}]",
+        r
+    );
+}
 
-        let mut petal = HashSet::new();
+// Checks the trace and anti-trace maps split E[r] into the Frobenius eigenspaces
+fn check_trace_maps(curve: &EllipticCurve, tors_pts: &[Point], r: u64) {
+    let q = curve.q;
+    let Some(k) = curve.embedding_degree(r).filter(|&k| k <= 2) else {
+        println!("E[{}] does not lie over Fq2, skipping the trace maps", r);
+        return;
+    };
+
+    for p in tors_pts {
+        let tr = curve.trace_map(p, k);
+        let atr = curve.anti_trace_map(p, k);
+
+        // pi acts as [1] on G1 and as [q] on G2
+        assert_eq!(curve.frobenius(&tr), tr);
+        assert_eq!(curve.frobenius(&atr), curve.scalar_mul(q, &atr));
+        // G2 is the trace-zero subgroup
+        assert!(curve.trace_map(&atr, k).z.is_zero());
+    }
+
+    let g1 = curve.g1_generator(tors_pts, k);
+    let g2 = curve.g2_generator(tors_pts, k);
+    let petals = flower::petal_generators(curve, &g1, &g2, r);
 
-        for j in 1..=points_per_petal {
-            petal.insert(random_point.clone());
+    // The remaining petals are neither in G1 nor in G2
+    for (_, generator) in &petals[2..] {
+        assert_ne!(curve.frobenius(generator), *generator);
+        assert_ne!(curve.frobenius(generator), curve.scalar_mul(q, generator));
+        assert!(curve.scalar_mul(r, generator).z.is_zero());
+    }
+    println!("Trace and anti-trace maps split E[{}] into G1 and G2", r);
+}
+
+// Takes each petal of the flower as the kernel of a degree r isogeny and checks phi lands
+// on the codomain, is a group homomorphism and kills exactly the petal
+fn check_velu(curve: &EllipticCurve, flower: &[Petal<Point>], points: &[Point]) {
+    let mut rng = rand::thread_rng();
+
+    for petal in flower {
+        let isogeny = curve.velu(&petal.points);
+        let codomain = &isogeny.codomain;
+
+        let mut kernel = HashSet::new();
+        for p in points {
+            let image = isogeny.evaluate(p);
+            assert!(codomain.is_on_curve(&image));
+            if image.z.is_zero() {
+                kernel.insert(p.clone());
+            }
+        }
+        assert_eq!(kernel, petal.points);
+
+        for _ in 0..20 {
+            let p1 = &points[rng.gen_range(0..points.len())];
+            let p2 = &points[rng.gen_range(0..points.len())];
+            assert_eq!(
+                isogeny.evaluate(&curve.add(p1, p2)),
+                codomain.add(&isogeny.evaluate(p1), &isogeny.evaluate(p2))
+            );
         }
 
-        for point in &petal {
-            remaining_points.remove(point);
+        // Isogenous curves are both supersingular or both ordinary, and kernels fixed by
+        // Frobenius give codomains defined over Fq
+        assert!(codomain.is_supersingular());
+        if matches!(petal.label, PetalLabel::G1 | PetalLabel::G2) {
+            assert!(codomain.a.imag.0 == 0 && codomain.b.imag.0 == 0);
         }
 
-        petals.push(petal);
+        println!(
+            "E / {:?}: y^2 = x^3 + ({:?})x + ({:?})",
+            petal.label, codomain.a, codomain.b
+        );
+    }
+}
+
+// Checks the distortion map sends G1 = E(Fq)[r] to another r-torsion subgroup and that
+// the symmetric pairing built from it is bilinear, symmetric and non-degenerate on G1
+fn check_distortion_map(curve: &EllipticCurve, r: u64) {
+    let q = curve.q;
+    let points = curve.base_field_points();
+    let h = points.len() as u64 / r;
+    let g1: Vec<Point> = points
+        .iter()
+        .map(|p| curve.scalar_mul(h, p))
+        .filter(|p| !p.z.is_zero())
+        .collect();
+    let p = &g1[0];
+
+    for s in &g1 {
+        let phi_s = curve.distortion_map(s).unwrap();
+        assert!(curve.is_on_curve(&phi_s));
+        assert!(curve.scalar_mul(r, &phi_s).z.is_zero());
+        // phi(Q) is not fixed by Frobenius, so it left G1
+        assert_ne!(curve.frobenius(&phi_s), phi_s);
+        // phi is a group homomorphism
+        assert_eq!(
+            curve.distortion_map(&curve.add(p, s)).unwrap(),
+            curve.add(&curve.distortion_map(p).unwrap(), &phi_s)
+        );
+    }
+
+    let e = curve.symmetric_pairing(p, p, r);
+    assert_ne!(e, Fq2::one());
+    assert_eq!(e.pow(r, q), Fq2::one());
+    for s in g1.iter().take(5) {
+        assert_eq!(
+            curve.symmetric_pairing(p, s, r),
+            curve.symmetric_pairing(s, p, r)
+        );
+    }
+    for (a, b) in [(2, 3), (3, 4), (4, 4)] {
+        let e_ab = curve.symmetric_pairing(&curve.scalar_mul(a, p), &curve.scalar_mul(b, p), r);
+        assert_eq!(e_ab, e.pow(a * b, q));
+    }
+
+    println!(
+        "y^2 = x^3 + {}x + {} over F{}: phi(P) = {:?}, e^(P, P) = {:?}",
+        curve.a.real.0,
+        curve.b.real.0,
+        q,
+        curve.distortion_map(p).unwrap(),
+        e
+    );
+}
+
+// Checks the Hasse invariant against the traces of Frobenius over Fq and Fq2. Over Fq2 the
+// trace is t^2 - 2q, and #E(Fq2) is counted directly when the points are given.
+fn check_supersingularity(curve: &EllipticCurve, points: Option<&Vec<Point>>) {
+    let q = curve.q as i64;
+    let t = curve.trace();
+    let t2 = t * t - 2 * q;
+    if let Some(points) = points {
+        assert_eq!(points.len() as i64, q * q + 1 - t2);
     }
-    petals
+
+    let supersingular = curve.is_supersingular();
+    assert_eq!(supersingular, t % q == 0);
+    assert_eq!(supersingular, t2 % q == 0);
 }
+
 pub fn run() {
     let q: u64 = 59;
     let a = Fq::new(0, q);
@@ -342,21 +949,62 @@ pub fn run() {
     let points = curve.points();
     println!("Number of points: {}", points.len());
 
+    check_supersingularity(&curve, Some(&points));
+
+    // y^2 = x^3 + 1 over F67 is ordinary since 67 = 1 mod 3, although 67 = 3 mod 4
+    let ordinary = EllipticCurve::new(Fq(0), Fq(1), 67);
+    check_supersingularity(&ordinary, None);
+    assert!(!ordinary.is_supersingular());
+
+    // Every non-singular curve over F23
+    let mut supersingular = Vec::new();
+    for a in 0..23 {
+        for b in 0..23 {
+            if (4 * a * a * a + 27 * b * b) % 23 == 0 {
+                continue;
+            }
+            let curve = EllipticCurve::new(Fq(a), Fq(b), 23);
+            check_supersingularity(&curve, None);
+            if curve.is_supersingular() {
+                supersingular.push((a, b));
+            }
+        }
+    }
+    println!("Supersingular curves over F23: {:?}", supersingular);
+
     let tors_pts = find_torsion_points(&curve, &points, r);
     println!("Number of torsion points: {}", tors_pts.len());
 
-    let flower = flower_generator(tors_pts);
-    println!("Number of flower petals: {}", flower.len());
+    let tors_pts_list: Vec<Point> = tors_pts.iter().cloned().collect();
+    check_weil_pairing(&curve, &tors_pts_list, r);
+    check_tate_pairing(&curve, &tors_pts_list, r);
+    check_trace_maps(&curve, &tors_pts_list, r);
 
-    let zi3 = Fq2::new(Fq::new(29, q), Fq::new(24, q));
-    let mut rng = rand::thread_rng();
+    // q = 59 = -1 mod 5 gives k = 2. Mod 7 the order of 59 = 3 is 6, so E[7] needs Fq6,
+    // and 59 | q leaves no k at all.
+    assert_eq!(curve.embedding_degree(r), Some(2));
+    assert_eq!(curve.embedding_degree(7), Some(6));
+    assert!(torsion_flower(&curve, 7).is_none());
+    assert_eq!(curve.embedding_degree(q), None);
 
-    for _ in 0..10 {
-        let random_point = &points[rng.gen_range(0..points.len())];
-        let multiplied_x = random_point.x.mul(&zi3, q);
-        let new_point = Point::new(multiplied_x, random_point.y);
-
-        println!("Original point: {:?}", random_point);
-        println!("Multiplied point: {:?}", new_point);
+    let flower = torsion_flower(&curve, r).unwrap();
+    println!("Number of flower petals: {}", flower.len());
+    flower::check_torsion_flower(&curve, &flower, r);
+    for petal in &flower {
+        println!("{:?} = <{:?}>", petal.label, petal.generator);
     }
+    check_velu(&curve, &flower, &points);
+
+    // zi3 = 29 + 24i is a primitive cube root of unity, so (x, y) -> (zi3 x, y) is the
+    // distortion map of y^2 = x^3 + 1
+    let zi3 = Fq2::new(Fq::new(29, q), Fq::new(24, q));
+    assert_eq!(zi3.pow(3, q), Fq2::one());
+    let zeta = curve.zeta.unwrap();
+    assert!(zeta != Fq2::one() && zeta.pow(3, q) == Fq2::one());
+    assert!(zeta == zi3 || zeta == zi3.square(q));
+    check_distortion_map(&curve, r);
+
+    // y^2 = x^3 + x over F59 is supersingular too, with distortion map (x, y) -> (-x, iy)
+    let curve = EllipticCurve::new(Fq::new(1, q), Fq::new(0, q), q);
+    check_distortion_map(&curve, r);
 }
@@ -1,8 +1,7 @@
+use crate::error::Error;
 use num_bigint::{BigUint, RandBigInt};
 use num_traits::ToPrimitive;
 use num_traits::{One, Zero};
-use rand::Rng;
-use std::ops::{Add, Div, Mul, Sub};
 
 #[derive(Clone, Debug, PartialEq)]
 struct FiniteField {
@@ -21,12 +20,7 @@ struct EllipticCurve {
     b: FieldElement,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Point {
-    x: Option<FieldElement>,
-    y: Option<FieldElement>,
-    curve: EllipticCurve,
-}
+type Point = crate::point::Point<FieldElement>;
 
 #[derive(Clone, Debug)]
 struct Divisor {
@@ -47,110 +41,111 @@ impl FiniteField {
     }
 }
 
+// Every element carries its field, so the arithmetic below checks both operands agree and
+// returns MismatchedFields when they do not
 impl FieldElement {
     fn new(value: BigUint, field: FiniteField) -> Self {
-        let mut val = value % &field.prime;
-        if val < BigUint::zero() {
-            val += &field.prime;
+        FieldElement {
+            value: value % &field.prime,
+            field,
         }
-        FieldElement { value: val, field }
     }
 
-    fn pow(&self, exp: &BigUint) -> Self {
-        let mut base = self.clone();
-        let mut result = FieldElement::new(BigUint::one(), self.field.clone());
-        let mut exp = exp.clone();
-
-        while exp > BigUint::zero() {
-            if &exp % BigUint::from(2u32) == BigUint::one() {
-                result = result * base.clone();
-            }
-            base = base.clone() * base;
-            exp /= 2u32;
+    fn same_field(&self, other: &FieldElement) -> Result<&FiniteField, Error> {
+        if self.field != other.field {
+            return Err(Error::MismatchedFields);
         }
-        result
+        Ok(&self.field)
     }
 
-    fn inv(&self) -> Self {
-        // This implementation is based on Fermat's Little Theorem, which states that for a prime p and any integer a not divisible by p:
-        // a^(p-1) ≡ 1 (mod p)
-        // From this, we can derive that a^(p-2) is the multiplicative inverse of a in the field of integers modulo p.
-        let exp = &self.field.prime - BigUint::from(2u32);
-        self.pow(&exp)
+    fn add(&self, other: &FieldElement) -> Result<FieldElement, Error> {
+        let field = self.same_field(other)?;
+        Ok(FieldElement::new(&self.value + &other.value, field.clone()))
     }
-}
 
-impl Add for FieldElement {
-    type Output = FieldElement;
+    fn sub(&self, other: &FieldElement) -> Result<FieldElement, Error> {
+        let field = self.same_field(other)?;
+        // BigUint cannot go negative, so add p before subtracting
+        Ok(FieldElement::new(
+            &self.value + &field.prime - &other.value,
+            field.clone(),
+        ))
+    }
 
-    fn add(self, other: FieldElement) -> FieldElement {
-        assert_eq!(self.field, other.field);
-        FieldElement::new(self.value + other.value, self.field)
+    fn mul(&self, other: &FieldElement) -> Result<FieldElement, Error> {
+        let field = self.same_field(other)?;
+        Ok(FieldElement::new(&self.value * &other.value, field.clone()))
     }
-}
 
-// Implement Sub for FieldElement
-impl Sub for FieldElement {
-    type Output = FieldElement;
+    fn div(&self, other: &FieldElement) -> Result<FieldElement, Error> {
+        self.same_field(other)?;
+        self.mul(&other.inv()?)
+    }
 
-    fn sub(self, other: FieldElement) -> FieldElement {
-        assert_eq!(self.field, other.field);
-        let mut result = self.value - other.value;
-        if result < BigUint::zero() {
-            result += &self.field.prime;
+    fn pow(&self, exp: &BigUint) -> Self {
+        FieldElement {
+            value: self.value.modpow(exp, &self.field.prime),
+            field: self.field.clone(),
         }
-        FieldElement::new(result, self.field)
     }
-}
 
-// Implement Mul for FieldElement
-impl Mul for FieldElement {
-    type Output = FieldElement;
+    // Fermat: a^(p - 1) = 1 for a != 0, so a^(p - 2) is the inverse. 0 has none.
+    fn inv(&self) -> Result<FieldElement, Error> {
+        if self.value.is_zero() {
+            return Err(Error::NotInvertible);
+        }
+        let exp = &self.field.prime - BigUint::from(2u32);
+        Ok(self.pow(&exp))
+    }
 
-    fn mul(self, other: FieldElement) -> FieldElement {
-        assert_eq!(self.field, other.field);
-        FieldElement::new(self.value * other.value, self.field)
+    fn neg(&self) -> Self {
+        FieldElement::new(&self.field.prime - &self.value, self.field.clone())
     }
 }
 
-// Implement Div for FieldElement
-impl Div for FieldElement {
-    type Output = FieldElement;
+impl EllipticCurve {
+    // y^2 = x^3 + ax + b, refusing a and b from different fields and 4a^3 + 27b^2 = 0
+    fn try_new(a: FieldElement, b: FieldElement) -> Result<Self, Error> {
+        let field = a.same_field(&b)?.clone();
+        let four = FieldElement::new(BigUint::from(4u32), field.clone());
+        let twenty_seven = FieldElement::new(BigUint::from(27u32), field);
+        let sum = four
+            .mul(&a.pow(&BigUint::from(3u32)))?
+            .add(&twenty_seven.mul(&b.pow(&BigUint::from(2u32)))?)?;
+        if sum.value.is_zero() {
+            return Err(Error::SingularCurve);
+        }
+        Ok(EllipticCurve { a, b })
+    }
 
-    fn div(self, other: FieldElement) -> FieldElement {
-        assert_eq!(self.field, other.field);
-        let inv = other.inv();
-        self * inv
+    // x^3 + ax + b
+    fn rhs(&self, x: &FieldElement) -> Result<FieldElement, Error> {
+        x.pow(&BigUint::from(3u32))
+            .add(&self.a.mul(x)?)?
+            .add(&self.b)
     }
-}
 
-impl EllipticCurve {
-    fn new(a: FieldElement, b: FieldElement) -> Self {
-        assert_eq!(a.field, b.field);
-        EllipticCurve { a, b }
+    // The affine point (x, y), if it satisfies the curve equation
+    fn point(&self, x: FieldElement, y: FieldElement) -> Result<Point, Error> {
+        let p = Point::new(x, y);
+        if !self.contains(&p)? {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
     }
 
-    fn contains(&self, point: &Point) -> bool {
-        if let (Some(x), Some(y)) = (point.x.clone(), point.y.clone()) {
-            let lhs = y.clone() * y;
-            let rhs = ((x.clone() * x.clone() * x.clone()) + (self.a.clone() * x)) + self.b.clone();
-            lhs == rhs
-        } else {
-            true // Point at infinity is always on the curve
+    fn contains(&self, point: &Point) -> Result<bool, Error> {
+        match point.coordinates() {
+            Some((x, y)) => Ok(y.mul(y)? == self.rhs(x)?),
+            None => Ok(true), // Point at infinity is always on the curve
         }
     }
 
-    fn random_point(&self) -> Point {
+    fn random_point(&self) -> Result<Point, Error> {
         loop {
             let x = self.a.field.random_element();
-            let y_squared = ((x.clone() * x.clone() * x.clone()) + (self.a.clone() * x.clone()))
-                + self.b.clone();
-            if let Some(y) = self.sqrt(&y_squared) {
-                return Point {
-                    x: Some(x),
-                    y: Some(y),
-                    curve: self.clone(),
-                };
+            if let Some(y) = self.sqrt(&self.rhs(&x)?) {
+                return self.point(x, y);
             }
         }
     }
@@ -159,61 +154,50 @@ impl EllipticCurve {
         // For a general solution, implement Tonelli-Shanks algorithm
         let exp = (&a.field.prime + BigUint::one()) / BigUint::from(4u32);
         let root = a.pow(&exp);
-        if &(root.clone() * root.clone()) == a {
+        if &root.pow(&BigUint::from(2u32)) == a {
             Some(root)
         } else {
             None
         }
     }
-}
-
-impl Add for &Point {
-    type Output = Point;
-
-    fn add(self, other: &Point) -> Point {
-        assert_eq!(self.curve, other.curve);
 
-        if self.x.is_none() {
-            return other.clone();
-        }
-        if other.x.is_none() {
-            return self.clone();
+    fn negate(&self, point: &Point) -> Point {
+        match point {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x.clone(), y.neg()),
         }
+    }
 
-        let (x1, y1) = (self.x.as_ref().unwrap(), self.y.as_ref().unwrap());
-        let (x2, y2) = (other.x.as_ref().unwrap(), other.y.as_ref().unwrap());
+    fn add(&self, p: &Point, q: &Point) -> Result<Point, Error> {
+        let ((x1, y1), (x2, y2)) = match (p.coordinates(), q.coordinates()) {
+            (None, _) => return Ok(q.clone()),
+            (_, None) => return Ok(p.clone()),
+            (Some(p), Some(q)) => (p, q),
+        };
 
-        if x1 == x2 && y1 != y2 {
-            return Point {
-                x: None,
-                y: None,
-                curve: self.curve.clone(),
-            }; // Point at infinity
+        // P + (-P), which includes doubling a point of order 2
+        if x1 == x2 && *y2 == y1.neg() {
+            return Ok(Point::Infinity);
         }
 
         let m = if x1 == x2 {
             // Point doubling
-            let numerator = ((x1.clone()
-                * x1.clone()
-                * FieldElement::new(BigUint::from(3u32), x1.field.clone()))
-                + self.curve.a.clone());
-            let denominator = y1.clone() * FieldElement::new(BigUint::from(2u32), y1.field.clone());
-            numerator / denominator
+            let three = FieldElement::new(BigUint::from(3u32), x1.field.clone());
+            let two = FieldElement::new(BigUint::from(2u32), y1.field.clone());
+            let numerator = x1.mul(x1)?.mul(&three)?.add(&self.a)?;
+            let denominator = y1.mul(&two)?;
+            numerator.div(&denominator)?
         } else {
             // Point addition
-            let numerator = y2.clone() - y1.clone();
-            let denominator = x2.clone() - x1.clone();
-            numerator / denominator
+            let numerator = y2.sub(y1)?;
+            let denominator = x2.sub(x1)?;
+            numerator.div(&denominator)?
         };
 
-        let x3 = (m.clone() * m.clone()) - x1.clone() - x2.clone();
-        let y3 = (m.clone() * (x1.clone() - x3.clone())) - y1.clone();
+        let x3 = m.mul(&m)?.sub(x1)?.sub(x2)?;
+        let y3 = m.mul(&x1.sub(&x3)?)?.sub(y1)?;
 
-        Point {
-            x: Some(x3),
-            y: Some(y3),
-            curve: self.curve.clone(),
-        }
+        Ok(Point::new(x3, y3))
     }
 }
 
@@ -264,14 +248,14 @@ impl Polynomial {
         Polynomial { coefficients }
     }
 
-    fn evaluate(&self, x: &FieldElement) -> FieldElement {
+    fn evaluate(&self, x: &FieldElement) -> Result<FieldElement, Error> {
         let mut result = self.coefficients[0].clone();
         let mut power = x.clone();
         for coeff in self.coefficients.iter().skip(1) {
-            result = result + (power.clone() * coeff.clone());
-            power = power.clone() * x.clone();
+            result = result.add(&power.mul(coeff)?)?;
+            power = power.mul(x)?;
         }
-        result
+        Ok(result)
     }
 }
 
@@ -291,8 +275,8 @@ impl FunctionFieldElement {
     }
 }
 
-// langrange polynomial
-fn interpolate(x_coords: &[FieldElement], y_coords: &[FieldElement]) -> Polynomial {
+// langrange polynomial, NotInvertible if two of the x coordinates coincide
+fn interpolate(x_coords: &[FieldElement], y_coords: &[FieldElement]) -> Result<Polynomial, Error> {
     assert_eq!(x_coords.len(), y_coords.len());
     let n = x_coords.len();
     let mut result = Polynomial::new(vec![
@@ -311,101 +295,78 @@ fn interpolate(x_coords: &[FieldElement], y_coords: &[FieldElement]) -> Polynomi
                     FieldElement::new(BigUint::zero(), x_coords[0].field.clone()),
                     FieldElement::new(BigUint::one(), x_coords[0].field.clone()),
                 ]);
-                let denominator = x_coords[i].clone() - x_coords[j].clone();
-                term = multiply_polynomials(&term, &numerator);
-                term = scalar_multiply_polynomial(&term, &denominator.inv());
+                let denominator = x_coords[i].sub(&x_coords[j])?;
+                term = multiply_polynomials(&term, &numerator)?;
+                term = scalar_multiply_polynomial(&term, &denominator.inv()?)?;
             }
         }
-        result = add_polynomials(&result, &term);
+        result = add_polynomials(&result, &term)?;
     }
 
-    result
+    Ok(result)
 }
 
-fn add_polynomials(p1: &Polynomial, p2: &Polynomial) -> Polynomial {
+fn add_polynomials(p1: &Polynomial, p2: &Polynomial) -> Result<Polynomial, Error> {
     let max_len = std::cmp::max(p1.coefficients.len(), p2.coefficients.len());
     let mut result =
         vec![FieldElement::new(BigUint::zero(), p1.coefficients[0].field.clone()); max_len];
 
     for (i, coeff) in result.iter_mut().enumerate() {
         if i < p1.coefficients.len() {
-            *coeff = coeff.clone() + p1.coefficients[i].clone();
+            *coeff = coeff.add(&p1.coefficients[i])?;
         }
         if i < p2.coefficients.len() {
-            *coeff = coeff.clone() + p2.coefficients[i].clone();
+            *coeff = coeff.add(&p2.coefficients[i])?;
         }
     }
 
-    Polynomial::new(result)
+    Ok(Polynomial::new(result))
 }
 
-fn multiply_polynomials(p1: &Polynomial, p2: &Polynomial) -> Polynomial {
+fn multiply_polynomials(p1: &Polynomial, p2: &Polynomial) -> Result<Polynomial, Error> {
     let len = p1.coefficients.len() + p2.coefficients.len() - 1;
     let mut result =
         vec![FieldElement::new(BigUint::zero(), p1.coefficients[0].field.clone()); len];
 
     for (i, c1) in p1.coefficients.iter().enumerate() {
         for (j, c2) in p2.coefficients.iter().enumerate() {
-            result[i + j] = result[i + j].clone() + (c1.clone() * c2.clone());
+            result[i + j] = result[i + j].add(&c1.mul(c2)?)?;
         }
     }
 
-    Polynomial::new(result)
+    Ok(Polynomial::new(result))
 }
 
-fn scalar_multiply_polynomial(p: &Polynomial, scalar: &FieldElement) -> Polynomial {
-    Polynomial::new(
-        p.coefficients
-            .iter()
-            .map(|c| c.clone() * scalar.clone())
-            .collect(),
-    )
+fn scalar_multiply_polynomial(p: &Polynomial, scalar: &FieldElement) -> Result<Polynomial, Error> {
+    let coefficients = p
+        .coefficients
+        .iter()
+        .map(|c| c.mul(scalar))
+        .collect::<Result<_, _>>()?;
+    Ok(Polynomial::new(coefficients))
 }
-fn divisor_of_function(f: &FunctionFieldElement) -> Divisor {
+fn divisor_of_function(f: &FunctionFieldElement) -> Result<Divisor, Error> {
+    let curve = &f.field.curve;
     let mut divisor = Divisor::new();
     // Add zeros of the numerator
-    for x in 0..f.field.curve.a.field.prime.clone().to_u32().unwrap() {
-        let x_fe = FieldElement::new(BigUint::from(x), f.field.curve.a.field.clone());
-        if f.numerator.evaluate(&x_fe).value == BigUint::zero() {
-            let y = f.field.curve.sqrt(
-                &((x_fe.clone() * x_fe.clone() * x_fe.clone())
-                    + (f.field.curve.a.clone() * x_fe.clone())
-                    + f.field.curve.b.clone()),
-            );
-            if let Some(y) = y {
-                divisor.add_point(
-                    Point {
-                        x: Some(x_fe.clone()),
-                        y: Some(y),
-                        curve: f.field.curve.clone(),
-                    },
-                    1,
-                );
+    for x in 0..curve.a.field.prime.to_u32().unwrap() {
+        let x_fe = FieldElement::new(BigUint::from(x), curve.a.field.clone());
+        if f.numerator.evaluate(&x_fe)?.value.is_zero() {
+            if let Some(y) = curve.sqrt(&curve.rhs(&x_fe)?) {
+                divisor.add_point(Point::new(x_fe.clone(), y), 1);
             }
         }
     }
     // Subtract poles of the denominator
-    for x in 0..f.field.curve.a.field.prime.clone().to_u32().unwrap() {
-        let x_fe = FieldElement::new(BigUint::from(x), f.field.curve.a.field.clone());
-        if f.denominator.evaluate(&x_fe).value == BigUint::zero() {
-            let y = f.field.curve.sqrt(
-                &((x_fe.clone() * x_fe.clone() * x_fe.clone())
-                    + (f.field.curve.a.clone() * x_fe.clone())
-                    + f.field.curve.b.clone()),
-            );
-            if let Some(y) = y {
-                divisor.add_point(
-                    Point {
-                        x: Some(x_fe.clone()),
-                        y: Some(y),
-                        curve: f.field.curve.clone(),
-                    },
-                    -1,
-                );
+    for x in 0..curve.a.field.prime.to_u32().unwrap() {
+        let x_fe = FieldElement::new(BigUint::from(x), curve.a.field.clone());
+        if f.denominator.evaluate(&x_fe)?.value.is_zero() {
+            if let Some(y) = curve.sqrt(&curve.rhs(&x_fe)?) {
+                divisor.add_point(Point::new(x_fe.clone(), y), -1);
             }
         }
     }
-    divisor
+    Ok(divisor)
 }
 
 fn support(divisor: &Divisor) -> Vec<Point> {
@@ -415,36 +376,90 @@ fn support(divisor: &Divisor) -> Vec<Point> {
         .map(|(point, _)| point.clone())
         .collect()
 }
-pub fn run() {
-    let mut rng = rand::thread_rng();
-    let q: u32 = rng.gen_range(2..50);
-    let q = BigUint::from(q);
 
-    let fq = FiniteField::new(q.clone());
+// The group law, the checked constructors and the field arithmetic errors on
+// y^2 = x^3 + 2x + 9 over F43
+fn check_curve() {
+    let fq = FiniteField::new(BigUint::from(43u32));
+    let fe = |n: u32| FieldElement::new(BigUint::from(n), fq.clone());
+    let other = FieldElement::new(BigUint::one(), FiniteField::new(BigUint::from(47u32)));
+
+    assert_eq!(fe(0).inv(), Err(Error::NotInvertible));
+    assert_eq!(fe(1).div(&fe(0)), Err(Error::NotInvertible));
+    assert_eq!(fe(6).div(&fe(2)), Ok(fe(3)));
+    assert_eq!(fe(1).add(&other), Err(Error::MismatchedFields));
+    assert_eq!(fe(1).sub(&other), Err(Error::MismatchedFields));
+    assert_eq!(fe(1).mul(&other), Err(Error::MismatchedFields));
+    assert_eq!(fe(1).div(&other), Err(Error::MismatchedFields));
+
+    // 4a^3 + 27b^2 = 0 for a = b = 0, and for a = -3, b = 2 where x^3 - 3x + 2 = (x - 1)^2 (x + 2)
+    assert_eq!(
+        EllipticCurve::try_new(fe(0), fe(0)),
+        Err(Error::SingularCurve)
+    );
+    assert_eq!(
+        EllipticCurve::try_new(fe(40), fe(2)),
+        Err(Error::SingularCurve)
+    );
+    assert_eq!(
+        EllipticCurve::try_new(fe(2), other),
+        Err(Error::MismatchedFields)
+    );
+    let e = EllipticCurve::try_new(fe(2), fe(9)).unwrap();
+
+    // 3^2 = 9 puts (0, 3) on the curve, and (0, 4) is not
+    assert_eq!(e.point(fe(0), fe(4)), Err(Error::NotOnCurve));
+    let p = e.point(fe(0), fe(3)).unwrap();
+    let minus_p = e.negate(&p);
+    assert_eq!(minus_p, Point::new(fe(0), fe(40)));
+    assert_eq!(e.add(&p, &minus_p), Ok(Point::Infinity));
+    assert_eq!(e.add(&p, &Point::Infinity), Ok(p.clone()));
+    assert_eq!(e.negate(&Point::Infinity), Point::Infinity);
+
+    // The tangent at (0, 3) has slope 2 / 6 = 29, so 2P = (29^2, 29 (0 - 24) - 3) = (24, 32)
+    let p2 = e.add(&p, &p).unwrap();
+    assert_eq!(p2, Point::new(fe(24), fe(32)));
+    let p3 = e.add(&p2, &p).unwrap();
+    assert_eq!(e.contains(&p3), Ok(true));
+    assert_eq!(Ok(p3.clone()), e.add(&p, &p2));
+    assert_eq!(e.add(&e.add(&p2, &p2).unwrap(), &minus_p), Ok(p3));
+}
 
-    let a = fq.random_element();
-    let b = fq.random_element();
+pub fn run() {
+    check_curve();
 
-    let e = EllipticCurve::new(a, b);
+    // q = 3 mod 4, which sqrt() needs
+    let q = BigUint::from(43u32);
+    let fq = FiniteField::new(q.clone());
 
-    let o = Point {
-        x: None,
-        y: None,
-        curve: e.clone(),
+    let e = loop {
+        if let Ok(e) = EllipticCurve::try_new(fq.random_element(), fq.random_element()) {
+            break e;
+        }
     };
 
+    let o = Point::Infinity;
+
     let mut points = Vec::new();
     let mut xcoords = Vec::new();
     let mut ycoords = Vec::new();
 
     let mut d = Divisor::new();
 
-    for _ in 0..11 {
-        let pi = e.random_point();
-        if let (Some(x), Some(y)) = (&pi.x, &pi.y) {
-            xcoords.push(x.clone());
-            ycoords.push(y.clone());
+    // 11 points with distinct x, so that they can be interpolated
+    while points.len() < 11 {
+        let pi = e.random_point().unwrap();
+        let Some((x, y)) = pi.coordinates() else {
+            continue;
+        };
+        if xcoords.contains(x) {
+            continue;
         }
+        xcoords.push(x.clone());
+        ycoords.push(y.clone());
+        assert_eq!(e.contains(&pi), Ok(true));
+        assert_eq!(e.add(&pi, &e.negate(&pi)), Ok(o.clone()));
+        assert_eq!(e.add(&pi, &o), Ok(pi.clone()));
         points.push(pi.clone());
         d.add_point(pi, 1);
         d.add_point(o.clone(), -1);
@@ -455,7 +470,7 @@ pub fn run() {
         points.len()
     );
 
-    let interpolated = interpolate(&xcoords, &ycoords);
+    let interpolated = interpolate(&xcoords, &ycoords).unwrap();
     let f = FunctionField::new(e.clone());
     let l = FunctionFieldElement::new(
         add_polynomials(
@@ -464,12 +479,13 @@ pub fn run() {
                 FieldElement::new(BigUint::from(0u32), fq.clone()),
             ]),
             &interpolated,
-        ),
+        )
+        .unwrap(),
         Polynomial::new(vec![FieldElement::new(BigUint::one(), fq.clone())]),
         f,
     );
 
-    let div_l = divisor_of_function(&l);
+    let div_l = divisor_of_function(&l).unwrap();
     let support_l = support(&div_l);
     println!("Support of Divisor(l): {:?}", support_l);
 
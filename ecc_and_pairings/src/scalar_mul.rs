@@ -0,0 +1,260 @@
+use crate::error::Error;
+use crate::weierstrass::{F2m, Field, Fp, Point, WeierstrassCurve};
+use num_bigint::{BigInt, RandBigInt, Sign};
+use num_traits::{One, Signed, Zero};
+use rand::Rng;
+use std::time::Instant;
+
+// Scalar multiplication with fewer additions than double-and-add
+//
+// Double-and-add on an n-bit k does n doublings and about n / 2 additions. Negation is
+// free on an elliptic curve, so digits may be negative: the width-w NAF has digits in
+// (-2^(w-1), 2^(w-1)), all odd, and at most one nonzero in any w consecutive ones, which
+// cuts the additions to about n / (w + 1) at the cost of precomputing P, 3P, ..., (2^(w-1) - 1)P.
+// A fixed window instead adds once every w bits from a table of [0]P .. [2^w - 1]P; its
+// operation sequence does not depend on k, only the table lookups do.
+
+// Both methods precompute a table of 2^(w - 2) or 2^w points, so w is capped well before
+// the table outgrows any scalar worth multiplying by
+const MAX_WIDTH: u32 = 16;
+
+// The width-w NAF of k, least significant digit first, for 2 <= w <= MAX_WIDTH. w = 2 is
+// the ordinary NAF, and negative k gets the negated digits of |k|.
+pub fn wnaf(k: &BigInt, w: u32) -> Result<Vec<i64>, Error> {
+    if !(2..=MAX_WIDTH).contains(&w) {
+        return Err(Error::InvalidWindow);
+    }
+    if k.is_negative() {
+        return Ok(wnaf(&k.abs(), w)?.into_iter().map(|d| -d).collect());
+    }
+    let modulus = 1i64 << w;
+    let mut k = k.clone();
+    let mut digits = Vec::new();
+    while !k.is_zero() {
+        let digit = if k.bit(0) {
+            // k mods 2^w: the residue of k in (-2^(w-1), 2^(w-1))
+            let residue = (0..w as u64).rev().fold(0, |r, i| r << 1 | k.bit(i) as i64);
+            let digit = if residue >= modulus / 2 {
+                residue - modulus
+            } else {
+                residue
+            };
+            k -= digit;
+            digit
+        } else {
+            0
+        };
+        digits.push(digit);
+        k >>= 1;
+    }
+    Ok(digits)
+}
+
+impl<F: Field> WeierstrassCurve<F> {
+    // [k]P = [-k](-P), so both methods only ever see |k|
+    fn with_sign(&self, k: &BigInt, p: &Point<F>) -> (BigInt, Point<F>) {
+        if k.is_negative() {
+            (k.abs(), self.negate(p))
+        } else {
+            (k.clone(), *p)
+        }
+    }
+
+    pub fn scalar_mul_wnaf(&self, k: &BigInt, p: &Point<F>, w: u32) -> Result<Point<F>, Error> {
+        let (k, p) = self.with_sign(k, p);
+        let digits = wnaf(&k, w)?;
+        // table[i] = [2i + 1]P
+        let double = self.add(&p, &p)?;
+        let mut table = vec![p];
+        for i in 1..1 << (w - 2) {
+            table.push(self.add(&table[i - 1], &double)?);
+        }
+
+        let mut result = Point::Infinity;
+        for &digit in digits.iter().rev() {
+            result = self.add(&result, &result)?;
+            let multiple = table[digit.unsigned_abs() as usize / 2];
+            if digit > 0 {
+                result = self.add(&result, &multiple)?;
+            } else if digit < 0 {
+                result = self.add(&result, &self.negate(&multiple))?;
+            }
+        }
+        Ok(result)
+    }
+
+    // 1 <= w <= MAX_WIDTH
+    pub fn scalar_mul_window(&self, k: &BigInt, p: &Point<F>, w: u32) -> Result<Point<F>, Error> {
+        if !(1..=MAX_WIDTH).contains(&w) {
+            return Err(Error::InvalidWindow);
+        }
+        let (k, p) = self.with_sign(k, p);
+        // table[i] = [i]P
+        let mut table = vec![Point::Infinity, p];
+        for i in 2..1 << w {
+            table.push(self.add(&table[i - 1], &p)?);
+        }
+
+        let w = w as u64;
+        let mut result = Point::Infinity;
+        for window in (0..k.bits().div_ceil(w)).rev() {
+            for _ in 0..w {
+                result = self.add(&result, &result)?;
+            }
+            let digit = (0..w)
+                .rev()
+                .fold(0, |d, i| d << 1 | k.bit(window * w + i) as usize);
+            result = self.add(&result, &table[digit])?;
+        }
+        Ok(result)
+    }
+}
+
+// Both methods against double-and-add for every point and small k of either sign
+fn check_small_scalars<F: Field>(curve: &WeierstrassCurve<F>) {
+    for p in curve.points() {
+        for k in -40i64..40 {
+            let expected = curve
+                .scalar_mul(k.unsigned_abs(), &if k < 0 { curve.negate(&p) } else { p })
+                .unwrap();
+            let k = BigInt::from(k);
+            for w in 2..6 {
+                assert_eq!(curve.scalar_mul_wnaf(&k, &p, w), Ok(expected));
+            }
+            for w in 1..5 {
+                assert_eq!(curve.scalar_mul_window(&k, &p, w), Ok(expected));
+            }
+        }
+    }
+}
+
+// Average time per call in microseconds
+fn time<T>(scalars: &[T], mut f: impl FnMut(&T)) -> f64 {
+    let start = Instant::now();
+    for k in scalars {
+        f(k);
+    }
+    start.elapsed().as_secs_f64() * 1e6 / scalars.len() as f64
+}
+
+pub fn run() {
+    // The NAF of 7 = 2^3 - 1, and the digits add back up to k with the promised gaps
+    assert_eq!(wnaf(&BigInt::from(7), 2), Ok(vec![-1, 0, 0, 1]));
+    assert_eq!(wnaf(&BigInt::from(-7), 2), Ok(vec![1, 0, 0, -1]));
+    assert_eq!(wnaf(&BigInt::from(7), 1), Err(Error::InvalidWindow));
+    assert_eq!(
+        wnaf(&BigInt::from(7), MAX_WIDTH + 1),
+        Err(Error::InvalidWindow)
+    );
+    let mut rng = rand::thread_rng();
+    for _ in 0..50 {
+        let k = rng.gen_biguint(256).into();
+        for w in 2..7 {
+            let digits = wnaf(&k, w).unwrap();
+            let value = digits
+                .iter()
+                .rev()
+                .fold(BigInt::zero(), |acc, &d| (acc << 1) + d);
+            assert_eq!(value, k);
+            for (i, &d) in digits.iter().enumerate() {
+                assert!(d == 0 || (d % 2 != 0 && d.abs() < 1 << (w - 1)));
+                assert!(d == 0 || digits[i + 1..].iter().take(w as usize - 1).all(|&e| e == 0));
+            }
+        }
+    }
+
+    // Small curves in characteristic 13 and 2, exhaustively
+    check_small_scalars(&WeierstrassCurve::from_coefficients(
+        Fp::new(0, 13),
+        [0, -1, 1, -10, -20],
+    ));
+    check_small_scalars(&WeierstrassCurve::from_coefficients(
+        F2m::new(0, 0b10011),
+        [1, 0, 0, 0, 1],
+    ));
+    println!("wNAF and fixed-window multiplication agree with double-and-add on 11a1/F13 and y^2 + xy = x^3 + 1 over F16");
+
+    // y^2 = x^3 + 3x + 7 over F(2^61 - 1)
+    let p = (1 << 61) - 1;
+    let field = Fp::new(0, p);
+    let curve = WeierstrassCurve::try_from_coefficients(field, [0, 0, 0, 3, 7]).unwrap();
+    let base = (1..)
+        .find_map(|x| {
+            let x = field.embed(x);
+            let y = (x * x * x + field.embed(3) * x + field.embed(7)).sqrt()?;
+            curve.point(x, y).ok()
+        })
+        .unwrap();
+
+    // 256-bit scalars: k = k1 2^64 + k0, so [k]P = [k1]([2^32]([2^32]P)) + [k0]P
+    let two_32 = 1 << 32;
+    let p_64 = curve
+        .scalar_mul(two_32, &curve.scalar_mul(two_32, &base).unwrap())
+        .unwrap();
+    for _ in 0..10 {
+        let k: BigInt = rng.gen_biguint(256).into();
+        let k1: BigInt = &k >> 64;
+        let k0: BigInt = &k - (&k1 << 64);
+        let expected = curve
+            .add(
+                &curve.scalar_mul_window(&k1, &p_64, 4).unwrap(),
+                &curve.scalar_mul(k0.try_into().unwrap(), &base).unwrap(),
+            )
+            .unwrap();
+        for w in 2..7 {
+            assert_eq!(curve.scalar_mul_wnaf(&k, &base, w), Ok(expected));
+            assert_eq!(curve.scalar_mul_window(&k, &base, w - 1), Ok(expected));
+        }
+        let negative = BigInt::from_biguint(Sign::Minus, k.magnitude().clone());
+        assert_eq!(
+            curve.scalar_mul_wnaf(&negative, &base, 4),
+            Ok(curve.negate(&expected))
+        );
+    }
+    assert_eq!(curve.scalar_mul_wnaf(&BigInt::one(), &base, 5), Ok(base));
+
+    // Widths without a table to match are refused rather than underflowing or allocating
+    let k = BigInt::from(1000);
+    for w in [0, 1, MAX_WIDTH + 1, u32::MAX] {
+        assert_eq!(
+            curve.scalar_mul_wnaf(&k, &base, w),
+            Err(Error::InvalidWindow)
+        );
+    }
+    for w in [0, MAX_WIDTH + 1, u32::MAX] {
+        assert_eq!(
+            curve.scalar_mul_window(&k, &base, w),
+            Err(Error::InvalidWindow)
+        );
+    }
+
+    // The benchmark: the same 64-bit scalars through each method. Every affine doubling and
+    // addition pays for a Fermat inversion, so saving additions gains at most 1.5n / 1.2n
+    // for w = 4, and a larger table costs more than it saves on scalars this short.
+    let scalars: Vec<u64> = (0..200).map(|_| rng.gen()).collect();
+    let big_scalars: Vec<BigInt> = scalars.iter().map(|&k| BigInt::from(k)).collect();
+    let double_and_add = time(&scalars, |&k| {
+        curve.scalar_mul(k, &base).unwrap();
+    });
+    println!(
+        "64-bit scalars over F(2^61 - 1): double-and-add {:.1} us",
+        double_and_add
+    );
+    for w in [2, 4, 5] {
+        let t = time(&big_scalars, |k| {
+            curve.scalar_mul_wnaf(k, &base, w).unwrap();
+        });
+        println!("  w = {} NAF {:.1} us ({:.2}x)", w, t, double_and_add / t);
+    }
+    for w in [2, 4] {
+        let t = time(&big_scalars, |k| {
+            curve.scalar_mul_window(k, &base, w).unwrap();
+        });
+        println!(
+            "  {}-bit fixed window {:.1} us ({:.2}x)",
+            w,
+            t,
+            double_and_add / t
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::Error;
+use crate::point::Point;
+use num_bigint::{BigInt, Sign};
+use num_traits::{Euclid, One, Zero};
+
+// SEC 1 v2, section 2.3: octet strings for elements of Fp and for points on curves over Fp
+//
+//   O                      00
+//   (x, y)                 04 || X || Y
+//   (x, y) with y even     02 || X
+//   (x, y) with y odd      03 || X
+//
+// X and Y are big-endian and left-padded to the byte length of p, so that every encoding of
+// a point on a given curve has one of three lengths.
+
+// ceil(ceil(log2 p) / 8)
+pub fn field_len(p: &BigInt) -> usize {
+    ((p - 1u32).bits() as usize).div_ceil(8)
+}
+
+// a is reduced mod p first, so a negative or oversized value encodes as its residue
+pub fn encode_field_element(a: &BigInt, p: &BigInt) -> Vec<u8> {
+    let (_, bytes) = a.rem_euclid(p).to_bytes_be();
+    let mut out = vec![0u8; field_len(p)];
+    let start = out.len() - bytes.len();
+    out[start..].copy_from_slice(&bytes);
+    out
+}
+
+// Rejects anything but field_len(p) bytes, and values >= p
+pub fn decode_field_element(bytes: &[u8], p: &BigInt) -> Result<BigInt, Error> {
+    if bytes.len() != field_len(p) {
+        return Err(Error::InvalidEncoding);
+    }
+    let a = BigInt::from_bytes_be(Sign::Plus, bytes);
+    if &a >= p {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(a)
+}
+
+// Tonelli-Shanks, for any odd prime p rather than just p = 3 mod 4. Returns one of the two
+// roots, or None when a is not a square.
+pub fn sqrt_mod(a: &BigInt, p: &BigInt) -> Option<BigInt> {
+    let a = a.rem_euclid(p);
+    if a.is_zero() || p == &BigInt::from(2) {
+        return Some(a);
+    }
+    let one = BigInt::one();
+    let p_minus_one = p - 1u32;
+    let half = &p_minus_one >> 1;
+    // Euler's criterion
+    if a.modpow(&half, p) != one {
+        return None;
+    }
+
+    // p - 1 = q 2^s with q odd, and z any non-square
+    let s = p_minus_one.trailing_zeros()?;
+    let q = &p_minus_one >> s;
+    let mut z = BigInt::from(2);
+    while z.modpow(&half, p) != p_minus_one {
+        z += 1u32;
+    }
+
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = a.modpow(&q, p);
+    let mut r = a.modpow(&((&q + 1u32) >> 1), p);
+    // r^2 = a t, and t has order dividing 2^(m - 1)
+    while t != one {
+        let mut i = 0;
+        let mut t2i = t.clone();
+        while t2i != one {
+            t2i = &t2i * &t2i % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigInt::one() << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        r = r * &b % p;
+    }
+    Some(r)
+}
+
+pub fn encode_point(point: &Point<BigInt>, p: &BigInt, compressed: bool) -> Vec<u8> {
+    let Some((x, y)) = point.coordinates() else {
+        return vec![0];
+    };
+    if compressed {
+        let prefix = if y.rem_euclid(p).bit(0) { 3 } else { 2 };
+        [vec![prefix], encode_field_element(x, p)].concat()
+    } else {
+        [
+            vec![4],
+            encode_field_element(x, p),
+            encode_field_element(y, p),
+        ]
+        .concat()
+    }
+}
+
+// Parses any of the three forms. A compressed point is recovered as the root of
+// y^2 = rhs(x) with the right parity, so it is on the curve by construction; the caller still
+// has to check an uncompressed one.
+pub fn decode_point(
+    bytes: &[u8],
+    p: &BigInt,
+    rhs: impl Fn(&BigInt) -> BigInt,
+) -> Result<Point<BigInt>, Error> {
+    let len = field_len(p);
+    let Some((&prefix, rest)) = bytes.split_first() else {
+        return Err(Error::InvalidEncoding);
+    };
+    match prefix {
+        0 if rest.is_empty() => Ok(Point::Infinity),
+        4 if rest.len() == 2 * len => {
+            let (x, y) = rest.split_at(len);
+            Ok(Point::new(
+                decode_field_element(x, p)?,
+                decode_field_element(y, p)?,
+            ))
+        }
+        2 | 3 if rest.len() == len => {
+            let x = decode_field_element(rest, p)?;
+            let y = sqrt_mod(&rhs(&x), p).ok_or(Error::NotOnCurve)?;
+            let odd = prefix == 3;
+            if y.bit(0) == odd {
+                Ok(Point::new(x, y))
+            } else if y.is_zero() {
+                // (x, 0) has order 2 and only the even encoding
+                Err(Error::InvalidEncoding)
+            } else {
+                Ok(Point::new(x, p - y))
+            }
+        }
+        _ => Err(Error::InvalidEncoding),
+    }
+}
@@ -1,5 +1,7 @@
+use crate::error::Error;
+use crate::flower::{self, Petal};
 use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::ops::{Add, Div, Mul, Sub};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -55,7 +57,7 @@ impl Fq {
         result
     }
 
-    fn inv(&self, q: u64) -> Option<Fq> {
+    fn inv(&self, q: u64) -> Result<Fq, Error> {
         let mut t = 0i64;
         let mut newt = 1i64;
         let mut r = q as i64;
@@ -68,12 +70,12 @@ impl Fq {
         }
 
         if r > 1 {
-            return None;
+            return Err(Error::NotInvertible);
         }
         if t < 0 {
             t += q as i64;
         }
-        Some(Fq(t as u64))
+        Ok(Fq(t as u64))
     }
 }
 
@@ -90,8 +92,18 @@ impl Fq6 {
         Fq6 { coeffs }
     }
 
-    fn new(coeffs: Vec<Fq>) -> Self {
-        assert_eq!(coeffs.len(), 6);
+    // Anything but six coefficients does not describe an element of Fq6
+    fn new(coeffs: Vec<Fq>) -> Result<Self, Error> {
+        if coeffs.len() != 6 {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(Fq6 { coeffs })
+    }
+
+    // The image of v under Fq -> Fq6
+    fn from_base(v: u64) -> Self {
+        let mut coeffs = vec![Fq(0); 6];
+        coeffs[0] = Fq(v);
         Fq6 { coeffs }
     }
 
@@ -100,7 +112,7 @@ impl Fq6 {
         for i in 0..6 {
             result[i] = self.coeffs[i].add(&other.coeffs[i], q);
         }
-        Fq6::new(result)
+        Fq6 { coeffs: result }
     }
 
     fn sub(&self, other: &Fq6, q: u64) -> Fq6 {
@@ -108,7 +120,7 @@ impl Fq6 {
         for i in 0..6 {
             result[i] = self.coeffs[i].sub(&other.coeffs[i], q);
         }
-        Fq6::new(result)
+        Fq6 { coeffs: result }
     }
 
     fn mul(&self, other: &Fq6, q: u64) -> Fq6 {
@@ -131,7 +143,9 @@ impl Fq6 {
             final_result[power] = final_result[power].sub(&coeff.mul(&Fq(2), q), q);
         }
 
-        Fq6::new(final_result)
+        Fq6 {
+            coeffs: final_result,
+        }
     }
 
     fn pow(&self, mut exp: u64, q: u64) -> Fq6 {
@@ -147,6 +161,34 @@ impl Fq6 {
         }
         result
     }
+
+    // a^(-1) = a^(q + q^2 + ... + q^5) / N(a), where the norm N(a) = a^(1 + q + ... + q^5)
+    // lies in Fq, so five Frobenius maps and one inversion in Fq replace an exponentiation
+    // by q^6 - 2. The norm of 0 is 0, which has no inverse.
+    fn inv(&self, q: u64) -> Result<Fq6, Error> {
+        let mut conjugate = Fq6::one(q);
+        let mut frobenius = self.clone();
+        for _ in 1..6 {
+            frobenius = frobenius.frobenius(q);
+            conjugate = conjugate.mul(&frobenius, q);
+        }
+        let norm = self.mul(&conjugate, q).coeffs[0];
+        let norm_inv = norm.inv(q)?;
+        Ok(conjugate.mul(&Fq6::from_base(norm_inv.0), q))
+    }
+
+    // The q-power Frobenius on Fq6 = Fq(u) with u^6 = -2 maps u^i to (u^q)^i,
+    // where u^q = u * (u^6)^((q - 1) / 6) = u * (-2)^((q - 1) / 6)
+    fn frobenius(&self, q: u64) -> Fq6 {
+        let gamma = Fq(q - 2).pow((q - 1) / 6, q);
+        let mut gamma_i = Fq(1);
+        let mut result = vec![Fq(0); 6];
+        for (i, coeff) in self.coeffs.iter().enumerate() {
+            result[i] = coeff.mul(&gamma_i, q);
+            gamma_i = gamma_i.mul(&gamma, q);
+        }
+        Fq6 { coeffs: result }
+    }
 }
 
 impl Point {
@@ -188,9 +230,9 @@ impl EllipticCurve {
             }
         }
 
-        let slope =
-            p2.y.sub(&p1.y, self.q)
-                .mul(&p2.x.sub(&p1.x, self.q).pow(self.q - 2, self.q), self.q);
+        // x1 != x2, so the difference is a nonzero element of the field Fq6
+        let dx_inv = p2.x.sub(&p1.x, self.q).inv(self.q).expect("x1 != x2");
+        let slope = p2.y.sub(&p1.y, self.q).mul(&dx_inv, self.q);
 
         let x3 = slope
             .mul(&slope, self.q)
@@ -211,29 +253,20 @@ impl EllipticCurve {
             return Point::identity(self.q);
         }
 
+        // y != 0 and q is odd, so 2y is invertible
+        let two_y_inv =
+            p.y.mul(&Fq6::from_base(2), self.q)
+                .inv(self.q)
+                .expect("y != 0");
         let slope =
             p.x.mul(&p.x, self.q)
-                .mul(
-                    &Fq6::new(vec![Fq(3), Fq(0), Fq(0), Fq(0), Fq(0), Fq(0)]),
-                    self.q,
-                )
+                .mul(&Fq6::from_base(3), self.q)
                 .add(&self.a, self.q)
-                .mul(
-                    &p.y.mul(
-                        &Fq6::new(vec![Fq(2), Fq(0), Fq(0), Fq(0), Fq(0), Fq(0)]),
-                        self.q,
-                    )
-                    .pow(self.q - 2, self.q),
-                    self.q,
-                );
-
-        let x3 = slope.mul(&slope, self.q).sub(
-            &p.x.mul(
-                &Fq6::new(vec![Fq(2), Fq(0), Fq(0), Fq(0), Fq(0), Fq(0)]),
-                self.q,
-            ),
-            self.q,
-        );
+                .mul(&two_y_inv, self.q);
+
+        let x3 = slope
+            .mul(&slope, self.q)
+            .sub(&p.x.mul(&Fq6::from_base(2), self.q), self.q);
 
         let y3 = slope.mul(&p.x.sub(&x3, self.q), self.q).sub(&p.y, self.q);
 
@@ -256,7 +289,115 @@ impl EllipticCurve {
         result
     }
 
-    // If E is an elliptic curve y^2 = x^3 + ax + b, then the twisted curve E' has equation y^2 = x^3 + a(u^4)x + b(u^6)
+    // The q-power Frobenius endomorphism pi(x, y) = (x^q, y^q)
+    fn frobenius(&self, p: &Point) -> Point {
+        if p.z == Fq6::zero(self.q) {
+            return p.clone();
+        }
+        Point::new(p.x.frobenius(self.q), p.y.frobenius(self.q))
+    }
+
+    fn base_field_points(&self) -> Vec<Point> {
+        let mut points = vec![Point::identity(self.q)];
+
+        for x in 0..self.q {
+            let x = Fq6::from_base(x);
+            let rhs = x
+                .mul(&x, self.q)
+                .mul(&x, self.q)
+                .add(&self.a.mul(&x, self.q), self.q)
+                .add(&self.b, self.q);
+            for y in 0..self.q {
+                let y = Fq6::from_base(y);
+                if y.mul(&y, self.q) == rhs {
+                    points.push(Point::new(x.clone(), y));
+                }
+            }
+        }
+
+        points
+    }
+
+    // Evaluates at s the line through t and p (the tangent when t == p) divided by the
+    // vertical line through t + p, and returns t + p alongside it. NotInvertible when s lies
+    // on that vertical, where the quotient has a pole.
+    fn line_function(&self, t: &Point, p: &Point, s: &Point) -> Result<(Point, Fq6), Error> {
+        let sum = self.add(t, p);
+
+        // t = -p: the line is the vertical x - x_t and there is no vertical through O
+        if sum.z == Fq6::zero(self.q) {
+            return Ok((sum, s.x.sub(&t.x, self.q)));
+        }
+
+        let slope = if t == p {
+            let three = Fq6::from_base(3);
+            let two = Fq6::from_base(2);
+            t.x.mul(&t.x, self.q)
+                .mul(&three, self.q)
+                .add(&self.a, self.q)
+                .mul(&t.y.mul(&two, self.q).inv(self.q)?, self.q)
+        } else {
+            p.y.sub(&t.y, self.q)
+                .mul(&p.x.sub(&t.x, self.q).inv(self.q)?, self.q)
+        };
+
+        // l(s) = y_s - y_t - slope * (x_s - x_t), v(s) = x_s - x_(t+p)
+        let line =
+            s.y.sub(&t.y, self.q)
+                .sub(&slope.mul(&s.x.sub(&t.x, self.q), self.q), self.q);
+        let vertical = s.x.sub(&sum.x, self.q);
+
+        Ok((sum, line.mul(&vertical.inv(self.q)?, self.q)))
+    }
+
+    // Miller's algorithm: evaluates at s the normalised function f_{r,p} with divisor
+    // r(p) - r(O)
+    fn miller(&self, p: &Point, s: &Point, r: u64) -> Result<Fq6, Error> {
+        let mut t = p.clone();
+        let mut f = Fq6::one(self.q);
+
+        for i in (0..63 - r.leading_zeros()).rev() {
+            let (doubled, l) = self.line_function(&t, &t, s)?;
+            f = f.mul(&f, self.q).mul(&l, self.q);
+            t = doubled;
+
+            if (r >> i) & 1 == 1 {
+                let (added, l) = self.line_function(&t, p, s)?;
+                f = f.mul(&l, self.q);
+                t = added;
+            }
+        }
+
+        Ok(f)
+    }
+
+    // Final exponentiation for k = 6: (q^6 - 1) / r = (q^3 - 1)(q + 1) * (q^2 - q + 1) / r.
+    // The easy part (q^3 - 1)(q + 1) only needs Frobenius maps and an inversion, leaving
+    // the hard part (q^2 - q + 1) / r = Phi_6(q) / r as a plain exponentiation.
+    fn final_exponentiation(&self, f: &Fq6, r: u64) -> Result<Fq6, Error> {
+        let q = self.q;
+        let f_q3 = f.frobenius(q).frobenius(q).frobenius(q);
+        let easy = f_q3.mul(&f.inv(q)?, q);
+        let easy = easy.frobenius(q).mul(&easy, q);
+
+        Ok(easy.pow((q * q - q + 1) / r, q))
+    }
+
+    // Reduced Tate pairing t_r(p, s) = f_{r,p}(s)^((q^6 - 1) / r) for p in E[r] and
+    // s in E(Fq6). If s lies in <p> the pairing is trivial and Miller's algorithm
+    // would hit a zero of its line functions, so 1 is returned directly. Any other zero or
+    // pole of a line function at s comes back as NotInvertible.
+    fn tate_pairing(&self, p: &Point, s: &Point, r: u64) -> Result<Fq6, Error> {
+        if (0..r).any(|i| self.scalar_mul(i, p) == *s) {
+            return Ok(Fq6::one(self.q));
+        }
+
+        self.final_exponentiation(&self.miller(p, s, r)?, r)
+    }
+
+    // Maps E: y^2 = x^3 + b to its sextic twist E': y^2 = x^3 + b u^6, (x, y) -> (x u^2, y u^3).
+    // Since u^6 = -2 lies in Fq, E' is defined over Fq and this is an isomorphism over Fq6.
+    // The twists module handles the other degrees.
     fn twist(&self, p: &Point, u: &Fq6) -> Point {
         let u2 = u.mul(u, self.q); // u^2
         let u3 = u2.mul(u, self.q); // u^3
@@ -267,139 +408,176 @@ impl EllipticCurve {
         ) // y' = y * u^3
     }
 
-    fn untwist(&self, p: &Point, u: &Fq6) -> Point {
+    fn untwist(&self, p: &Point, u: &Fq6) -> Result<Point, Error> {
         // We use u^(-2) and u^(-3) in untwisting because these are the inverses of u^2 and u^3
         let u2 = u.mul(u, self.q); // u^2
         let u3 = u2.mul(u, self.q); // u^3
-        let u2_inv = u2.pow(self.q - 2, self.q); // (u^2)^(-1)
-        let u3_inv = u3.pow(self.q - 2, self.q); // (u^3)^(-1)
-        Point::new(
+        let u2_inv = u2.inv(self.q)?; // (u^2)^(-1)
+        let u3_inv = u3.inv(self.q)?; // (u^3)^(-1)
+        Ok(Point::new(
             p.x.mul(&u2_inv, self.q), // x = x' * (u^2)^(-1)
             p.y.mul(&u3_inv, self.q),
-        ) // y = y' * (u^3)^(-1)
+        )) // y = y' * (u^3)^(-1)
     }
 }
-fn flower_generator(tors_pts: HashSet<Point>, curve: &EllipticCurve) -> Vec<HashSet<Point>> {
-    let mut petals = Vec::new();
-    let mut remaining_points = tors_pts.clone();
-    let petals_count = (tors_pts.len() as f64).sqrt() as usize + 1;
-    let points_per_petal = petals_count - 1;
+impl flower::TorsionCurve for EllipticCurve {
+    type Point = Point;
 
-    let mut rng = rand::thread_rng();
+    fn q(&self) -> u64 {
+        self.q
+    }
 
-    while petals.len() < petals_count && !remaining_points.is_empty() {
-        // Find a random non-zero point
-        let random_point = remaining_points
-            .iter()
-            .filter(|p| p.z != Fq6::zero(curve.q))
-            .nth(rng.gen_range(0..remaining_points.len()))
-            .unwrap()
-            .clone();
-
-        let mut petal = HashSet::new();
-
-        // Generate points for this petal
-        for j in 1..=points_per_petal {
-            let point = curve.scalar_mul(j as u64, &random_point);
-            petal.insert(point.clone());
-            remaining_points.remove(&point);
+    fn is_on_curve(&self, p: &Point) -> bool {
+        if self.is_identity(p) {
+            return true;
         }
+        let q = self.q;
+        let rhs =
+            p.x.mul(&p.x, q)
+                .mul(&p.x, q)
+                .add(&self.a.mul(&p.x, q), q)
+                .add(&self.b, q);
+        p.y.mul(&p.y, q) == rhs
+    }
 
-        petals.push(petal);
+    fn is_identity(&self, p: &Point) -> bool {
+        p.z == Fq6::zero(self.q)
     }
 
-    petals
+    fn add(&self, p1: &Point, p2: &Point) -> Point {
+        EllipticCurve::add(self, p1, p2)
+    }
+
+    fn scalar_mul(&self, k: u64, p: &Point) -> Point {
+        EllipticCurve::scalar_mul(self, k, p)
+    }
+
+    fn frobenius(&self, p: &Point) -> Point {
+        EllipticCurve::frobenius(self, p)
+    }
+}
+
+// Generator of G1 = E(Fq)[r]: clear the cofactor of the first base field point that
+// survives it
+fn g1_generator(curve: &EllipticCurve, r: u64) -> Point {
+    let points = curve.base_field_points();
+    let h = points.len() as u64 / r;
+    points
+        .iter()
+        .map(|p| curve.scalar_mul(h, p))
+        .find(|p| p.z != Fq6::zero(curve.q))
+        .unwrap()
+}
+
+// Generator of G2: the untwist of an r-torsion point on the sextic twist E'(Fq). It
+// is in E[r] but not in E(Fq), so pi acts on it as [q].
+fn g2_generator(curve_twist: &EllipticCurve, u: &Fq6, r: u64) -> Result<Point, Error> {
+    curve_twist.untwist(&g1_generator(curve_twist, r), u)
+}
+
+// Splits E[r] into its r + 1 cyclic subgroups of order r from the generators of G1
+// and G2, which span E[r]
+fn torsion_flower(
+    curve: &EllipticCurve,
+    curve_twist: &EllipticCurve,
+    u: &Fq6,
+    r: u64,
+) -> Vec<Petal<Point>> {
+    let g1 = g1_generator(curve, r);
+    let g2 = g2_generator(curve_twist, u, r).unwrap();
+    flower::torsion_flower(curve, &g1, &g2, r)
+}
+
+// Pairs P in G1 = E(Fq)[r] with Q in G2, taken as the untwist of an r-torsion point on
+// the sextic twist E'(Fq), and checks the reduced Tate pairing
+fn check_tate_pairing(curve: &EllipticCurve, curve_twist: &EllipticCurve, u: &Fq6, r: u64) {
+    let q = curve.q;
+
+    let p = g1_generator(curve, r);
+    let s = g2_generator(curve_twist, u, r).unwrap();
+
+    let t = curve.tate_pairing(&p, &s, r).unwrap();
+    println!(
+        "#E(Fq) = {}, #E'(Fq) = {}",
+        curve.base_field_points().len(),
+        curve_twist.base_field_points().len()
+    );
+    println!("P = {:?}", p);
+    println!("Q = {:?}", s);
+    println!("t_{}(P, Q) = {:?}", r, t);
+
+    // The easy/hard split agrees with a single exponentiation by (q^6 - 1) / r
+    assert_eq!(
+        t,
+        curve.miller(&p, &s, r).unwrap().pow((q.pow(6) - 1) / r, q)
+    );
+    assert_eq!(t.pow(r, q), Fq6::one(q));
+    assert_ne!(t, Fq6::one(q));
+
+    // Bilinearity, and P pairs trivially with G1 so shifting Q by P leaves t_r unchanged
+    for (a, b) in [(2, 3), (3, 5), (6, 4)] {
+        let t_ab = curve.tate_pairing(&curve.scalar_mul(a, &p), &curve.scalar_mul(b, &s), r);
+        assert_eq!(t_ab, Ok(t.pow(a * b, q)));
+    }
+    assert_eq!(curve.tate_pairing(&p, &curve.add(&s, &p), r), Ok(t));
+    // Miller's first step doubles P, and s = -2P lies on the vertical through 2P
+    let minus_2p = curve.scalar_mul(r - 2, &p);
+    assert_eq!(curve.miller(&p, &minus_2p, r), Err(Error::NotInvertible));
+    println!(
+        "Reduced Tate pairing is bilinear and non-degenerate on E[{}]",
+        r
+    );
 }
+
 pub fn run() {
     let q: u64 = 103;
     let r: u64 = 7;
 
     // Create base field Fq
     let a = Fq6::zero(q);
-    let b = Fq6::new(vec![Fq(72), Fq(0), Fq(0), Fq(0), Fq(0), Fq(0)]);
+    let b = Fq6::from_base(72);
 
     let curve = EllipticCurve::new(a.clone(), b.clone(), q);
 
     // Create extension field Fq6 and u
-    let u = Fq6::new(vec![Fq(0), Fq(1), Fq(0), Fq(0), Fq(0), Fq(0)]); // Representing x
+    let u = Fq6::new(vec![Fq(0), Fq(1), Fq(0), Fq(0), Fq(0), Fq(0)]).unwrap(); // Representing x
+
+    // Three coefficients are not an element of Fq6
+    assert_eq!(
+        Fq6::new(vec![Fq(0), Fq(1), Fq(0)]),
+        Err(Error::InvalidEncoding)
+    );
+
+    // a * a^(-1) = 1 through the norm, for u and a dense element
+    let dense = Fq6::new((1..7).map(|c| Fq(c * 17 % q)).collect()).unwrap();
+    for a in [&u, &dense] {
+        assert_eq!(a.mul(&a.inv(q).unwrap(), q), Fq6::one(q));
+    }
+    assert_eq!(Fq6::zero(q).inv(q), Err(Error::NotInvertible));
 
     // Create twisted curve
     let b_twist = b.mul(&u.pow(6, q), q);
     let curve_twist = EllipticCurve::new(a.clone(), b_twist, q);
 
-    // Generate torsion points
-    let mut tors_pts = HashSet::new();
-    let mut tors_pts_twist = HashSet::new();
+    check_tate_pairing(&curve, &curve_twist, &u, r);
 
-    let h = 1; // This should be properly calculated
-    let mut rng = rand::thread_rng();
-    while tors_pts.len() < (r * r) as usize {
-        // Generate a random point by randomizing x and y
-        let x_rand = Fq6::new(
-            (0..6)
-                .map(|_| Fq(rng.gen_range(0..q))) // Random Fq6 element for x
-                .collect(),
-        );
-        let y_rand = Fq6::new(
-            (0..6)
-                .map(|_| Fq(rng.gen_range(0..q))) // Random Fq6 element for y
-                .collect(),
-        );
-
-        let p = Point::new(x_rand, y_rand);
-
-        // Multiply by cofactor (h)
-        let hp = curve.scalar_mul(h, &p);
-        if !tors_pts.contains(&hp) {
-            for i in 1..=6 {
-                tors_pts.insert(curve.scalar_mul(i as u64, &hp));
-            }
-        }
+    let flower = torsion_flower(&curve, &curve_twist, &u, r);
+    flower::check_torsion_flower(&curve, &flower, r);
+    for petal in &flower {
+        println!("{:?} = <{:?}>", petal.label, petal.generator);
     }
-
-    // Similar for twist points
-    while tors_pts_twist.len() < (r * r) as usize {
-        let x_rand = Fq6::new(
-            (0..6)
-                .map(|_| Fq(rng.gen_range(0..q))) // Random Fq6 element for x
-                .collect(),
-        );
-        let y_rand = Fq6::new(
-            (0..6)
-                .map(|_| Fq(rng.gen_range(0..q))) // Random Fq6 element for y
-                .collect(),
-        );
-
-        let p = Point::new(x_rand, y_rand);
-
-        // Multiply by cofactor (h)
-        let hp = curve_twist.scalar_mul(h, &p);
-        if !tors_pts_twist.contains(&hp) {
-            for i in 1..=6 {
-                tors_pts_twist.insert(curve_twist.scalar_mul(i as u64, &hp));
-            }
-        }
-    }
-
-    // Generate flowers
-    let flower = flower_generator(tors_pts.clone(), &curve);
-    let flower_twist = flower_generator(tors_pts_twist.clone(), &curve_twist);
-
     println!("Flower petals: {}", flower.len());
-    println!("Twisted flower petals: {}", flower_twist.len());
 
     // Test twisting and untwisting
     let mut rng = rand::thread_rng();
-    let random_point = tors_pts
-        .iter()
-        .nth(rng.gen_range(0..tors_pts.len()))
-        .unwrap();
+    let petal = &flower[rng.gen_range(0..flower.len())];
+    let random_point = curve.scalar_mul(rng.gen_range(1..r), &petal.generator);
+    let random_point = &random_point;
 
     println!("Original point: {:?}", random_point);
     let twisted = curve.twist(random_point, &u);
     println!("Twisted point: {:?}", twisted);
-    let untwisted = curve_twist.untwist(&twisted, &u);
+    let untwisted = curve_twist.untwist(&twisted, &u).unwrap();
     println!("Untwisted point: {:?}", untwisted);
-    // TODO: fix
-    // assert_eq!(*random_point, untwisted);
+    assert_eq!(*random_point, untwisted);
 }
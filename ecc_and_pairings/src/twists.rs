@@ -0,0 +1,453 @@
+use crate::error::Error;
+
+// Twists of E: y^2 = x^3 + ax + b over Fq. A twist of degree d is a curve E' over Fq
+// which only becomes isomorphic to E over Fq^d:
+//
+//   E': y^2 = x^3 + a w^4 x + b w^6,   psi: E' -> E, (x, y) -> (x / w^2, y / w^3)
+//
+// We take Fq^d = Fq[u]/(u^d - xi) and w = u. Then a w^4 and b w^6 lie in Fq for d = 2 on
+// every curve, for d = 3 and d = 6 when a = 0 (j = 0) and for d = 4 when b = 0 (j = 1728).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fq(u64);
+
+// An element of Fq[u]/(u^k - xi) given by its k coefficients
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FqK {
+    coeffs: Vec<Fq>,
+}
+
+#[derive(Debug)]
+struct ExtensionField {
+    q: u64,
+    k: usize,
+    xi: Fq,
+}
+
+// Affine points over Fq^k
+type Point = crate::point::Point<FqK>;
+
+#[derive(Debug, Clone)]
+struct EllipticCurve {
+    a: Fq,
+    b: Fq,
+    q: u64,
+}
+
+#[derive(Debug)]
+struct Twist {
+    degree: usize,
+    field: ExtensionField,
+    curve: EllipticCurve, // E' over Fq
+    omega: FqK,           // w = u with w^d = xi
+}
+
+impl Fq {
+    fn add(&self, other: &Fq, q: u64) -> Fq {
+        Fq((self.0 + other.0) % q)
+    }
+
+    fn sub(&self, other: &Fq, q: u64) -> Fq {
+        Fq((self.0 + q - other.0) % q)
+    }
+
+    fn mul(&self, other: &Fq, q: u64) -> Fq {
+        Fq((self.0 * other.0) % q)
+    }
+
+    fn pow(&self, mut exp: u64, q: u64) -> Fq {
+        let mut base = *self;
+        let mut result = Fq(1);
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base, q);
+            }
+            base = base.mul(&base, q);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+impl ExtensionField {
+    // u^k - xi is irreducible over Fq when every prime l dividing k also divides q - 1 and
+    // xi is not an l-th power, and when 4 | k implies q = 1 mod 4. We only build Fq^k with
+    // k | q - 1, which covers both conditions, and take the smallest such xi.
+    fn new(q: u64, k: usize) -> Option<Self> {
+        if !(q - 1).is_multiple_of(k as u64) {
+            return None;
+        }
+
+        let primes: Vec<u64> = (2..=k as u64)
+            .filter(|l| (k as u64).is_multiple_of(*l) && (2..*l).all(|m| l % m != 0))
+            .collect();
+        let xi = (2..q)
+            .map(Fq)
+            .find(|xi| primes.iter().all(|l| xi.pow((q - 1) / l, q) != Fq(1)))?;
+
+        Some(ExtensionField { q, k, xi })
+    }
+
+    fn embed(&self, value: Fq) -> FqK {
+        let mut coeffs = vec![Fq(0); self.k];
+        coeffs[0] = value;
+        FqK { coeffs }
+    }
+
+    fn zero(&self) -> FqK {
+        self.embed(Fq(0))
+    }
+
+    fn one(&self) -> FqK {
+        self.embed(Fq(1))
+    }
+
+    // The generator u with u^k = xi
+    fn u(&self) -> FqK {
+        let mut u = self.zero();
+        u.coeffs[1] = Fq(1);
+        u
+    }
+
+    fn is_base(&self, a: &FqK) -> bool {
+        a.coeffs[1..].iter().all(|c| *c == Fq(0))
+    }
+
+    fn add(&self, a: &FqK, b: &FqK) -> FqK {
+        let coeffs = (0..self.k)
+            .map(|i| a.coeffs[i].add(&b.coeffs[i], self.q))
+            .collect();
+        FqK { coeffs }
+    }
+
+    fn sub(&self, a: &FqK, b: &FqK) -> FqK {
+        let coeffs = (0..self.k)
+            .map(|i| a.coeffs[i].sub(&b.coeffs[i], self.q))
+            .collect();
+        FqK { coeffs }
+    }
+
+    fn mul(&self, a: &FqK, b: &FqK) -> FqK {
+        let q = self.q;
+        let mut coeffs = vec![Fq(0); self.k];
+        for i in 0..self.k {
+            for j in 0..self.k {
+                let mut prod = a.coeffs[i].mul(&b.coeffs[j], q);
+                // Reduce with u^k = xi
+                if i + j >= self.k {
+                    prod = prod.mul(&self.xi, q);
+                }
+                let idx = (i + j) % self.k;
+                coeffs[idx] = coeffs[idx].add(&prod, q);
+            }
+        }
+        FqK { coeffs }
+    }
+
+    fn pow(&self, a: &FqK, mut exp: u64) -> FqK {
+        let mut base = a.clone();
+        let mut result = self.one();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(&result, &base);
+            }
+            base = self.mul(&base, &base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    // The q-power Frobenius. k | q - 1 gives u^q = u xi^((q - 1) / k), so the coefficient
+    // of u^i picks up gamma^i for gamma = xi^((q - 1) / k).
+    fn frobenius(&self, a: &FqK) -> FqK {
+        let gamma = self.xi.pow((self.q - 1) / self.k as u64, self.q);
+        let mut gamma_i = Fq(1);
+        let mut coeffs = Vec::with_capacity(self.k);
+        for c in &a.coeffs {
+            coeffs.push(c.mul(&gamma_i, self.q));
+            gamma_i = gamma_i.mul(&gamma, self.q);
+        }
+        FqK { coeffs }
+    }
+
+    // a^(-1) = a^(q + q^2 + ... + q^(k-1)) / N(a), where the norm N(a) = a^(1 + q + ... + q^(k-1))
+    // lies in Fq. Exponentiating by q^k - 2 instead overflows u64 for q^k > 2^64.
+    fn inv(&self, a: &FqK) -> Result<FqK, Error> {
+        let mut conjugate = self.one();
+        let mut frobenius = a.clone();
+        for _ in 1..self.k {
+            frobenius = self.frobenius(&frobenius);
+            conjugate = self.mul(&conjugate, &frobenius);
+        }
+        let norm = self.mul(a, &conjugate).coeffs[0];
+        if norm == Fq(0) {
+            return Err(Error::NotInvertible);
+        }
+        let norm_inv = norm.pow(self.q - 2, self.q);
+        Ok(self.mul(&conjugate, &self.embed(norm_inv)))
+    }
+}
+
+impl EllipticCurve {
+    fn new(a: u64, b: u64, q: u64) -> Self {
+        EllipticCurve {
+            a: Fq(a % q),
+            b: Fq(b % q),
+            q,
+        }
+    }
+
+    fn rhs(&self, field: &ExtensionField, x: &FqK) -> FqK {
+        let x3 = field.mul(&field.mul(x, x), x);
+        let ax = field.mul(&field.embed(self.a), x);
+        field.add(&field.add(&x3, &ax), &field.embed(self.b))
+    }
+
+    fn is_on_curve(&self, field: &ExtensionField, p: &Point) -> bool {
+        match p {
+            Point::Infinity => true,
+            Point::Affine { x, y } => field.mul(y, y) == self.rhs(field, x),
+        }
+    }
+
+    // Affine group law on E(Fq^k)
+    fn add(&self, field: &ExtensionField, p1: &Point, p2: &Point) -> Point {
+        let ((x1, y1), (x2, y2)) = match (p1.coordinates(), p2.coordinates()) {
+            (None, _) => return p2.clone(),
+            (_, None) => return p1.clone(),
+            (Some(p1), Some(p2)) => (p1, p2),
+        };
+
+        let slope = if x1 == x2 {
+            if field.add(y1, y2) == field.zero() {
+                return Point::Infinity;
+            }
+            // y1 = y2 != 0 here and q is odd, so 2y is invertible
+            let three_x2 = field.mul(&field.embed(Fq(3)), &field.mul(x1, x1));
+            let two_y = field.add(y1, y1);
+            field.mul(
+                &field.add(&three_x2, &field.embed(self.a)),
+                &field.inv(&two_y).expect("y != 0"),
+            )
+        } else {
+            let dx_inv = field.inv(&field.sub(x2, x1)).expect("x1 != x2");
+            field.mul(&field.sub(y2, y1), &dx_inv)
+        };
+
+        let x3 = field.sub(&field.sub(&field.mul(&slope, &slope), x1), x2);
+        let y3 = field.sub(&field.mul(&slope, &field.sub(x1, &x3)), y1);
+        Point::new(x3, y3)
+    }
+
+    // The points of E(Fq), embedded in E(Fq^k)
+    fn base_field_points(&self, field: &ExtensionField) -> Vec<Point> {
+        let mut points = vec![Point::Infinity];
+        for x in 0..self.q {
+            let x = field.embed(Fq(x));
+            let rhs = self.rhs(field, &x);
+            for y in 0..self.q {
+                let y = field.embed(Fq(y));
+                if field.mul(&y, &y) == rhs {
+                    points.push(Point::new(x.clone(), y));
+                }
+            }
+        }
+        points
+    }
+
+    // The twist of degree d, if E has one of the form above. E' is defined over Fq
+    // exactly when a w^4 and b w^6 land in the base field.
+    fn twist(&self, d: usize) -> Option<Twist> {
+        if ![2, 3, 4, 6].contains(&d) {
+            return None;
+        }
+        let field = ExtensionField::new(self.q, d)?;
+        let omega = field.u();
+
+        let a_twist = field.mul(&field.embed(self.a), &field.pow(&omega, 4));
+        let b_twist = field.mul(&field.embed(self.b), &field.pow(&omega, 6));
+        if !field.is_base(&a_twist) || !field.is_base(&b_twist) {
+            return None;
+        }
+
+        let curve = EllipticCurve {
+            a: a_twist.coeffs[0],
+            b: b_twist.coeffs[0],
+            q: self.q,
+        };
+        Some(Twist {
+            degree: d,
+            field,
+            curve,
+            omega,
+        })
+    }
+}
+
+impl Twist {
+    // psi: E' -> E, (x, y) -> (x / w^2, y / w^3), defined over Fq^d
+    fn psi(&self, p: &Point) -> Result<Point, Error> {
+        let field = &self.field;
+        let Some((x, y)) = p.coordinates() else {
+            return Ok(Point::Infinity);
+        };
+        let omega2 = field.mul(&self.omega, &self.omega);
+        let omega3 = field.mul(&omega2, &self.omega);
+        Ok(Point::new(
+            field.mul(x, &field.inv(&omega2)?),
+            field.mul(y, &field.inv(&omega3)?),
+        ))
+    }
+
+    // psi^-1: E -> E', (x, y) -> (x w^2, y w^3)
+    fn psi_inv(&self, p: &Point) -> Point {
+        let field = &self.field;
+        let Some((x, y)) = p.coordinates() else {
+            return Point::Infinity;
+        };
+        let omega2 = field.mul(&self.omega, &self.omega);
+        let omega3 = field.mul(&omega2, &self.omega);
+        Point::new(field.mul(x, &omega2), field.mul(y, &omega3))
+    }
+}
+
+// psi maps the given points of E'(Fq) into E(Fq^d), psi^-1 undoes it, and psi is a group
+// homomorphism on every pair from a fixed sample of them
+fn check_psi(curve: &EllipticCurve, twist: &Twist, points: &[Point]) {
+    let field = &twist.field;
+    for p in points {
+        let image = twist.psi(p).unwrap();
+        assert!(curve.is_on_curve(field, &image));
+        assert_eq!(twist.psi_inv(&image), *p);
+    }
+    for p1 in points.iter().step_by(3) {
+        for p2 in points.iter().step_by(5) {
+            assert_eq!(
+                twist.psi(&twist.curve.add(field, p1, p2)).unwrap(),
+                curve.add(field, &twist.psi(p1).unwrap(), &twist.psi(p2).unwrap())
+            );
+        }
+    }
+}
+
+// Round-trip and homomorphism checks for the twist of degree d of y^2 = x^3 + ax + b
+fn check_twist(curve: &EllipticCurve, d: usize) {
+    let twist = curve.twist(d).unwrap();
+    let field = &twist.field;
+    println!(
+        "y^2 = x^3 + {}x + {} over F{}: degree {} twist y^2 = x^3 + {}x + {} with u^{} = {}",
+        curve.a.0,
+        curve.b.0,
+        curve.q,
+        twist.degree,
+        twist.curve.a.0,
+        twist.curve.b.0,
+        d,
+        field.xi.0
+    );
+
+    let points = twist.curve.base_field_points(field);
+    let base_points = curve.base_field_points(field);
+    check_psi(curve, &twist, &points);
+    for p in &base_points {
+        let image = twist.psi_inv(p);
+        assert!(twist.curve.is_on_curve(field, &image));
+        assert_eq!(twist.psi(&image), Ok(p.clone()));
+    }
+
+    // ...and psi is not defined over Fq, so E' is a genuine twist
+    assert!(points
+        .iter()
+        .any(|p| !base_points.contains(&twist.psi(p).unwrap())));
+    println!(
+        "#E(F{}) = {}, #E'(F{}) = {}",
+        curve.q,
+        base_points.len(),
+        curve.q,
+        points.len()
+    );
+}
+
+pub fn run() {
+    // j = 0 has quadratic, cubic and sextic twists when 6 | q - 1
+    let curve = EllipticCurve::new(0, 72, 103);
+    for d in [2, 3, 6] {
+        check_twist(&curve, d);
+    }
+    assert!(curve.twist(4).is_none());
+
+    // j = 1728 has quadratic and quartic twists when 4 | q - 1
+    let curve = EllipticCurve::new(3, 0, 101);
+    for d in [2, 4] {
+        check_twist(&curve, d);
+    }
+    assert!(curve.twist(3).is_none());
+
+    // Any other j only has the quadratic twist
+    let curve = EllipticCurve::new(1, 5, 23);
+    check_twist(&curve, 2);
+    assert!(curve.twist(3).is_none());
+    assert!(curve.twist(6).is_none());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn j0_twists_over_f103() {
+        let curve = EllipticCurve::new(0, 72, 103);
+        for d in [2, 3, 6] {
+            check_twist(&curve, d);
+        }
+        assert!(curve.twist(4).is_none());
+    }
+
+    #[test]
+    fn j1728_twists_over_f101() {
+        let curve = EllipticCurve::new(3, 0, 101);
+        for d in [2, 4] {
+            check_twist(&curve, d);
+        }
+        assert!(curve.twist(3).is_none());
+    }
+
+    #[test]
+    fn quadratic_twist_over_f23() {
+        let curve = EllipticCurve::new(1, 5, 23);
+        check_twist(&curve, 2);
+        assert!(curve.twist(3).is_none());
+        assert!(curve.twist(6).is_none());
+    }
+
+    // q^6 > 2^64 for q = 2011, so the inverse has to go through the norm
+    #[test]
+    fn sextic_twist_over_f2011() {
+        let curve = EllipticCurve::new(0, 7, 2011);
+        let twist = curve.twist(6).unwrap();
+        let field = &twist.field;
+
+        let a = FqK {
+            coeffs: (1..7).map(|c| Fq(c * 331 % 2011)).collect(),
+        };
+        assert_eq!(field.mul(&a, &field.inv(&a).unwrap()), field.one());
+        assert_eq!(field.inv(&field.zero()), Err(Error::NotInvertible));
+
+        // The first few points of E'(F2011) by x
+        let points: Vec<Point> = (0..2011)
+            .flat_map(|x| {
+                let x = field.embed(Fq(x));
+                let rhs = twist.curve.rhs(field, &x);
+                (0..2011)
+                    .map(|y| field.embed(Fq(y)))
+                    .filter(move |y| field.mul(y, y) == rhs)
+                    .map(move |y| Point::new(x.clone(), y))
+            })
+            .take(12)
+            .collect();
+        assert_eq!(points.len(), 12);
+        check_psi(&curve, &twist, &points);
+    }
+}
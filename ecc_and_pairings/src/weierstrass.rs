@@ -0,0 +1,970 @@
+use crate::error::Error;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, BitXor, Mul, Neg, Sub};
+
+// The general Weierstrass equation
+//
+//   E: y^2 + a1 xy + a3 y = x^3 + a2 x^2 + a4 x + a6
+//
+// over any field, including characteristic 2 and 3 where it cannot be brought into the short
+// form y^2 = x^3 + ax + b. Notation follows Silverman, The Arithmetic of Elliptic Curves, III.1.
+
+pub trait Field:
+    Copy
+    + PartialEq
+    + Debug
+    + Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+    // The image of n under Z -> F, using self only to know which field we are in
+    fn embed(&self, n: i64) -> Self;
+    fn inv(&self) -> Result<Self, Error>;
+    // One of the square roots, if any
+    fn sqrt(&self) -> Option<Self>;
+    fn characteristic(&self) -> u64;
+    fn elements(&self) -> Vec<Self>;
+    // Big-endian bytes, padded to the same length for every element of the field as in
+    // SEC 1 2.3.5, and back. decode() rejects other lengths and unreduced values.
+    fn encode(&self) -> Vec<u8>;
+    fn decode(&self, bytes: &[u8]) -> Result<Self, Error>;
+
+    fn zero(&self) -> Self {
+        self.embed(0)
+    }
+
+    fn one(&self) -> Self {
+        self.embed(1)
+    }
+
+    fn pow(&self, mut exp: u64) -> Self {
+        let mut base = *self;
+        let mut result = self.one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+// Fp for a prime p
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fp {
+    pub value: u64,
+    pub p: u64,
+}
+
+// F_2^m = F2[z]/(f(z)) with elements stored as bit vectors of polynomial coefficients
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct F2m {
+    pub bits: u64,
+    pub modulus: u64, // f(z), irreducible of degree m
+    pub m: u32,
+}
+
+impl Fp {
+    pub fn new(n: i64, p: u64) -> Self {
+        Fp::reduce(n as i128, p)
+    }
+
+    // Sums and differences of two elements fit in an i128 for any u64 modulus
+    fn reduce(n: i128, p: u64) -> Self {
+        Fp {
+            value: n.rem_euclid(p as i128) as u64,
+            p,
+        }
+    }
+}
+
+impl Display for Fp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl Add for Fp {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Fp::reduce(self.value as i128 + other.value as i128, self.p)
+    }
+}
+
+impl Sub for Fp {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Fp::reduce(self.value as i128 - other.value as i128, self.p)
+    }
+}
+
+impl Mul for Fp {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Fp {
+            value: (self.value as u128 * other.value as u128 % self.p as u128) as u64,
+            p: self.p,
+        }
+    }
+}
+
+impl Neg for Fp {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Fp::reduce(-(self.value as i128), self.p)
+    }
+}
+
+impl Field for Fp {
+    fn embed(&self, n: i64) -> Self {
+        Fp::new(n, self.p)
+    }
+
+    // a^(p - 2) by Fermat. If that is not an inverse then p was not prime to begin with.
+    fn inv(&self) -> Result<Self, Error> {
+        if self.value == 0 {
+            return Err(Error::NotInvertible);
+        }
+        let inv = self.pow(self.p - 2);
+        if *self * inv != self.one() {
+            return Err(Error::CompositeModulus);
+        }
+        Ok(inv)
+    }
+
+    // Tonelli-Shanks
+    fn sqrt(&self) -> Option<Self> {
+        if self.value == 0 || self.p == 2 {
+            return Some(*self);
+        }
+        let one = self.one();
+        let half = (self.p - 1) / 2;
+        if self.pow(half) != one {
+            return None;
+        }
+        let s = (self.p - 1).trailing_zeros();
+        let q = (self.p - 1) >> s;
+        let z = (2..self.p)
+            .map(|n| self.embed(n as i64))
+            .find(|z| z.pow(half) != one)?;
+
+        let (mut m, mut c, mut t, mut r) = (s, z.pow(q), self.pow(q), self.pow(q.div_ceil(2)));
+        while t != one {
+            let mut i = 0;
+            let mut t2i = t;
+            while t2i != one {
+                t2i = t2i * t2i;
+                i += 1;
+            }
+            let b = c.pow(1 << (m - i - 1));
+            m = i;
+            c = b * b;
+            t = t * c;
+            r = r * b;
+        }
+        Some(r)
+    }
+
+    fn characteristic(&self) -> u64 {
+        self.p
+    }
+
+    fn elements(&self) -> Vec<Self> {
+        (0..self.p).map(|n| Fp::new(n as i64, self.p)).collect()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let len = (64 - (self.p - 1).leading_zeros()).div_ceil(8) as usize;
+        self.value.to_be_bytes()[8 - len..].to_vec()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != self.encode().len() {
+            return Err(Error::InvalidEncoding);
+        }
+        let value = bytes
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as u64);
+        if value >= self.p {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(Fp { value, p: self.p })
+    }
+}
+
+impl F2m {
+    pub fn new(bits: u64, modulus: u64) -> Self {
+        let m = 63 - modulus.leading_zeros();
+        F2m { bits, modulus, m }
+    }
+}
+
+// Shown as a polynomial in z, e.g. z^3 + z + 1
+impl Display for F2m {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let terms: Vec<String> = (0..self.m)
+            .rev()
+            .filter(|i| self.bits >> i & 1 == 1)
+            .map(|i| match i {
+                0 => "1".to_string(),
+                1 => "z".to_string(),
+                _ => format!("z^{}", i),
+            })
+            .collect();
+        if terms.is_empty() {
+            write!(f, "0")
+        } else {
+            write!(f, "{}", terms.join(" + "))
+        }
+    }
+}
+
+impl Add for F2m {
+    type Output = Self;
+    // Coefficient-wise addition mod 2
+    fn add(self, other: Self) -> Self {
+        F2m::new(self.bits.bitxor(other.bits), self.modulus)
+    }
+}
+
+impl Sub for F2m {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        F2m::new(self.bits.bitxor(other.bits), self.modulus)
+    }
+}
+
+impl Mul for F2m {
+    type Output = Self;
+    // Carry-less multiplication, reducing by f(z) whenever the degree reaches m
+    fn mul(self, other: Self) -> Self {
+        let mut a = self.bits;
+        let mut b = other.bits;
+        let mut result = 0;
+        while b > 0 {
+            if b & 1 == 1 {
+                result ^= a;
+            }
+            b >>= 1;
+            a <<= 1;
+            if a >> self.m & 1 == 1 {
+                a ^= self.modulus;
+            }
+        }
+        F2m::new(result, self.modulus)
+    }
+}
+
+impl Neg for F2m {
+    type Output = Self;
+    fn neg(self) -> Self {
+        self
+    }
+}
+
+impl Field for F2m {
+    fn embed(&self, n: i64) -> Self {
+        F2m::new(n.rem_euclid(2) as u64, self.modulus)
+    }
+
+    // The multiplicative group has order 2^m - 1
+    fn inv(&self) -> Result<Self, Error> {
+        if self.bits == 0 {
+            return Err(Error::NotInvertible);
+        }
+        Ok(self.pow((1 << self.m) - 2))
+    }
+
+    // Squaring is an automorphism of order m, so every element has the square root a^(2^(m - 1))
+    fn sqrt(&self) -> Option<Self> {
+        Some(self.pow(1 << (self.m - 1)))
+    }
+
+    fn characteristic(&self) -> u64 {
+        2
+    }
+
+    fn elements(&self) -> Vec<Self> {
+        (0..1 << self.m)
+            .map(|bits| F2m::new(bits, self.modulus))
+            .collect()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let len = self.m.div_ceil(8) as usize;
+        self.bits.to_be_bytes()[8 - len..].to_vec()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != self.encode().len() {
+            return Err(Error::InvalidEncoding);
+        }
+        let bits = bytes.iter().fold(0, |bits, &byte| bits << 8 | byte as u64);
+        if bits >> self.m != 0 {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(F2m::new(bits, self.modulus))
+    }
+}
+
+pub type Point<F> = crate::point::Point<F>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeierstrassCurve<F: Field> {
+    pub a1: F,
+    pub a2: F,
+    pub a3: F,
+    pub a4: F,
+    pub a6: F,
+}
+
+// The substitution x = u^2 x' + r, y = u^3 y' + s u^2 x' + t, taking E to E'
+#[derive(Clone, Copy, Debug)]
+pub struct ChangeOfVariables<F: Field> {
+    pub u: F,
+    pub r: F,
+    pub s: F,
+    pub t: F,
+}
+
+impl<F: Field> WeierstrassCurve<F> {
+    pub fn new(a1: F, a2: F, a3: F, a4: F, a6: F) -> Self {
+        WeierstrassCurve { a1, a2, a3, a4, a6 }
+    }
+
+    // Coefficients given as integers [a1, a2, a3, a4, a6], reduced into the field of `field`
+    pub fn from_coefficients(field: F, a: [i64; 5]) -> Self {
+        let [a1, a2, a3, a4, a6] = a.map(|n| field.embed(n));
+        WeierstrassCurve::new(a1, a2, a3, a4, a6)
+    }
+
+    // new(), refusing a zero discriminant
+    pub fn try_new(a1: F, a2: F, a3: F, a4: F, a6: F) -> Result<Self, Error> {
+        let curve = WeierstrassCurve::new(a1, a2, a3, a4, a6);
+        if curve.discriminant() == curve.c(0) {
+            return Err(Error::SingularCurve);
+        }
+        Ok(curve)
+    }
+
+    pub fn try_from_coefficients(field: F, a: [i64; 5]) -> Result<Self, Error> {
+        let curve = WeierstrassCurve::from_coefficients(field, a);
+        WeierstrassCurve::try_new(curve.a1, curve.a2, curve.a3, curve.a4, curve.a6)
+    }
+
+    fn c(&self, n: i64) -> F {
+        self.a1.embed(n)
+    }
+
+    pub fn b2(&self) -> F {
+        self.a1 * self.a1 + self.c(4) * self.a2
+    }
+
+    pub fn b4(&self) -> F {
+        self.c(2) * self.a4 + self.a1 * self.a3
+    }
+
+    pub fn b6(&self) -> F {
+        self.a3 * self.a3 + self.c(4) * self.a6
+    }
+
+    pub fn b8(&self) -> F {
+        self.a1 * self.a1 * self.a6 + self.c(4) * self.a2 * self.a6 - self.a1 * self.a3 * self.a4
+            + self.a2 * self.a3 * self.a3
+            - self.a4 * self.a4
+    }
+
+    pub fn c4(&self) -> F {
+        self.b2() * self.b2() - self.c(24) * self.b4()
+    }
+
+    pub fn c6(&self) -> F {
+        let (b2, b4, b6) = (self.b2(), self.b4(), self.b6());
+        -b2 * b2 * b2 + self.c(36) * b2 * b4 - self.c(216) * b6
+    }
+
+    pub fn discriminant(&self) -> F {
+        let (b2, b4, b6, b8) = (self.b2(), self.b4(), self.b6(), self.b8());
+        -b2 * b2 * b8 - self.c(8) * b4 * b4 * b4 - self.c(27) * b6 * b6 + self.c(9) * b2 * b4 * b6
+    }
+
+    // j = c4^3 / discriminant, which only exists for a nonsingular curve
+    pub fn j_invariant(&self) -> Result<F, Error> {
+        let discriminant = self.discriminant();
+        if discriminant == self.c(0) {
+            return Err(Error::SingularCurve);
+        }
+        let c4 = self.c4();
+        Ok(c4 * c4 * c4 * discriminant.inv()?)
+    }
+
+    pub fn is_on_curve(&self, p: &Point<F>) -> bool {
+        match *p {
+            Point::Infinity => true,
+            Point::Affine { x, y } => {
+                y * y + self.a1 * x * y + self.a3 * y
+                    == x * x * x + self.a2 * x * x + self.a4 * x + self.a6
+            }
+        }
+    }
+
+    // The affine point (x, y), if it satisfies the curve equation
+    pub fn point(&self, x: F, y: F) -> Result<Point<F>, Error> {
+        let p = Point::new(x, y);
+        if !self.is_on_curve(&p) {
+            return Err(Error::NotOnCurve);
+        }
+        Ok(p)
+    }
+
+    // point(), and also [n]P = O so that P lies in the subgroup of order n
+    pub fn subgroup_point(&self, x: F, y: F, n: u64) -> Result<Point<F>, Error> {
+        let p = self.point(x, y)?;
+        if !self.scalar_mul(n, &p)?.is_infinity() {
+            return Err(Error::NotInSubgroup);
+        }
+        Ok(p)
+    }
+
+    // -(x, y) = (x, -y - a1 x - a3)
+    pub fn negate(&self, p: &Point<F>) -> Point<F> {
+        let Point::Affine { x, y } = *p else {
+            return Point::Infinity;
+        };
+        Point::new(x, -y - self.a1 * x - self.a3)
+    }
+
+    // Silverman III.2.3: the line y = lambda x + nu through P1 and P2 meets E a third time
+    // at -(P1 + P2)
+    pub fn add(&self, p1: &Point<F>, p2: &Point<F>) -> Result<Point<F>, Error> {
+        let ((x1, y1), (x2, y2)) = match (*p1, *p2) {
+            (Point::Infinity, _) => return Ok(*p2),
+            (_, Point::Infinity) => return Ok(*p1),
+            (Point::Affine { x: x1, y: y1 }, Point::Affine { x: x2, y: y2 }) => {
+                ((x1, y1), (x2, y2))
+            }
+        };
+
+        let (lambda, nu) = if x1 != x2 {
+            let inv = (x2 - x1).inv()?;
+            ((y2 - y1) * inv, (y1 * x2 - y2 * x1) * inv)
+        } else {
+            let denominator = y1 + y2 + self.a1 * x2 + self.a3;
+            // Both cover P2 = -P1, including doubling a point of order 2
+            if denominator == self.c(0) {
+                return Ok(Point::Infinity);
+            }
+            let inv = denominator.inv()?;
+            let lambda =
+                (self.c(3) * x1 * x1 + self.c(2) * self.a2 * x1 + self.a4 - self.a1 * y1) * inv;
+            let nu = (-x1 * x1 * x1 + self.a4 * x1 + self.c(2) * self.a6 - self.a3 * y1) * inv;
+            (lambda, nu)
+        };
+
+        let x3 = lambda * lambda + self.a1 * lambda - self.a2 - x1 - x2;
+        let y3 = -(lambda + self.a1) * x3 - nu - self.a3;
+        Ok(Point::new(x3, y3))
+    }
+
+    pub fn scalar_mul(&self, k: u64, p: &Point<F>) -> Result<Point<F>, Error> {
+        let mut result = Point::Infinity;
+        for i in (0..64 - k.leading_zeros()).rev() {
+            result = self.add(&result, &result)?;
+            if k >> i & 1 == 1 {
+                result = self.add(&result, p)?;
+            }
+        }
+        Ok(result)
+    }
+
+    // SEC 1 2.3.3: 00 for O, 04 || X || Y, or 02 / 03 || X with the compression bit below
+    pub fn encode_point(&self, p: &Point<F>, compressed: bool) -> Vec<u8> {
+        let Point::Affine { x, y } = *p else {
+            return vec![0];
+        };
+        if compressed {
+            let prefix = if self.compression_bit(x, y) { 3 } else { 2 };
+            [vec![prefix], x.encode()].concat()
+        } else {
+            [vec![4], x.encode(), y.encode()].concat()
+        }
+    }
+
+    // SEC 1 2.3.4, checking the curve equation for uncompressed input
+    pub fn decode_point(&self, bytes: &[u8]) -> Result<Point<F>, Error> {
+        let len = self.a1.encode().len();
+        let Some((&prefix, rest)) = bytes.split_first() else {
+            return Err(Error::InvalidEncoding);
+        };
+        match prefix {
+            0 if rest.is_empty() => Ok(Point::Infinity),
+            4 if rest.len() == 2 * len => {
+                let (x, y) = rest.split_at(len);
+                self.point(self.a1.decode(x)?, self.a1.decode(y)?)
+            }
+            2 | 3 if rest.len() == len => self.decompress(self.a1.decode(rest)?, prefix == 3),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    // The two points above x have y and -y - h with h = a1 x + a3. In odd characteristic
+    // y + h / 2 is the y of the completed square and the two points have opposite values, so
+    // its low bit tells them apart; for h = 0 that is SEC 1's low bit of y. In characteristic
+    // 2 they are y and y + h, and SEC 1 takes the low bit of y / x, which is y / h on the
+    // curves it covers.
+    fn compression_bit(&self, x: F, y: F) -> bool {
+        let h = self.a1 * x + self.a3;
+        let z = if self.a1.characteristic() == 2 {
+            h.inv().map(|h_inv| y * h_inv)
+        } else {
+            self.c(2).inv().map(|half| y + h * half)
+        };
+        // Only h = 0 in characteristic 2 fails, and then there is a single y
+        z.is_ok_and(|z| z.encode().last().is_some_and(|byte| byte & 1 == 1))
+    }
+
+    // Solves y^2 + hy = f for h = a1 x + a3 and f = x^3 + a2 x^2 + a4 x + a6
+    fn decompress(&self, x: F, bit: bool) -> Result<Point<F>, Error> {
+        let h = self.a1 * x + self.a3;
+        let f = x * x * x + self.a2 * x * x + self.a4 * x + self.a6;
+        let y = if self.a1.characteristic() != 2 {
+            // (2y + h)^2 = h^2 + 4f
+            let root = (h * h + self.c(4) * f).sqrt().ok_or(Error::NotOnCurve)?;
+            (root - h) * self.c(2).inv()?
+        } else if h == self.c(0) {
+            f.sqrt().ok_or(Error::NotOnCurve)?
+        } else {
+            // y = hz with z^2 + z = f / h^2. That is F2-linear in z, and the binary fields
+            // here are small enough to search.
+            let c = f * (h * h).inv()?;
+            let z = c
+                .elements()
+                .into_iter()
+                .find(|&z| z * z + z == c)
+                .ok_or(Error::NotOnCurve)?;
+            h * z
+        };
+        let y = if self.compression_bit(x, y) == bit {
+            y
+        } else {
+            -y - h
+        };
+        // A point of order 2 only has the encoding with bit 0
+        if self.compression_bit(x, y) != bit {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(Point::new(x, y))
+    }
+
+    // E(F) by exhaustive search, with the point at infinity first
+    pub fn points(&self) -> Vec<Point<F>> {
+        let elements = self.a1.elements();
+        let mut points = vec![Point::Infinity];
+        for &x in &elements {
+            for &y in &elements {
+                let p = Point::new(x, y);
+                if self.is_on_curve(&p) {
+                    points.push(p);
+                }
+            }
+        }
+        points
+    }
+
+    // The coefficients of E' with E' = E^(u, r, s, t), Silverman table 3.1
+    pub fn change_variables(&self, change: &ChangeOfVariables<F>) -> Result<Self, Error> {
+        let ChangeOfVariables { u, r, s, t } = *change;
+        let (a1, a2, a3, a4, a6) = (self.a1, self.a2, self.a3, self.a4, self.a6);
+        let u_inv = u.inv()?;
+        Ok(WeierstrassCurve {
+            a1: (a1 + self.c(2) * s) * u_inv,
+            a2: (a2 - s * a1 + self.c(3) * r - s * s) * u_inv.pow(2),
+            a3: (a3 + r * a1 + self.c(2) * t) * u_inv.pow(3),
+            a4: (a4 - s * a3 + self.c(2) * r * a2 - (t + r * s) * a1 + self.c(3) * r * r
+                - self.c(2) * s * t)
+                * u_inv.pow(4),
+            a6: (a6 + r * a4 + r * r * a2 + r * r * r - t * a3 - t * t - r * t * a1) * u_inv.pow(6),
+        })
+    }
+
+    // Completing the square and the cube, x = x' - b2 / 12 and y = y' - (a1 x + a3) / 2,
+    // gives y'^2 = x'^3 - c4 / 48 x' - c6 / 864. This needs 2 and 3 to be invertible.
+    pub fn short_weierstrass(&self) -> Result<(Self, ChangeOfVariables<F>), Error> {
+        if [2, 3].contains(&self.a1.characteristic()) {
+            return Err(Error::NotInvertible);
+        }
+        let half = self.c(2).inv()?;
+        let r = -self.b2() * self.c(12).inv()?;
+        let s = -self.a1 * half;
+        let t = -(self.a3 + r * self.a1) * half;
+        let change = ChangeOfVariables {
+            u: self.c(1),
+            r,
+            s,
+            t,
+        };
+        Ok((self.change_variables(&change)?, change))
+    }
+}
+
+// Lopez-Dahab coordinates (X : Y : Z) for (X / Z, Y / Z^2) on ordinary binary curves
+// y^2 + xy = x^3 + a2 x^2 + a6, with (1 : 0 : 0) the point at infinity
+#[derive(Clone, Copy, Debug)]
+pub struct LopezDahabPoint<F: Field> {
+    pub x: F,
+    pub y: F,
+    pub z: F,
+}
+
+impl<F: Field> WeierstrassCurve<F> {
+    fn is_lopez_dahab_form(&self) -> bool {
+        let zero = self.c(0);
+        self.a1.characteristic() == 2 && self.a1 == self.c(1) && self.a3 == zero && self.a4 == zero
+    }
+
+    pub fn lopez_dahab_point(&self, p: &Point<F>) -> LopezDahabPoint<F> {
+        match *p {
+            Point::Infinity => LopezDahabPoint {
+                x: self.c(1),
+                y: self.c(0),
+                z: self.c(0),
+            },
+            Point::Affine { x, y } => LopezDahabPoint { x, y, z: self.c(1) },
+        }
+    }
+
+    pub fn lopez_dahab_affine(&self, p: &LopezDahabPoint<F>) -> Result<Point<F>, Error> {
+        if p.z == self.c(0) {
+            return Ok(Point::Infinity);
+        }
+        let z_inv = p.z.inv()?;
+        Ok(Point::new(p.x * z_inv, p.y * z_inv * z_inv))
+    }
+
+    // Z3 = X^2 Z^2, X3 = X^4 + a6 Z^4, Y3 = a6 Z^4 Z3 + X3 (a2 Z3 + Y^2 + a6 Z^4): 4M + 5S
+    // and no inversion. A point with X = 0 has order 2 and doubles to Z3 = 0.
+    pub fn lopez_dahab_double(&self, p: &LopezDahabPoint<F>) -> Result<LopezDahabPoint<F>, Error> {
+        if !self.is_lopez_dahab_form() {
+            return Err(Error::UnsupportedCurve);
+        }
+        let xx = p.x * p.x;
+        let zz = p.z * p.z;
+        let a6_z4 = self.a6 * zz * zz;
+        let z3 = xx * zz;
+        let x3 = xx * xx + a6_z4;
+        let y3 = a6_z4 * z3 + x3 * (self.a2 * z3 + p.y * p.y + a6_z4);
+        Ok(LopezDahabPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        })
+    }
+
+    // Mixed addition of an affine Q (Al-Daoud et al., Guide to ECC eq. 3.25): 8M + 5S
+    pub fn lopez_dahab_add_mixed(
+        &self,
+        p: &LopezDahabPoint<F>,
+        q: &(F, F),
+    ) -> Result<LopezDahabPoint<F>, Error> {
+        if !self.is_lopez_dahab_form() {
+            return Err(Error::UnsupportedCurve);
+        }
+        let (x2, y2) = *q;
+        if p.z == self.c(0) {
+            return Ok(self.lopez_dahab_point(&Point::new(x2, y2)));
+        }
+
+        let zz = p.z * p.z;
+        let a = y2 * zz + p.y;
+        let b = x2 * p.z + p.x;
+        if b == self.c(0) {
+            // Same x: P = Q or P = -Q
+            return if a == self.c(0) {
+                self.lopez_dahab_double(p)
+            } else {
+                Ok(self.lopez_dahab_point(&Point::Infinity))
+            };
+        }
+
+        let c = p.z * b;
+        let d = b * b * (c + self.a2 * zz);
+        let z3 = c * c;
+        let e = a * c;
+        let x3 = a * a + d + e;
+        let f = x3 + x2 * z3;
+        let g = (x2 + y2) * z3 * z3;
+        Ok(LopezDahabPoint {
+            x: x3,
+            y: (e + z3) * f + g,
+            z: z3,
+        })
+    }
+
+    pub fn lopez_dahab_scalar_mul(&self, k: u64, p: &Point<F>) -> Result<Point<F>, Error> {
+        let Point::Affine { x, y } = *p else {
+            return Ok(Point::Infinity);
+        };
+        let q = (x, y);
+        let mut result = self.lopez_dahab_point(&Point::Infinity);
+        for i in (0..64 - k.leading_zeros()).rev() {
+            result = self.lopez_dahab_double(&result)?;
+            if k >> i & 1 == 1 {
+                result = self.lopez_dahab_add_mixed(&result, &q)?;
+            }
+        }
+        self.lopez_dahab_affine(&result)
+    }
+}
+
+impl<F: Field> ChangeOfVariables<F> {
+    // E -> E': x' = (x - r) / u^2, y' = (y - s(x - r) - t) / u^3
+    pub fn map(&self, p: &Point<F>) -> Result<Point<F>, Error> {
+        let Point::Affine { x, y } = *p else {
+            return Ok(Point::Infinity);
+        };
+        let u_inv = self.u.inv()?;
+        let x_shifted = x - self.r;
+        Ok(Point::new(
+            x_shifted * u_inv.pow(2),
+            (y - self.s * x_shifted - self.t) * u_inv.pow(3),
+        ))
+    }
+
+    // E' -> E: x = u^2 x' + r, y = u^3 y' + s u^2 x' + t
+    pub fn map_inv(&self, p: &Point<F>) -> Point<F> {
+        let Point::Affine { x, y } = *p else {
+            return Point::Infinity;
+        };
+        let u2 = self.u * self.u;
+        Point::new(u2 * x + self.r, u2 * self.u * y + self.s * u2 * x + self.t)
+    }
+}
+
+// Sanity checks of the invariants and the group law on E(F)
+fn check_curve<F: Field>(curve: &WeierstrassCurve<F>) -> Vec<Point<F>> {
+    // 4 b8 = b2 b6 - b4^2 and 1728 discriminant = c4^3 - c6^2 hold in any characteristic
+    let (b2, b4, b6, b8) = (curve.b2(), curve.b4(), curve.b6(), curve.b8());
+    assert_eq!(curve.c(4) * b8, b2 * b6 - b4 * b4);
+    let (c4, c6) = (curve.c4(), curve.c6());
+    assert_eq!(curve.c(1728) * curve.discriminant(), c4 * c4 * c4 - c6 * c6);
+    assert_ne!(curve.discriminant(), curve.c(0));
+
+    let points = curve.points();
+    let order = points.len() as u64;
+    for p1 in &points {
+        assert!(curve.is_on_curve(&curve.negate(p1)));
+        assert_eq!(curve.add(p1, &curve.negate(p1)), Ok(Point::Infinity));
+        assert_eq!(curve.scalar_mul(order, p1), Ok(Point::Infinity));
+        for compressed in [false, true] {
+            let bytes = curve.encode_point(p1, compressed);
+            assert_eq!(curve.decode_point(&bytes), Ok(*p1));
+        }
+        for p2 in points.iter().step_by(3) {
+            let sum = curve.add(p1, p2).unwrap();
+            assert!(curve.is_on_curve(&sum));
+            assert_eq!(Ok(sum), curve.add(p2, p1));
+        }
+    }
+    for p1 in points.iter().step_by(2) {
+        for p2 in points.iter().step_by(3) {
+            for p3 in points.iter().step_by(5) {
+                assert_eq!(
+                    curve.add(&curve.add(p1, p2).unwrap(), p3),
+                    curve.add(p1, &curve.add(p2, p3).unwrap())
+                );
+            }
+        }
+    }
+    points
+}
+
+// Lopez-Dahab doubling, mixed addition and scalar multiplication against the affine law
+fn check_lopez_dahab<F: Field>(curve: &WeierstrassCurve<F>, points: &[Point<F>]) {
+    for p in points {
+        let ld = curve.lopez_dahab_point(p);
+        assert_eq!(
+            curve.lopez_dahab_affine(&curve.lopez_dahab_double(&ld).unwrap()),
+            curve.add(p, p)
+        );
+        for q in points.iter().skip(1).step_by(3) {
+            let (&x, &y) = q.coordinates().unwrap();
+            assert_eq!(
+                curve.lopez_dahab_affine(&curve.lopez_dahab_add_mixed(&ld, &(x, y)).unwrap()),
+                curve.add(p, q)
+            );
+        }
+    }
+    for p in points.iter().step_by(7) {
+        for k in [2, 3, 5, 17, 100, 1000] {
+            assert_eq!(curve.lopez_dahab_scalar_mul(k, p), curve.scalar_mul(k, p));
+        }
+    }
+}
+
+// The short form has the same invariants, and the change of variables is a group isomorphism
+fn check_short_form<F: Field>(curve: &WeierstrassCurve<F>, points: &[Point<F>]) {
+    let (short, change) = curve.short_weierstrass().unwrap();
+    let zero = curve.c(0);
+    assert!(short.a1 == zero && short.a2 == zero && short.a3 == zero);
+    assert_eq!(short.discriminant(), curve.discriminant());
+    assert_eq!(short.j_invariant(), curve.j_invariant());
+    println!(
+        "  short form y^2 = x^3 + {}x + {}, j = {}",
+        short.a4,
+        short.a6,
+        short.j_invariant().unwrap()
+    );
+
+    for p in points {
+        let image = change.map(p).unwrap();
+        assert!(short.is_on_curve(&image));
+        assert_eq!(change.map_inv(&image), *p);
+    }
+    assert_eq!(short.points().len(), points.len());
+    for p1 in points.iter().step_by(2) {
+        for p2 in points.iter().step_by(3) {
+            assert_eq!(
+                change.map(&curve.add(p1, p2).unwrap()),
+                short.add(&change.map(p1).unwrap(), &change.map(p2).unwrap())
+            );
+        }
+    }
+}
+
+pub fn run() {
+    // Cremona 11a1: y^2 + y = x^3 - x^2 - 10x - 20 with good reduction away from 11, and
+    // #E(Fp) = p + 1 - a_p where a_p are the coefficients of q prod (1 - q^n)^2 (1 - q^11n)^2
+    let a = [0, -1, 1, -10, -20];
+    for (p, a_p) in [(2, -2), (3, -1), (5, 1), (7, -2), (13, 4)] {
+        let curve = WeierstrassCurve::try_from_coefficients(Fp::new(0, p), a).unwrap();
+        let points = check_curve(&curve);
+        println!(
+            "11a1 over F{}: #E = {}, j = {}",
+            p,
+            points.len(),
+            curve.j_invariant().unwrap()
+        );
+        assert_eq!(points.len() as i64, p as i64 + 1 - a_p);
+        // 11a1 has a rational 5-torsion point, which survives reduction
+        assert!(points.len().is_multiple_of(5));
+
+        if p > 3 {
+            check_short_form(&curve, &points);
+        } else {
+            assert!(curve.short_weierstrass().is_err());
+        }
+    }
+
+    // 11 is the conductor, where the reduction is singular. Over F13, #E = 10 and the
+    // rational 5-torsion gives four affine points in the subgroup of order 5.
+    assert_eq!(
+        WeierstrassCurve::try_from_coefficients(Fp::new(0, 11), a),
+        Err(Error::SingularCurve)
+    );
+    let curve = WeierstrassCurve::try_from_coefficients(Fp::new(0, 13), a).unwrap();
+    let five_torsion = curve
+        .points()
+        .iter()
+        .filter_map(Point::coordinates)
+        .filter(|&(&x, &y)| curve.subgroup_point(x, y, 5).is_ok())
+        .count();
+    assert_eq!(five_torsion, 4);
+    // At x = 0 the equation is y^2 + y = -20, i.e. (y - 2)(y + 3) = 0
+    let p = curve.point(Fp::new(0, 13), Fp::new(2, 13)).unwrap();
+    assert_eq!(
+        curve.point(Fp::new(0, 13), Fp::new(3, 13)),
+        Err(Error::NotOnCurve)
+    );
+
+    // SEC 1 with a3 = 1: (0, 2) and -(0, 2) = (0, 10) have y + 1/2 = 9 and 4, so (0, 2) is
+    // the odd one. Elements of F13 take one byte, and 13 itself is not one.
+    assert_eq!(curve.encode_point(&p, true), [0x03, 0x00]);
+    assert_eq!(curve.encode_point(&p, false), [0x04, 0x00, 0x02]);
+    assert_eq!(curve.decode_point(&[0x02, 0x00]), Ok(curve.negate(&p)));
+    assert_eq!(
+        curve.decode_point(&[0x04, 0x00, 0x0d]),
+        Err(Error::InvalidEncoding)
+    );
+
+    // Zero has no inverse, and over Z/91 = Z/7 x Z/13 Fermat's 2^89 is not an inverse of 2
+    assert_eq!(Fp::new(0, 13).inv(), Err(Error::NotInvertible));
+    assert_eq!(Fp::new(2, 91).inv(), Err(Error::CompositeModulus));
+
+    // The largest 64-bit prime, where sums and products of elements overflow a u64
+    let p = u64::MAX - 58;
+    let minus_one = Fp::new(-1, p);
+    assert_eq!(minus_one.value, p - 1);
+    assert_eq!(minus_one * minus_one, minus_one.one());
+    assert_eq!(minus_one + minus_one, Fp::new(-2, p));
+    assert_eq!(minus_one - minus_one.one(), Fp::new(-2, p));
+    assert_eq!(
+        Fp::new(2, p).inv().unwrap() * Fp::new(2, p),
+        minus_one.one()
+    );
+    assert_eq!(Fp::new(4, p).sqrt().map(|r| r * r), Some(Fp::new(4, p)));
+
+    // A random long form over F97
+    let curve = WeierstrassCurve::from_coefficients(Fp::new(0, 97), [5, 17, 42, 3, 88]);
+    let points = check_curve(&curve);
+    println!(
+        "y^2 + 5xy + 42y = x^3 + 17x^2 + 3x + 88 over F97: #E = {}",
+        points.len()
+    );
+    check_short_form(&curve, &points);
+
+    // The Koblitz curve y^2 + xy = x^3 + 1 has #E(F2) = 4, trace t = -1, so over F16 its
+    // order is 17 - V4 with V0 = 2, V1 = t and Vm = t V(m-1) - 2 V(m-2), i.e. 17 - 1 = 16
+    let field = F2m::new(0, 0b10011); // F2[z]/(z^4 + z + 1)
+    let koblitz = WeierstrassCurve::from_coefficients(field, [1, 0, 0, 0, 1]);
+    let points = check_curve(&koblitz);
+    println!(
+        "y^2 + xy = x^3 + 1 over F16: #E = {}, j = {}",
+        points.len(),
+        koblitz.j_invariant().unwrap()
+    );
+    assert_eq!(points.len(), 16);
+    assert_eq!(
+        koblitz.short_weierstrass().err(),
+        Some(Error::NotInvertible)
+    );
+    check_lopez_dahab(&koblitz, &points);
+    // The formulas only hold on y^2 + xy = x^3 + a2 x^2 + a6 in characteristic 2
+    let odd = WeierstrassCurve::from_coefficients(Fp::new(0, 13), [0, -1, 1, -10, -20]);
+    let p = odd.points()[1];
+    let ld = odd.lopez_dahab_point(&p);
+    assert_eq!(
+        odd.lopez_dahab_double(&ld).err(),
+        Some(Error::UnsupportedCurve)
+    );
+    assert_eq!(
+        odd.lopez_dahab_scalar_mul(3, &p),
+        Err(Error::UnsupportedCurve)
+    );
+
+    // A random ordinary curve y^2 + xy = x^3 + z^5 x^2 + (z^7 + z) over F256
+    let field256 = F2m::new(0, 0x11b); // F2[z]/(z^8 + z^4 + z^3 + z + 1)
+    let curve = WeierstrassCurve::new(
+        field256.one(),
+        F2m::new(0b100000, 0x11b),
+        field256.zero(),
+        field256.zero(),
+        F2m::new(0b10000010, 0x11b),
+    );
+    let points = check_curve(&curve);
+    check_lopez_dahab(&curve, &points);
+    println!(
+        "y^2 + xy = x^3 + z^5 x^2 + z^7 + z over F256: #E = {}, Lopez-Dahab arithmetic agrees",
+        points.len()
+    );
+
+    // y^2 + y = x^3 is supersingular in characteristic 2: j = 0, #E(F2) = 3 so t = 0 and
+    // V4 = 8, giving #E(F16) = 17 - 8
+    let supersingular = WeierstrassCurve::from_coefficients(field, [0, 0, 1, 0, 0]);
+    let points = check_curve(&supersingular);
+    assert_eq!(supersingular.j_invariant(), Ok(field.zero()));
+    assert_eq!(points.len(), 9);
+}